@@ -0,0 +1,124 @@
+// movegen.rs
+// Move generation benchmarks: `cargo bench --bench movegen`
+use chess::engine::movepick::MovePicker;
+use chess::engine::{Board, Color};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+// The start position, a middlegame full of open lines, and an endgame of long slider moves
+const POSITIONS: [(&str, &str); 3] = [
+    (
+        "start",
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    ),
+    (
+        "kiwipete",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    ),
+    ("endgame", "8/5k2/3q4/8/2B5/8/1R4Q1/6K1 w - - 0 1"),
+];
+
+fn legal_moves(c: &mut Criterion) {
+    for (name, fen) in POSITIONS {
+        let board = Board::from_fen(fen).unwrap();
+        c.bench_function(&format!("legal_moves/{}", name), |b| {
+            b.iter(|| black_box(&board).generate_all_legal_moves(board.side_to_move))
+        });
+    }
+}
+
+fn perft_3(c: &mut Criterion) {
+    for (name, fen) in POSITIONS {
+        let board = Board::from_fen(fen).unwrap();
+        c.bench_function(&format!("perft_3/{}", name), |b| {
+            b.iter(|| black_box(&board).perft(3))
+        });
+    }
+}
+
+// The standard perft(4) from the start position: 197 281 leaf nodes
+fn perft_4(c: &mut Criterion) {
+    let board = Board::new();
+    let mut group = c.benchmark_group("perft_4");
+    group.sample_size(10);
+    group.bench_function("start", |b| b.iter(|| black_box(&board).perft(4)));
+    group.finish();
+}
+
+// Knight and king moves and the attack scan, all of which read the precomputed attack tables
+fn attack_tables(c: &mut Criterion) {
+    let board = Board::new();
+    c.bench_function("knight_and_king_moves/start", |b| {
+        b.iter(|| {
+            [(0, 1), (0, 4), (0, 6), (7, 1), (7, 4), (7, 6)]
+                .iter()
+                .map(|&(row, col)| black_box(&board).generate_moves_for_piece(row, col).len())
+                .sum::<usize>()
+        })
+    });
+    c.bench_function("square_under_attack/start", |b| {
+        b.iter(|| {
+            (0..64)
+                .filter(|&square| {
+                    black_box(&board).is_square_under_attack(square / 8, square % 8, Color::White)
+                })
+                .count()
+        })
+    });
+}
+
+// The work the staged move picker does before a node that cuts off on its first capture,
+// against the work of trying every move
+fn move_picker(c: &mut Criterion) {
+    for (name, fen) in POSITIONS {
+        let board = Board::from_fen(fen).unwrap();
+        let color = board.side_to_move;
+        c.bench_function(&format!("move_picker_first/{}", name), |b| {
+            b.iter(|| MovePicker::new(color, None, [None; 2], Vec::new()).next(black_box(&board)))
+        });
+        c.bench_function(&format!("move_picker_all/{}", name), |b| {
+            b.iter(|| {
+                let mut picker = MovePicker::new(color, None, [None; 2], Vec::new());
+                let mut count = 0;
+                while picker.next(black_box(&board)).is_some() {
+                    count += 1;
+                }
+                count
+            })
+        });
+    }
+}
+
+// The GUI's check of a clicked move, for each pseudo-legal move of the side to move, so
+// every call gets as far as testing whether the king is left in check
+fn valid_moves(c: &mut Criterion) {
+    for (name, fen) in POSITIONS {
+        let board = Board::from_fen(fen).unwrap();
+        let moves: Vec<_> = (0..64)
+            .filter(|&square| {
+                board
+                    .piece_at(square / 8, square % 8)
+                    .is_some_and(|piece| piece.color == board.side_to_move)
+            })
+            .flat_map(|square| board.generate_moves_for_piece(square / 8, square % 8))
+            .collect();
+        c.bench_function(&format!("is_valid_move/{}", name), |b| {
+            b.iter(|| {
+                moves
+                    .iter()
+                    .filter(|m| black_box(&board).is_valid_move(m.from, m.to))
+                    .count()
+            })
+        });
+    }
+}
+
+criterion_group!(
+    benches,
+    legal_moves,
+    perft_3,
+    perft_4,
+    attack_tables,
+    move_picker,
+    valid_moves
+);
+criterion_main!(benches);
@@ -0,0 +1,80 @@
+// search.rs
+// Search benchmarks: `cargo bench --bench search`
+use chess::engine::{
+    evaluate_position, evaluate_position_from_scratch, improved_best_move_for_color, Board,
+    SearchLimits, SearchResult,
+};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+const POSITIONS: [(&str, &str); 3] = [
+    (
+        "start",
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    ),
+    (
+        "kiwipete",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    ),
+    ("rook_endgame", "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1"),
+];
+
+// Deepest depth the time-to-depth benchmark searches to
+const MAX_BENCH_DEPTH: u32 = 5;
+
+fn search(board: &Board, depth: u32) -> SearchResult {
+    improved_best_move_for_color(
+        black_box(board),
+        board.side_to_move,
+        &SearchLimits::depth(depth),
+        &[],
+    )
+    .unwrap()
+}
+
+// A fixed-depth search, so the node count stays the same and the time per run tracks NPS.
+// The nodes are the throughput, so the report gives nodes per second directly.
+fn fixed_depth(c: &mut Criterion) {
+    let mut group = c.benchmark_group("search_depth_4");
+    group.sample_size(10);
+    for (name, fen) in POSITIONS {
+        let board = Board::from_fen(fen).unwrap();
+        group.throughput(Throughput::Elements(search(&board, 4).nodes));
+        group.bench_function(name, |b| b.iter(|| search(&board, 4)));
+    }
+    group.finish();
+}
+
+// How long each position takes to reach each depth, to show where a change starts to pay off
+// or cost
+fn time_to_depth(c: &mut Criterion) {
+    let mut group = c.benchmark_group("time_to_depth");
+    group.sample_size(10);
+    for (name, fen) in POSITIONS {
+        let board = Board::from_fen(fen).unwrap();
+        for depth in 1..=MAX_BENCH_DEPTH {
+            group.throughput(Throughput::Elements(search(&board, depth).nodes));
+            group.bench_with_input(BenchmarkId::new(name, depth), &depth, |b, &depth| {
+                b.iter(|| search(&board, depth))
+            });
+        }
+    }
+    group.finish();
+}
+
+// Leaf evaluation from the sums the board keeps, against summing every square again
+fn evaluation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("evaluation");
+    for (name, fen) in POSITIONS {
+        let board = Board::from_fen(fen).unwrap();
+        group.bench_function(format!("{}/incremental", name), |b| {
+            b.iter(|| evaluate_position(black_box(&board)))
+        });
+        group.bench_function(format!("{}/from_scratch", name), |b| {
+            b.iter(|| evaluate_position_from_scratch(black_box(&board)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, fixed_depth, time_to_depth, evaluation);
+criterion_main!(benches);
@@ -0,0 +1,8 @@
+#!/usr/bin/env sh
+# Builds the browser version of the game into dist/ (see WASM.md)
+set -e
+
+rustup target add wasm32-unknown-unknown
+command -v trunk >/dev/null 2>&1 || cargo install --locked trunk
+
+trunk build --release "$@"
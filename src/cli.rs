@@ -0,0 +1,151 @@
+// cli.rs
+// A game against the engine in the terminal, for `chess --cli`: moves are read one per line
+// in coordinate or standard algebraic notation, the board is printed after every move, and
+// the engine replies for the other side. Nothing here depends on the GUI.
+use crate::engine::{
+    improved_best_move_for_color, opposite_color, Board, Color, GameStatus, SearchLimits,
+};
+use std::io::{self, BufRead, Write};
+
+// The engine depth when none is given, the GUI's default difficulty
+pub const DEFAULT_DEPTH: u32 = 3;
+
+#[derive(Clone, Debug)]
+pub struct CliOptions {
+    pub player_color: Color, // The engine plays the other side
+    pub depth: u32,
+}
+
+impl Default for CliOptions {
+    fn default() -> Self {
+        CliOptions {
+            player_color: Color::White,
+            depth: DEFAULT_DEPTH,
+        }
+    }
+}
+
+impl CliOptions {
+    // Reads `--color white|black` and `--depth N` from the arguments after `--cli`
+    pub fn from_args(args: &[String]) -> Result<CliOptions, String> {
+        let mut options = CliOptions::default();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+            match arg.as_str() {
+                "--color" => {
+                    options.player_color = match value()?.as_str() {
+                        "white" => Color::White,
+                        "black" => Color::Black,
+                        other => return Err(format!("invalid colour '{}'", other)),
+                    }
+                }
+                "--depth" => {
+                    let depth = value()?;
+                    options.depth = depth
+                        .parse()
+                        .map_err(|_| format!("invalid depth '{}'", depth))?;
+                }
+                other => return Err(format!("unknown option '{}'", other)),
+            }
+        }
+        Ok(options)
+    }
+}
+
+pub const USAGE: &str = "usage: chess --cli [--color white|black] [--depth N]";
+
+// Plays one game from the start position, reading the player's moves from `input` until the
+// game ends, `quit` is entered or the input runs out
+pub fn run(
+    input: impl BufRead,
+    output: &mut impl Write,
+    options: &CliOptions,
+) -> Result<(), io::Error> {
+    let mut board = Board::new();
+    let mut history = vec![board.zobrist_key()]; // Every position of the game, for repetitions
+    let mut lines = input.lines();
+    let limits = SearchLimits::depth(options.depth);
+    writeln!(
+        output,
+        "You play {}. Enter moves as e2e4 or Nf3, or quit to stop.",
+        color_name(options.player_color)
+    )?;
+    writeln!(output, "{}", board)?;
+
+    loop {
+        if let Some(result) = game_result(&board, &history) {
+            writeln!(output, "{}", result)?;
+            return Ok(());
+        }
+        let mover = board.side_to_move;
+        let m = if mover == options.player_color {
+            write!(output, "{} to move: ", color_name(mover))?;
+            output.flush()?;
+            let line = match lines.next() {
+                Some(line) => line?,
+                None => return Ok(()),
+            };
+            let text = line.trim();
+            if text == "quit" {
+                return Ok(());
+            }
+            match board.parse_move(text) {
+                Ok(m) => m,
+                Err(error) => {
+                    writeln!(output, "{}", error)?;
+                    continue;
+                }
+            }
+        } else {
+            match improved_best_move_for_color(&board, mover, &limits, &history) {
+                Some(result) => result.best_move,
+                None => return Ok(()),
+            }
+        };
+
+        writeln!(
+            output,
+            "{} plays {}",
+            color_name(mover),
+            board.move_to_san(m)
+        )?;
+        board.apply_move(m);
+        history.push(board.zobrist_key());
+        writeln!(output, "{}", board)?;
+        if board.game_status(board.side_to_move) == GameStatus::Check {
+            writeln!(output, "Check!")?;
+        }
+    }
+}
+
+// How the game on `board` has ended, or None while it goes on
+fn game_result(board: &Board, history: &[u64]) -> Option<String> {
+    let mover = board.side_to_move;
+    match board.game_status(mover) {
+        GameStatus::Checkmate => {
+            return Some(format!(
+                "Checkmate. {} wins.",
+                color_name(opposite_color(mover))
+            ))
+        }
+        GameStatus::Stalemate => return Some("Stalemate. The game is drawn.".to_string()),
+        GameStatus::Ongoing | GameStatus::Check => {}
+    }
+    if board.is_insufficient_material() {
+        Some("Draw by insufficient material.".to_string())
+    } else if board.half_move_clock >= 100 {
+        Some("Draw by the fifty-move rule.".to_string())
+    } else if board.is_threefold_repetition(history) {
+        Some("Draw by threefold repetition.".to_string())
+    } else {
+        None
+    }
+}
+
+fn color_name(color: Color) -> &'static str {
+    match color {
+        Color::White => "White",
+        Color::Black => "Black",
+    }
+}
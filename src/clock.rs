@@ -0,0 +1,25 @@
+// clock.rs
+// Time management shared by the UCI and XBoard front ends: how much of the clock to spend on
+// a move, and how deep to let a search go when a timer is what stops it
+use std::time::Duration;
+
+// Moves left in the time control assumed when the control does not say
+pub const ASSUMED_MOVES_TO_GO: u32 = 30;
+// Time kept back from each move's budget, for the protocol round trip
+pub const MOVE_OVERHEAD: Duration = Duration::from_millis(50);
+// How deep the engine searches when there is a clock to stop it
+pub const TIMED_SEARCH_DEPTH: u32 = 64;
+
+// An even share of the clock over the moves left in the control, plus most of the increment,
+// never more than is on the clock
+pub fn share_of_clock(clock: Duration, increment: Duration, moves_to_go: Option<u32>) -> Duration {
+    let moves_to_go = moves_to_go.unwrap_or(ASSUMED_MOVES_TO_GO).max(1);
+    less_overhead((clock / moves_to_go + increment * 3 / 4).min(clock))
+}
+
+// A move's budget less the protocol round trip, leaving at least a moment to search
+pub fn less_overhead(budget: Duration) -> Duration {
+    budget
+        .saturating_sub(MOVE_OVERHEAD)
+        .max(Duration::from_millis(10))
+}
@@ -1,8 +1,31 @@
 // engine.rs
+pub mod bitboard;
+pub mod book;
+pub mod movepick;
+#[cfg(feature = "nnue")]
+pub mod nnue;
+pub mod polyglot;
+mod tt;
+pub mod uci_client;
+
+use bitboard::{Bitboard, KING_ATTACKS, KNIGHT_ATTACKS};
+use movepick::MovePicker;
+use rand::rngs::StdRng;
+use rand::seq::IndexedRandom;
+use rand::{Rng, SeedableRng};
+#[cfg(not(target_arch = "wasm32"))]
 use rayon::prelude::*;
-use std::sync::{Arc, Mutex};
-
-#[derive(Clone, Copy, PartialEq, Debug)]
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+// The standard clock panics in the browser
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::{Duration, Instant};
+pub use tt::DEFAULT_HASH_MB;
+use tt::{Bound, TranspositionTable, TtEntry};
+#[cfg(target_arch = "wasm32")]
+use web_time::{Duration, Instant};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum Color {
     White,
     Black,
@@ -29,6 +52,31 @@ impl PieceType {
             PieceType::Pawn => "Pawn",
         }
     }
+
+    // The uppercase letter naming the piece in FEN and algebraic notation
+    pub fn letter(&self) -> char {
+        match *self {
+            PieceType::King => 'K',
+            PieceType::Queen => 'Q',
+            PieceType::Rook => 'R',
+            PieceType::Knight => 'N',
+            PieceType::Bishop => 'B',
+            PieceType::Pawn => 'P',
+        }
+    }
+
+    // Parses a piece letter in either case
+    pub fn from_letter(letter: char) -> Option<PieceType> {
+        match letter.to_ascii_uppercase() {
+            'K' => Some(PieceType::King),
+            'Q' => Some(PieceType::Queen),
+            'R' => Some(PieceType::Rook),
+            'N' => Some(PieceType::Knight),
+            'B' => Some(PieceType::Bishop),
+            'P' => Some(PieceType::Pawn),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -39,122 +87,975 @@ pub struct Piece {
 
 pub const EMPTY: Option<Piece> = None;
 
+// A move from one (row, col) square to another. `promotion` is the piece a pawn reaching the
+// last rank becomes, and None for every other move.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Move {
+    pub from: (usize, usize),
+    pub to: (usize, usize),
+    pub promotion: Option<PieceType>,
+}
+
+impl Move {
+    pub fn new(from: (usize, usize), to: (usize, usize)) -> Move {
+        Move {
+            from,
+            to,
+            promotion: None,
+        }
+    }
+
+    pub fn promoting(from: (usize, usize), to: (usize, usize), kind: PieceType) -> Move {
+        Move {
+            from,
+            to,
+            promotion: Some(kind),
+        }
+    }
+}
+
+// The pieces a pawn may promote to, most useful first
+pub const PROMOTION_PIECES: [PieceType; 4] = [
+    PieceType::Queen,
+    PieceType::Knight,
+    PieceType::Rook,
+    PieceType::Bishop,
+];
+
+// What a null move changed beyond the side to move, see `Board::make_null_move`
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct NullMove {
+    en_passant_target: Option<(usize, usize)>,
+    half_move_clock: u32,
+}
+
+// Why a move written as text could not be read in a position
+#[derive(Clone, PartialEq, Debug)]
+pub enum ChessError {
+    InvalidNotation(String), // Text that is not a move in the notation expected
+    IllegalMove(String),     // A well-formed move that cannot be played in the position
+    AmbiguousMove(String),   // A SAN move more than one legal move fits
+}
+
+impl std::fmt::Display for ChessError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ChessError::InvalidNotation(text) => write!(f, "unrecognised move '{}'", text),
+            ChessError::IllegalMove(text) => write!(f, "illegal move '{}'", text),
+            ChessError::AmbiguousMove(text) => write!(f, "ambiguous move '{}'", text),
+        }
+    }
+}
+
+// Why a move between two squares cannot be played, see `Board::validate_move`
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MoveError {
+    OutOfBounds,        // A square off the board
+    NoPieceAtSource,    // Nothing stands on the square moved from
+    WrongColor,         // The piece belongs to the side not to move
+    OwnPieceOnTarget,   // The target holds a piece of the mover's own side
+    NotAPieceMove,      // The piece never moves that way, or a pawn has nothing to capture
+    BlockedPath,        // The piece moves that way, but something stands in between
+    NoCastlingRights,   // The King or that Rook has moved, so castling there is gone
+    CastleThroughCheck, // Castling out of, through or into check
+    LeavesKingInCheck,  // The move itself is fine, but it leaves the mover's King attacked
+}
+
+impl std::fmt::Display for MoveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(match self {
+            MoveError::OutOfBounds => "that square is off the board",
+            MoveError::NoPieceAtSource => "there is no piece there",
+            MoveError::WrongColor => "that piece belongs to the other side",
+            MoveError::OwnPieceOnTarget => "a piece cannot capture its own side",
+            MoveError::NotAPieceMove => "that piece does not move that way",
+            MoveError::BlockedPath => "the way there is blocked",
+            MoveError::NoCastlingRights => "castling that way is no longer allowed",
+            MoveError::CastleThroughCheck => "the King cannot castle out of, through or into check",
+            MoveError::LeavesKingInCheck => "that would leave the King in check",
+        })
+    }
+}
+
+// How things stand for the side to move, from one look at its legal moves
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum GameStatus {
+    Ongoing,   // Not in check, with a legal move
+    Check,     // In check, with a legal move out of it
+    Checkmate, // In check, without a legal move
+    Stalemate, // Not in check, without a legal move
+}
+
+impl GameStatus {
+    fn new(in_check: bool, has_moves: bool) -> Self {
+        match (in_check, has_moves) {
+            (false, true) => GameStatus::Ongoing,
+            (true, true) => GameStatus::Check,
+            (true, false) => GameStatus::Checkmate,
+            (false, false) => GameStatus::Stalemate,
+        }
+    }
+}
+
+// Adds a pawn move, as one move per promotion piece when it reaches the last rank
+fn push_pawn_move(moves: &mut Vec<Move>, from: (usize, usize), to: (usize, usize)) {
+    if to.0 == 0 || to.0 == 7 {
+        moves.extend(
+            PROMOTION_PIECES
+                .iter()
+                .map(|&kind| Move::promoting(from, to, kind)),
+        );
+    } else {
+        moves.push(Move::new(from, to));
+    }
+}
+
+// L-shaped jumps of a knight, as (row, col) offsets
+const KNIGHT_OFFSETS: [(isize, isize); 8] = [
+    (2, 1),
+    (1, 2),
+    (-1, 2),
+    (-2, 1),
+    (-2, -1),
+    (-1, -2),
+    (1, -2),
+    (2, -1),
+];
+
+// Single steps of a king, as (row, col) offsets
+const KING_OFFSETS: [(isize, isize); 8] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, -1),
+    (0, 1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+
+// A step along a rank, file or diagonal, as (row change, col change)
+pub type Direction = (isize, isize);
+
+// Ray directions of the sliding pieces, with the piece besides the queen that moves along each
+const SLIDING_RAYS: [(Direction, PieceType); 8] = [
+    ((1, 0), PieceType::Rook),
+    ((-1, 0), PieceType::Rook),
+    ((0, 1), PieceType::Rook),
+    ((0, -1), PieceType::Rook),
+    ((1, 1), PieceType::Bishop),
+    ((1, -1), PieceType::Bishop),
+    ((-1, 1), PieceType::Bishop),
+    ((-1, -1), PieceType::Bishop),
+];
+
+// Which pseudo-legal moves a generator produces. Captures include en passant; everything
+// else, castling and promotions by a push among them, is quiet.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum MoveKinds {
+    All,
+    Captures,
+    Quiets,
+}
+
 #[derive(Clone, Debug)]
 pub struct Board {
-    pub squares: [[Option<Piece>; 8]; 8],
+    // The pieces are kept both square by square and as bitboards; `set_piece` updates both
+    squares: [[Option<Piece>; 8]; 8],
+    pieces: [Bitboard; 12], // One bitboard per piece, indexed like the Zobrist piece table
+    occupied: [Bitboard; 2], // All White pieces, then all Black pieces
+    // The evaluation's sums of piece values and of piece-square bonuses, White's then Black's,
+    // kept up to date by `set_piece` so a leaf needs no scan of the board
+    material: [i32; 2],
+    placement: [i32; 2],
+    // The network's first-layer sums for the pieces on the board, its accumulator, kept up to
+    // date by `set_piece` too; empty if no network was set when the board was made
+    #[cfg(feature = "nnue")]
+    accumulator: Vec<f32>,
     pub half_move_clock: u32, // Tracks moves since last pawn move or capture
     pub white_castle_possible: (bool, bool),
     pub black_castle_possible: (bool, bool),
     pub en_passant_target: Option<(usize, usize)>,
+    pub side_to_move: Color,
+    pub full_move_number: u32, // Starts at 1 and increases after each Black move
+    // The files the king and the castling rooks start on, the queenside rook's first: e, a and
+    // h, except in Chess960, where castling is written as the king taking its own rook
+    king_col: usize,
+    rook_cols: (usize, usize),
+    pub chess960_id: Option<u16>, // The Chess960 starting position played from, 0 to 959
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Draws the board as text, rank 8 at the top, with White's pieces in uppercase, Black's in
+// lowercase and empty squares as dots:
+//
+// 8 r n b q k b n r
+// ...
+// 1 R N B Q K B N R
+//   a b c d e f g h
+impl std::fmt::Display for Board {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for row in (0..8).rev() {
+            write!(f, "{}", row + 1)?;
+            for col in 0..8 {
+                let symbol = match self.squares[row][col] {
+                    Some(piece) if piece.color == Color::White => piece.kind.letter(),
+                    Some(piece) => piece.kind.letter().to_ascii_lowercase(),
+                    None => '.',
+                };
+                write!(f, " {}", symbol)?;
+            }
+            writeln!(f)?;
+        }
+        write!(f, "  a b c d e f g h")
+    }
 }
 
 impl Board {
-    pub fn new() -> Self {
-        let mut board = Board {
+    // A board with no pieces and no castling rights, White to move
+    pub fn empty() -> Self {
+        Board {
             squares: [[EMPTY; 8]; 8],
+            pieces: [0; 12],
+            occupied: [0; 2],
+            material: [0; 2],
+            placement: [0; 2],
+            #[cfg(feature = "nnue")]
+            accumulator: nnue::network()
+                .map_or_else(Vec::new, nnue::NnueEvaluator::empty_accumulator),
             half_move_clock: 0,
+            white_castle_possible: (false, false),
+            black_castle_possible: (false, false),
+            en_passant_target: None,
+            side_to_move: Color::White,
+            full_move_number: 1,
+            king_col: 4,
+            rook_cols: (0, 7),
+            chess960_id: None,
+        }
+    }
+
+    pub fn new() -> Self {
+        let mut board = Board {
             white_castle_possible: (true, true),
             black_castle_possible: (true, true),
-            en_passant_target: None,
+            ..Board::empty()
         };
 
         // Initialize board with pieces (only a few for brevity)
         for col in 0..8 {
-            board.squares[1][col] = Some(Piece {
+            board.set_piece(
+                1,
+                col,
+                Some(Piece {
+                    color: Color::White,
+                    kind: PieceType::Pawn,
+                }),
+            );
+            board.set_piece(
+                6,
+                col,
+                Some(Piece {
+                    color: Color::Black,
+                    kind: PieceType::Pawn,
+                }),
+            );
+        }
+
+        board.set_piece(
+            0,
+            4,
+            Some(Piece {
                 color: Color::White,
-                kind: PieceType::Pawn,
-            });
-            board.squares[6][col] = Some(Piece {
+                kind: PieceType::King,
+            }),
+        );
+        board.set_piece(
+            7,
+            4,
+            Some(Piece {
                 color: Color::Black,
-                kind: PieceType::Pawn,
-            });
+                kind: PieceType::King,
+            }),
+        );
+
+        board.set_piece(
+            0,
+            3,
+            Some(Piece {
+                color: Color::White,
+                kind: PieceType::Queen,
+            }),
+        );
+        board.set_piece(
+            7,
+            3,
+            Some(Piece {
+                color: Color::Black,
+                kind: PieceType::Queen,
+            }),
+        );
+
+        board.set_piece(
+            0,
+            0,
+            Some(Piece {
+                color: Color::White,
+                kind: PieceType::Rook,
+            }),
+        );
+        board.set_piece(
+            7,
+            0,
+            Some(Piece {
+                color: Color::Black,
+                kind: PieceType::Rook,
+            }),
+        );
+
+        board.set_piece(
+            0,
+            7,
+            Some(Piece {
+                color: Color::White,
+                kind: PieceType::Rook,
+            }),
+        );
+        board.set_piece(
+            7,
+            7,
+            Some(Piece {
+                color: Color::Black,
+                kind: PieceType::Rook,
+            }),
+        );
+
+        board.set_piece(
+            0,
+            1,
+            Some(Piece {
+                color: Color::White,
+                kind: PieceType::Knight,
+            }),
+        );
+        board.set_piece(
+            7,
+            1,
+            Some(Piece {
+                color: Color::Black,
+                kind: PieceType::Knight,
+            }),
+        );
+
+        board.set_piece(
+            0,
+            6,
+            Some(Piece {
+                color: Color::White,
+                kind: PieceType::Knight,
+            }),
+        );
+        board.set_piece(
+            7,
+            6,
+            Some(Piece {
+                color: Color::Black,
+                kind: PieceType::Knight,
+            }),
+        );
+
+        board.set_piece(
+            0,
+            2,
+            Some(Piece {
+                color: Color::White,
+                kind: PieceType::Bishop,
+            }),
+        );
+        board.set_piece(
+            7,
+            2,
+            Some(Piece {
+                color: Color::Black,
+                kind: PieceType::Bishop,
+            }),
+        );
+
+        board.set_piece(
+            0,
+            5,
+            Some(Piece {
+                color: Color::White,
+                kind: PieceType::Bishop,
+            }),
+        );
+        board.set_piece(
+            7,
+            5,
+            Some(Piece {
+                color: Color::Black,
+                kind: PieceType::Bishop,
+            }),
+        );
+
+        board
+    }
+
+    // One of the 960 Chess960 starting positions, numbered as Scharnagl numbers them, 518 being
+    // the standard one: the pawns as usual and the pieces shuffled behind them, with the
+    // bishops on opposite colours and the king between the rooks, Black's mirroring White's.
+    // Numbers from 960 up wrap around.
+    pub fn new_chess960(id: u16) -> Self {
+        // Puts `kind` on the `nth` square of the back rank still empty
+        fn place(back_rank: &mut [Option<PieceType>; 8], kind: PieceType, nth: usize) {
+            let col = (0..8).filter(|&col| back_rank[col].is_none()).nth(nth);
+            back_rank[col.expect("the back rank has room")] = Some(kind);
+        }
+        // The two of the five squares left that the knights take, for each remaining number
+        const KNIGHTS: [(usize, usize); 10] = [
+            (0, 1),
+            (0, 2),
+            (0, 3),
+            (0, 4),
+            (1, 2),
+            (1, 3),
+            (1, 4),
+            (2, 3),
+            (2, 4),
+            (3, 4),
+        ];
+
+        let id = id % 960;
+        let mut n = id as usize;
+        let mut back_rank = [None; 8];
+        // The bishops on the light squares (b, d, f, h) and the dark ones (a, c, e, g), then
+        // the queen and the knights, and the rook, king and rook on the three squares left
+        back_rank[n % 4 * 2 + 1] = Some(PieceType::Bishop);
+        n /= 4;
+        back_rank[n % 4 * 2] = Some(PieceType::Bishop);
+        n /= 4;
+        place(&mut back_rank, PieceType::Queen, n % 6);
+        let (first, second) = KNIGHTS[n / 6];
+        place(&mut back_rank, PieceType::Knight, second);
+        place(&mut back_rank, PieceType::Knight, first);
+        for kind in [PieceType::Rook, PieceType::King, PieceType::Rook] {
+            place(&mut back_rank, kind, 0);
         }
 
-        board.squares[0][4] = Some(Piece {
-            color: Color::White,
-            kind: PieceType::King,
-        });
-        board.squares[7][4] = Some(Piece {
-            color: Color::Black,
-            kind: PieceType::King,
-        });
+        let cols_of = |kind| (0..8).filter(move |&col| back_rank[col] == Some(kind));
+        let mut rooks = cols_of(PieceType::Rook);
+        let mut board = Board {
+            white_castle_possible: (true, true),
+            black_castle_possible: (true, true),
+            king_col: cols_of(PieceType::King).next().expect("a king was placed"),
+            rook_cols: (rooks.next().unwrap(), rooks.next().unwrap()),
+            chess960_id: Some(id),
+            ..Board::empty()
+        };
+        for (col, kind) in back_rank.into_iter().enumerate() {
+            for (color, pawn_row, piece_row) in [(Color::White, 1, 0), (Color::Black, 6, 7)] {
+                board.set_piece(
+                    pawn_row,
+                    col,
+                    Some(Piece {
+                        color,
+                        kind: PieceType::Pawn,
+                    }),
+                );
+                board.set_piece(piece_row, col, kind.map(|kind| Piece { color, kind }));
+            }
+        }
+        board
+    }
 
-        board.squares[0][3] = Some(Piece {
-            color: Color::White,
-            kind: PieceType::Queen,
-        });
-        board.squares[7][3] = Some(Piece {
-            color: Color::Black,
-            kind: PieceType::Queen,
-        });
+    // Parses a position in Forsyth-Edwards Notation. The move counters may be left out, as in
+    // EPD, and default to 0 and 1.
+    pub fn from_fen(fen: &str) -> Result<Board, String> {
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        if fields.len() < 4 || fields.len() > 6 {
+            return Err(format!("expected 4 to 6 fields, found {}", fields.len()));
+        }
+        let mut board = Board::empty();
 
-        board.squares[0][0] = Some(Piece {
-            color: Color::White,
-            kind: PieceType::Rook,
-        });
-        board.squares[7][0] = Some(Piece {
-            color: Color::Black,
-            kind: PieceType::Rook,
-        });
+        let ranks: Vec<&str> = fields[0].split('/').collect();
+        if ranks.len() != 8 {
+            return Err(format!("expected 8 ranks, found {}", ranks.len()));
+        }
+        // FEN lists the ranks from the 8th down to the 1st
+        for (i, rank) in ranks.iter().enumerate() {
+            let row = 7 - i;
+            let mut col = 0;
+            for c in rank.chars() {
+                if let Some(empty) = c.to_digit(10) {
+                    col += empty as usize;
+                } else {
+                    let kind = PieceType::from_letter(c)
+                        .ok_or_else(|| format!("invalid piece '{}'", c))?;
+                    if col >= 8 {
+                        return Err(format!("rank {} is too long", row + 1));
+                    }
+                    let color = if c.is_ascii_uppercase() {
+                        Color::White
+                    } else {
+                        Color::Black
+                    };
+                    board.set_piece(row, col, Some(Piece { color, kind }));
+                    col += 1;
+                }
+            }
+            if col != 8 {
+                return Err(format!("rank {} does not have 8 squares", row + 1));
+            }
+        }
+        // The search and the game-over checks look for each side's king, so a position without
+        // exactly one of each cannot be played; `validate` checks the rest
+        for color in [Color::White, Color::Black] {
+            let kings = board
+                .bitboard(Piece {
+                    color,
+                    kind: PieceType::King,
+                })
+                .count_ones();
+            if kings != 1 {
+                return Err(format!("expected one {:?} king, found {}", color, kings));
+            }
+        }
 
-        board.squares[0][7] = Some(Piece {
-            color: Color::White,
-            kind: PieceType::Rook,
-        });
-        board.squares[7][7] = Some(Piece {
-            color: Color::Black,
-            kind: PieceType::Rook,
-        });
+        board.side_to_move = match fields[1] {
+            "w" => Color::White,
+            "b" => Color::Black,
+            other => return Err(format!("invalid side to move '{}'", other)),
+        };
 
-        board.squares[0][1] = Some(Piece {
-            color: Color::White,
-            kind: PieceType::Knight,
-        });
-        board.squares[7][1] = Some(Piece {
-            color: Color::Black,
-            kind: PieceType::Knight,
-        });
+        // KQkq stand for the outermost rook on either side of the king, as X-FEN has it for
+        // Chess960, and a file letter (Shredder-FEN) for any rook on the back rank; the king's
+        // and rooks' files are taken from the position, `validate` checking they agree
+        if fields[2] != "-" {
+            for c in fields[2].chars() {
+                let (color, row) = if c.is_ascii_uppercase() {
+                    (Color::White, 0)
+                } else {
+                    (Color::Black, 7)
+                };
+                let king_col = match board.find_king(color) {
+                    Some((king_row, col)) if king_row == row => col,
+                    _ => 4,
+                };
+                let is_rook = |col: &usize| {
+                    board.squares[row][*col]
+                        .is_some_and(|piece| piece.color == color && piece.kind == PieceType::Rook)
+                };
+                let (kingside, rook_col) = match c.to_ascii_lowercase() {
+                    'k' => (true, (king_col + 1..8).rev().find(is_rook).unwrap_or(7)),
+                    'q' => (false, (0..king_col).find(is_rook).unwrap_or(0)),
+                    file @ 'a'..='h' => {
+                        let col = file as usize - 'a' as usize;
+                        (col > king_col, col)
+                    }
+                    _ => return Err(format!("invalid castling rights '{}'", fields[2])),
+                };
+                board.king_col = king_col;
+                let rights = match color {
+                    Color::White => &mut board.white_castle_possible,
+                    Color::Black => &mut board.black_castle_possible,
+                };
+                if kingside {
+                    rights.1 = true;
+                    board.rook_cols.1 = rook_col;
+                } else {
+                    rights.0 = true;
+                    board.rook_cols.0 = rook_col;
+                }
+            }
+        }
 
-        board.squares[0][6] = Some(Piece {
-            color: Color::White,
-            kind: PieceType::Knight,
-        });
-        board.squares[7][6] = Some(Piece {
-            color: Color::Black,
-            kind: PieceType::Knight,
-        });
+        if fields[3] != "-" {
+            board.en_passant_target = Some(
+                algebraic_to_square(fields[3])
+                    .ok_or_else(|| format!("invalid en passant square '{}'", fields[3]))?,
+            );
+        }
 
-        board.squares[0][2] = Some(Piece {
-            color: Color::White,
-            kind: PieceType::Bishop,
-        });
-        board.squares[7][2] = Some(Piece {
-            color: Color::Black,
-            kind: PieceType::Bishop,
-        });
+        if let Some(clock) = fields.get(4) {
+            board.half_move_clock = clock
+                .parse()
+                .map_err(|_| format!("invalid half-move clock '{}'", clock))?;
+        }
+        if let Some(number) = fields.get(5) {
+            board.full_move_number = number
+                .parse()
+                .map_err(|_| format!("invalid full-move number '{}'", number))?;
+        }
+        Ok(board)
+    }
 
-        board.squares[0][5] = Some(Piece {
-            color: Color::White,
-            kind: PieceType::Bishop,
-        });
-        board.squares[7][5] = Some(Piece {
-            color: Color::Black,
-            kind: PieceType::Bishop,
-        });
+    // Checks that the position could arise in a game: one king a side, no more than 16 pieces
+    // and 8 pawns a side, no pawn on the first or last rank, the side that just moved not in
+    // check, castling rights only with the king and rook on their starting squares, and an en
+    // passant square only behind a pawn that has just moved two squares
+    pub fn validate(&self) -> Result<(), String> {
+        for color in [Color::White, Color::Black] {
+            let count = |kind| self.bitboard(Piece { color, kind }).count_ones();
+            if count(PieceType::King) != 1 {
+                return Err(format!("{:?} has {} kings", color, count(PieceType::King)));
+            }
+            if self.occupancy(color).count_ones() > 16 || count(PieceType::Pawn) > 8 {
+                return Err(format!("{:?} has too many pieces", color));
+            }
+        }
+        for col in 0..8 {
+            for row in [0, 7] {
+                if self.squares[row][col].is_some_and(|piece| piece.kind == PieceType::Pawn) {
+                    return Err(format!("pawn on {}", square_to_algebraic((row, col))));
+                }
+            }
+        }
+        if self.is_in_check(opposite_color(self.side_to_move)) {
+            return Err(format!(
+                "{:?} is in check with {:?} to move",
+                opposite_color(self.side_to_move),
+                self.side_to_move
+            ));
+        }
 
-        board
+        for (color, row, (queenside, kingside)) in [
+            (Color::White, 0, self.white_castle_possible),
+            (Color::Black, 7, self.black_castle_possible),
+        ] {
+            let is = |col: usize, kind: PieceType| {
+                self.squares[row][col]
+                    .is_some_and(|piece| piece.color == color && piece.kind == kind)
+            };
+            if (queenside || kingside) && !is(self.king_col, PieceType::King)
+                || queenside && !is(self.rook_cols.0, PieceType::Rook)
+                || kingside && !is(self.rook_cols.1, PieceType::Rook)
+            {
+                return Err(format!(
+                    "{:?} may castle without its king and rook at home",
+                    color
+                ));
+            }
+        }
+
+        if let Some((row, col)) = self.en_passant_target {
+            // The pawn that just moved stands one square past the target, its start square
+            // one square before it
+            let (mover, pawn_row, start_row) = match self.side_to_move {
+                Color::White => (Color::Black, 4, 6),
+                Color::Black => (Color::White, 3, 1),
+            };
+            let moved_pawn = self.squares[pawn_row][col]
+                .is_some_and(|piece| piece.color == mover && piece.kind == PieceType::Pawn);
+            if row != (pawn_row + start_row) / 2
+                || !moved_pawn
+                || self.squares[row][col].is_some()
+                || self.squares[start_row][col].is_some()
+            {
+                return Err(format!(
+                    "invalid en passant square {}",
+                    square_to_algebraic((row, col))
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    // A random position that passes `validate`, for fuzzing and tuning: the two kings and
+    // `constraints.pieces` other pieces of random kind and colour on random squares, with
+    // either side to move. Placements that break a rule are thrown away and redrawn.
+    pub fn random_position(rng: &mut impl Rng, constraints: &PositionConstraints) -> Board {
+        const KINDS: [PieceType; 5] = [
+            PieceType::Queen,
+            PieceType::Rook,
+            PieceType::Bishop,
+            PieceType::Knight,
+            PieceType::Pawn,
+        ];
+        // Two kings and at most 15 more pieces a side fit on the board
+        let pieces = constraints.pieces.min(30);
+        loop {
+            let mut board = Board {
+                side_to_move: if rng.random() {
+                    Color::White
+                } else {
+                    Color::Black
+                },
+                ..Board::empty()
+            };
+            let mut empty: Vec<(usize, usize)> =
+                (0..64).map(|square| (square / 8, square % 8)).collect();
+            let mut take_square = |rng: &mut _, pawn: bool| {
+                // Pawns never stand on the first or last rank
+                let allowed: Vec<usize> = (0..empty.len())
+                    .filter(|&i| !pawn || (1..7).contains(&empty[i].0))
+                    .collect();
+                let &i = allowed.choose(rng)?;
+                Some(empty.swap_remove(i))
+            };
+            for color in [Color::White, Color::Black] {
+                let (row, col) = take_square(rng, false).unwrap();
+                let kind = PieceType::King;
+                board.set_piece(row, col, Some(Piece { color, kind }));
+            }
+            for _ in 0..pieces {
+                let color = if rng.random() {
+                    Color::White
+                } else {
+                    Color::Black
+                };
+                let kinds = if constraints.allow_pawns {
+                    &KINDS[..]
+                } else {
+                    &KINDS[..4]
+                };
+                let kind = *kinds.choose(rng).unwrap();
+                let full = board.occupancy(color).count_ones() >= 16
+                    || kind == PieceType::Pawn
+                        && board.bitboard(Piece { color, kind }).count_ones() >= 8;
+                let color = if full { opposite_color(color) } else { color };
+                if let Some((row, col)) = take_square(rng, kind == PieceType::Pawn) {
+                    board.set_piece(row, col, Some(Piece { color, kind }));
+                }
+            }
+
+            if constraints.castling {
+                // Each right is granted at random where the king and rook are still at home
+                for (color, row) in [(Color::White, 0), (Color::Black, 7)] {
+                    let home = |col: usize, kind: PieceType| {
+                        board.squares[row][col]
+                            .is_some_and(|piece| piece.color == color && piece.kind == kind)
+                    };
+                    if !home(4, PieceType::King) {
+                        continue;
+                    }
+                    let rights = (
+                        home(0, PieceType::Rook) && rng.random(),
+                        home(7, PieceType::Rook) && rng.random(),
+                    );
+                    match color {
+                        Color::White => board.white_castle_possible = rights,
+                        Color::Black => board.black_castle_possible = rights,
+                    }
+                }
+            }
+            if board.validate().is_ok() {
+                return board;
+            }
+        }
+    }
+
+    // Writes the position in Forsyth-Edwards Notation
+    pub fn to_fen(&self) -> String {
+        let mut placement = Vec::new();
+        for row in (0..8).rev() {
+            let mut rank = String::new();
+            let mut empty = 0;
+            for col in 0..8 {
+                match self.squares[row][col] {
+                    Some(piece) => {
+                        if empty > 0 {
+                            rank.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        rank.push(match piece.color {
+                            Color::White => piece.kind.letter(),
+                            Color::Black => piece.kind.letter().to_ascii_lowercase(),
+                        });
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                rank.push_str(&empty.to_string());
+            }
+            placement.push(rank);
+        }
+
+        // A right is written by its rook's file only when another rook stands further out on
+        // that side, which only happens in Chess960
+        let mut castling = String::new();
+        for (color, row, (queenside, kingside)) in [
+            (Color::White, 0, self.white_castle_possible),
+            (Color::Black, 7, self.black_castle_possible),
+        ] {
+            for (allowed, kingside) in [(kingside, true), (queenside, false)] {
+                if !allowed {
+                    continue;
+                }
+                let (rook_col, mut outside, letter) = if kingside {
+                    (self.rook_cols.1, self.rook_cols.1 + 1..8, 'k')
+                } else {
+                    (self.rook_cols.0, 0..self.rook_cols.0, 'q')
+                };
+                let is_rook = |col: usize| {
+                    self.squares[row][col]
+                        .is_some_and(|piece| piece.color == color && piece.kind == PieceType::Rook)
+                };
+                let letter = if outside.any(is_rook) {
+                    (b'a' + rook_col as u8) as char
+                } else {
+                    letter
+                };
+                castling.push(match color {
+                    Color::White => letter.to_ascii_uppercase(),
+                    Color::Black => letter,
+                });
+            }
+        }
+        if castling.is_empty() {
+            castling.push('-');
+        }
+
+        format!(
+            "{} {} {} {} {} {}",
+            placement.join("/"),
+            match self.side_to_move {
+                Color::White => "w",
+                Color::Black => "b",
+            },
+            castling,
+            self.en_passant_target
+                .map_or("-".to_string(), square_to_algebraic),
+            self.half_move_clock,
+            self.full_move_number
+        )
+    }
+
+    // The same position with the colours swapped: every piece moves to the mirror square
+    // across the middle of the board and changes sides, as do the castling rights, the en
+    // passant square and the side to move. Its evaluation is the exact negation of this one's.
+    pub fn color_flipped(&self) -> Board {
+        let mut flipped = Board {
+            white_castle_possible: self.black_castle_possible,
+            black_castle_possible: self.white_castle_possible,
+            en_passant_target: self.en_passant_target.map(|(row, col)| (7 - row, col)),
+            side_to_move: opposite_color(self.side_to_move),
+            half_move_clock: self.half_move_clock,
+            full_move_number: self.full_move_number,
+            king_col: self.king_col,
+            rook_cols: self.rook_cols,
+            chess960_id: self.chess960_id,
+            ..Board::empty()
+        };
+        for (row, col) in bitboard::squares(self.occupied[0] | self.occupied[1]) {
+            let piece = self.squares[row][col].map(|piece| Piece {
+                color: opposite_color(piece.color),
+                kind: piece.kind,
+            });
+            flipped.set_piece(7 - row, col, piece);
+        }
+        flipped
+    }
+
+    // The position reflected left to right, the a-file swapped with the h-file. A mirrored
+    // king no longer stands where it could castle, so the castling rights are dropped. Its
+    // evaluation is the same as this one's.
+    pub fn mirrored(&self) -> Board {
+        let mut mirrored = Board {
+            en_passant_target: self.en_passant_target.map(|(row, col)| (row, 7 - col)),
+            side_to_move: self.side_to_move,
+            half_move_clock: self.half_move_clock,
+            full_move_number: self.full_move_number,
+            ..Board::empty()
+        };
+        for (row, col) in bitboard::squares(self.occupied[0] | self.occupied[1]) {
+            mirrored.set_piece(row, 7 - col, self.squares[row][col]);
+        }
+        mirrored
+    }
+
+    // Plies played since the start of the game, derived from the move counter
+    pub fn half_moves_played(&self) -> u32 {
+        (self.full_move_number - 1) * 2 + (self.side_to_move == Color::Black) as u32
+    }
+
+    pub fn piece_at(&self, row: usize, col: usize) -> Option<Piece> {
+        self.squares[row][col]
+    }
+
+    // Puts `piece` on (row, col), or empties the square for None
+    pub fn set_piece(&mut self, row: usize, col: usize, piece: Option<Piece>) {
+        let bit = bitboard::square_bit(row, col);
+        #[cfg(feature = "nnue")]
+        let network = nnue::network().filter(|_| !self.accumulator.is_empty());
+        if let Some(old) = self.squares[row][col] {
+            self.pieces[piece_index(&old)] &= !bit;
+            self.occupied[old.color as usize] &= !bit;
+            self.material[old.color as usize] -= get_piece_value(&old);
+            self.placement[old.color as usize] -= piece_square_value(row, col);
+            #[cfg(feature = "nnue")]
+            if let Some(network) = network {
+                network.update(&mut self.accumulator, &old, (row, col), -1.0);
+            }
+        }
+        if let Some(new) = piece {
+            self.pieces[piece_index(&new)] |= bit;
+            self.occupied[new.color as usize] |= bit;
+            self.material[new.color as usize] += get_piece_value(&new);
+            self.placement[new.color as usize] += piece_square_value(row, col);
+            #[cfg(feature = "nnue")]
+            if let Some(network) = network {
+                network.update(&mut self.accumulator, &new, (row, col), 1.0);
+            }
+        }
+        self.squares[row][col] = piece;
+    }
+
+    // The network's first-layer sums as `set_piece` keeps them, empty if no network was set
+    // when the board was made
+    #[cfg(feature = "nnue")]
+    pub fn accumulator(&self) -> &[f32] {
+        &self.accumulator
+    }
+
+    // The squares holding `piece`
+    pub fn bitboard(&self, piece: Piece) -> Bitboard {
+        self.pieces[piece_index(&piece)]
+    }
+
+    // The squares holding a piece of `color`
+    pub fn occupancy(&self, color: Color) -> Bitboard {
+        self.occupied[color as usize]
     }
 
     // Insert your custom move generation, evaluation, minimax, etc. here.
 
-    // Returns a vector of moves in the form: ((from_row, from_col), (to_row, to_col))
-    pub fn generate_moves_for_piece(
+    // Returns a vector of pseudo-legal moves for the piece on (row, col)
+    pub fn generate_moves_for_piece(&self, row: usize, col: usize) -> Vec<Move> {
+        let mut moves = Vec::new();
+        self.generate_moves_for_piece_into(row, col, &mut moves);
+        moves
+    }
+
+    // Appends the pseudo-legal moves of the piece on (row, col) to `moves`, so the search can
+    // reuse one buffer instead of allocating per call
+    pub fn generate_moves_for_piece_into(&self, row: usize, col: usize, moves: &mut Vec<Move>) {
+        self.generate_piece_moves(row, col, MoveKinds::All, moves);
+    }
+
+    // Appends the pseudo-legal moves of the piece on (row, col) of the given kinds to `moves`
+    fn generate_piece_moves(
         &self,
         row: usize,
         col: usize,
-    ) -> Vec<((usize, usize), (usize, usize))> {
-        let mut moves = Vec::new();
+        kinds: MoveKinds,
+        moves: &mut Vec<Move>,
+    ) {
         if let Some(piece) = self.squares[row][col] {
+            let enemies = self.occupancy(opposite_color(piece.color));
+            // The squares a piece may land on, for everything but pawns
+            let target_mask = match kinds {
+                MoveKinds::All => !self.occupancy(piece.color),
+                MoveKinds::Captures => enemies,
+                MoveKinds::Quiets => !(self.occupancy(piece.color) | enemies),
+            };
+            let quiets = kinds != MoveKinds::Captures;
+            let captures = kinds != MoveKinds::Quiets;
             match piece.kind {
                 PieceType::Pawn => {
                     // Determine the moving direction based on the pawn's color
@@ -165,36 +1066,36 @@ impl Board {
                     let new_row = row as isize + direction;
 
                     // Simple forward move (1 square ahead)
-                    if new_row >= 0 && new_row < 8 && self.squares[new_row as usize][col].is_none()
+                    if quiets
+                        && (0..8).contains(&new_row)
+                        && self.squares[new_row as usize][col].is_none()
                     {
-                        moves.push(((row, col), (new_row as usize, col)));
+                        push_pawn_move(moves, (row, col), (new_row as usize, col));
                     }
 
                     // Double forward move (only allowed on the starting row and if both squares are empty)
                     let starting_row = if piece.color == Color::White { 1 } else { 6 };
-                    if row == starting_row && self.squares[new_row as usize][col].is_none() {
+                    if quiets
+                        && row == starting_row
+                        && self.squares[new_row as usize][col].is_none()
+                    {
                         let double_row = new_row + direction; // Calculate the row 2 squares ahead
-                        if double_row >= 0
-                            && double_row < 8
+                        if (0..8).contains(&double_row)
                             && self.squares[double_row as usize][col].is_none()
                         {
                             // Check that the square two steps ahead is empty
-                            moves.push(((row, col), (double_row as usize, col)));
+                            moves.push(Move::new((row, col), (double_row as usize, col)));
                         }
                     }
 
+                    if !captures {
+                        return;
+                    }
+
                     // Diagonal captures (both left and right)
-                    for &dc in &[-1, 1] {
-                        let new_col = col as isize + dc;
-                        if new_row >= 0 && new_row < 8 && new_col >= 0 && new_col < 8 {
-                            if let Some(dest_piece) =
-                                self.squares[new_row as usize][new_col as usize]
-                            {
-                                if dest_piece.color != piece.color {
-                                    moves.push(((row, col), (new_row as usize, new_col as usize)));
-                                }
-                            }
-                        }
+                    let targets = bitboard::pawn_attacks(piece.color, row, col) & enemies;
+                    for to in bitboard::squares(targets) {
+                        push_pawn_move(moves, (row, col), to);
                     }
 
                     // En passant
@@ -203,375 +1104,396 @@ impl Board {
                             && (col as isize + 1 == target_col as isize
                                 || col as isize - 1 == target_col as isize)
                         {
-                            moves.push(((row, col), (new_row as usize, target_col)));
+                            moves.push(Move::new((row, col), (new_row as usize, target_col)));
                         }
                     }
                 }
                 PieceType::Knight => {
-                    // L-shaped moves for knights
-                    let knight_moves = [
-                        (2, 1),
-                        (1, 2),
-                        (-1, 2),
-                        (-2, 1),
-                        (-2, -1),
-                        (-1, -2),
-                        (1, -2),
-                        (2, -1),
-                    ];
-                    for (dr, dc) in knight_moves.iter() {
-                        let new_row = row as isize + dr;
-                        let new_col = col as isize + dc;
-                        if new_row >= 0 && new_row < 8 && new_col >= 0 && new_col < 8 {
-                            // Allow move if the destination is either empty or occupied by an enemy piece
-                            if let Some(dest_piece) =
-                                self.squares[new_row as usize][new_col as usize]
-                            {
-                                if dest_piece.color != piece.color {
-                                    moves.push(((row, col), (new_row as usize, new_col as usize)));
-                                }
-                            } else {
-                                moves.push(((row, col), (new_row as usize, new_col as usize)));
-                            }
-                        }
-                    }
+                    // L-shaped moves for knights, onto empty or enemy squares
+                    let targets = KNIGHT_ATTACKS[row * 8 + col] & target_mask;
+                    moves.extend(bitboard::squares(targets).map(|to| Move::new((row, col), to)));
                 }
                 PieceType::King => {
-                    let king_moves = [
-                        (-1, -1),
-                        (-1, 0),
-                        (-1, 1),
-                        (0, -1),
-                        (0, 1),
-                        (1, -1),
-                        (1, 0),
-                        (1, 1),
-                    ];
-                    for (dr, dc) in king_moves.iter() {
-                        let new_row = row as isize + dr;
-                        let new_col = col as isize + dc;
-                        if new_row >= 0 && new_row < 8 && new_col >= 0 && new_col < 8 {
-                            if let Some(dest_piece) =
-                                self.squares[new_row as usize][new_col as usize]
-                            {
-                                // Check if the destination is either empty or occupied by an enemy piece
-                                if dest_piece.color != piece.color {
-                                    // Check if the destination is under attack
-                                    // if !self.is_square_under_attack(new_row as usize, new_col as usize, piece.color) {
-                                    moves.push(((row, col), (new_row as usize, new_col as usize)));
-                                    // }
-                                }
-                            } else {
-                                // Check if the destination is under attack
-                                // if !self.is_square_under_attack(new_row as usize, new_col as usize, piece.color) {
-                                moves.push(((row, col), (new_row as usize, new_col as usize)));
-                                // }
+                    // Whether the destination is attacked is checked by the legality filter
+                    let targets = KING_ATTACKS[row * 8 + col] & target_mask;
+                    moves.extend(bitboard::squares(targets).map(|to| Move::new((row, col), to)));
+                    // Castling logic; can_castle_unsafe checks the King's color and rights
+                    if quiets {
+                        // Kingside castling, then queenside
+                        for kingside in [true, false] {
+                            let to = self.castling_square(row, kingside);
+                            if self.can_castle_unsafe((row, col), to) {
+                                moves.push(Move::new((row, col), to));
                             }
                         }
                     }
-                    // Castling logic
-                    if let Some(piece) = self.squares[row][col] {
-                        if piece.color == Color::White {
-                            if self.can_castle_unsafe((row, col), (row, 6)) {
-                                // Kingside castling
-                                moves.push(((row, col), (row, 6)));
-                            }
-                            if self.can_castle_unsafe((row, col), (row, 2)) {
-                                // Queenside castling
-                                moves.push(((row, col), (row, 2)));
-                            }
-                        }
-                        if piece.color == Color::Black {
-                            if self.can_castle_unsafe((row, col), (row, 6)) {
-                                // Kingside castling
-                                moves.push(((row, col), (row, 6)));
-                            }
-                            if self.can_castle_unsafe((row, col), (row, 2)) {
-                                // Queenside castling
-                                moves.push(((row, col), (row, 2)));
-                            }
-                        }
-                    }
-                }
-                PieceType::Queen => {
-                    // Queen moves are a combination of Rook and Bishop moves
-                    moves.extend(self.generate_moves_in_direction(row, col, 1, 0, piece)); // Right
-                    moves.extend(self.generate_moves_in_direction(row, col, -1, 0, piece)); // Left
-                    moves.extend(self.generate_moves_in_direction(row, col, 0, 1, piece)); // Down
-                    moves.extend(self.generate_moves_in_direction(row, col, 0, -1, piece)); // Up
-                    moves.extend(self.generate_moves_in_direction(row, col, 1, 1, piece)); // Down-Right
-                    moves.extend(self.generate_moves_in_direction(row, col, 1, -1, piece)); // Up-Right
-                    moves.extend(self.generate_moves_in_direction(row, col, -1, 1, piece)); // Down-Left
-                    moves.extend(self.generate_moves_in_direction(row, col, -1, -1, piece));
-                    // Up-Left
-                }
-                PieceType::Rook => {
-                    moves.extend(self.generate_moves_in_direction(row, col, 1, 0, piece)); // Right
-                    moves.extend(self.generate_moves_in_direction(row, col, -1, 0, piece)); // Left
-                    moves.extend(self.generate_moves_in_direction(row, col, 0, 1, piece)); // Down
-                    moves.extend(self.generate_moves_in_direction(row, col, 0, -1, piece));
-                    // Up
                 }
-                PieceType::Bishop => {
-                    moves.extend(self.generate_moves_in_direction(row, col, 1, 1, piece)); // Down-Right
-                    moves.extend(self.generate_moves_in_direction(row, col, 1, -1, piece)); // Up-Right
-                    moves.extend(self.generate_moves_in_direction(row, col, -1, 1, piece)); // Down-Left
-                    moves.extend(self.generate_moves_in_direction(row, col, -1, -1, piece));
-                    // Up-Left
+                PieceType::Queen | PieceType::Rook | PieceType::Bishop => {
+                    // Sliding moves come from the attack tables, onto empty or enemy squares
+                    let targets = self.attacks_from(row, col) & target_mask;
+                    moves.extend(bitboard::squares(targets).map(|to| Move::new((row, col), to)));
                 }
             }
         }
-        moves
     }
 
     // Generate moves for the current player (assume you pass which color is moving)
-    fn generate_all_moves(&self, color: Color) -> Vec<((usize, usize), (usize, usize))> {
+    fn generate_all_moves(&self, color: Color) -> Vec<Move> {
         let mut all_moves = Vec::new();
-        for row in 0..8 {
-            for col in 0..8 {
-                if let Some(piece) = self.squares[row][col] {
-                    if piece.color == color {
-                        all_moves.extend(self.generate_moves_for_piece(row, col));
-                    }
-                }
-            }
-        }
+        self.generate_all_moves_into(color, &mut all_moves);
         all_moves
     }
 
-    fn generate_moves_in_direction(
-        &self,
-        row: usize,
-        col: usize,
-        dr: isize,
-        dc: isize,
-        piece: Piece,
-    ) -> Vec<((usize, usize), (usize, usize))> {
-        let mut moves = Vec::new();
-        let mut new_row = row as isize + dr;
-        let mut new_col = col as isize + dc;
-
-        while new_row >= 0 && new_row < 8 && new_col >= 0 && new_col < 8 {
-            let dest_piece = self.squares[new_row as usize][new_col as usize];
-            if let Some(dest_piece) = dest_piece {
-                if dest_piece.color != piece.color {
-                    moves.push(((row, col), (new_row as usize, new_col as usize)));
-                }
-                break; // Stop after capturing a piece
-            } else {
-                moves.push(((row, col), (new_row as usize, new_col as usize)));
-            }
-            new_row += dr;
-            new_col += dc;
+    fn generate_all_moves_into(&self, color: Color, moves: &mut Vec<Move>) {
+        self.generate_moves_of_kinds(color, MoveKinds::All, moves);
+    }
+
+    fn generate_moves_of_kinds(&self, color: Color, kinds: MoveKinds, moves: &mut Vec<Move>) {
+        for (row, col) in bitboard::squares(self.occupancy(color)) {
+            self.generate_piece_moves(row, col, kinds, moves);
         }
-        moves
     }
 
-    pub fn apply_move(&mut self, m: ((usize, usize), (usize, usize))) {
-        let ((from_row, from_col), (to_row, to_col)) = m;
+    // Generate only the moves that do not leave the player's own king in check
+    pub fn generate_all_legal_moves(&self, color: Color) -> Vec<Move> {
+        let pins = self.pinned_pieces(color);
+        let in_check = self.is_in_check(color);
+        self.generate_all_moves(color)
+            .into_iter()
+            .filter(|m| self.is_legal_with_pins(*m, color, &pins, in_check))
+            .collect()
+    }
+
+    pub fn apply_move(&mut self, m: Move) {
+        self.play_move(m);
+        // The check is on the hand-crafted evaluation, whether or not a network is in use
+        debug_assert_eq!(
+            hand_crafted_evaluation(self),
+            evaluate_position_from_scratch(self),
+            "incremental evaluation out of step after {}",
+            move_to_uci(m)
+        );
+    }
+
+    // Passes the turn without moving a piece, for null-move pruning and for asking what the
+    // opponent would do if they could move twice. The en passant target lapses, as after any
+    // move. A side in check may not pass, so this returns None and leaves the board alone;
+    // otherwise it returns what `unmake_null_move` needs to restore the board.
+    pub fn make_null_move(&mut self) -> Option<NullMove> {
+        if self.is_in_check(self.side_to_move) {
+            return None;
+        }
+        let undo = NullMove {
+            en_passant_target: self.en_passant_target.take(),
+            half_move_clock: self.half_move_clock,
+        };
+        self.half_move_clock += 1;
+        if self.side_to_move == Color::Black {
+            self.full_move_number += 1;
+        }
+        self.side_to_move = opposite_color(self.side_to_move);
+        Some(undo)
+    }
+
+    // Takes back a null move made by `make_null_move`
+    pub fn unmake_null_move(&mut self, undo: NullMove) {
+        self.side_to_move = opposite_color(self.side_to_move);
+        if self.side_to_move == Color::Black {
+            self.full_move_number -= 1;
+        }
+        self.half_move_clock = undo.half_move_clock;
+        self.en_passant_target = undo.en_passant_target;
+    }
+
+    fn play_move(&mut self, m: Move) {
+        let Move {
+            from: (from_row, from_col),
+            to: (to_row, to_col),
+            promotion,
+        } = m;
+        if self.side_to_move == Color::Black {
+            self.full_move_number += 1;
+        }
+        self.side_to_move = opposite_color(self.side_to_move);
         if self.can_castle((from_row, from_col), (to_row, to_col)) {
             self.castle((from_row, from_col), (to_row, to_col));
+            self.half_move_clock += 1;
+            self.en_passant_target = None;
             return;
         }
         if let Some(mut piece) = self.squares[from_row][from_col] {
-            self.squares[from_row][from_col] = EMPTY;
+            self.set_piece(from_row, from_col, EMPTY);
+
+            // En passant capture: the captured pawn stands beside the pawn's starting square,
+            // not on its destination, so remove it before looking for a capture there
+            let is_en_passant = piece.kind == PieceType::Pawn
+                && from_col != to_col
+                && Some((to_row, to_col)) == self.en_passant_target;
+            if is_en_passant {
+                self.set_piece(from_row, to_col, None); // Remove captured pawn
+            }
+            let is_capture = is_en_passant || self.squares[to_row][to_col].is_some();
 
-            // Update half-move clock on captures or pawn moves
-            if piece.kind == PieceType::Pawn || self.squares[to_row][to_col].is_some() {
-                // En passant capture
-                if Some((to_row, to_col)) == self.en_passant_target {
-                    self.squares[from_row][to_col] = None; // Remove captured pawn
-                }
-                self.half_move_clock = 0; // Reset clock on pawn move or capture
+            // Every pawn move resets the half-move clock, and so does every capture
+            if piece.kind == PieceType::Pawn || is_capture {
+                self.half_move_clock = 0;
             } else {
                 self.half_move_clock += 1;
             }
-            if piece.kind == PieceType::Pawn && (to_row == 0 || to_row == 7) {
-                // Promote to a Queen (can be extended for other choices)
-                piece.kind = PieceType::Queen;
-            }
-            if piece.kind == PieceType::Rook {
-                if piece.color == Color::White {
-                    if from_col == 0 {
-                        self.white_castle_possible.0 = false;
-                    } else {
-                        self.white_castle_possible.1 = false;
-                    }
-                } else {
-                    if from_col == 0 {
-                        self.black_castle_possible.0 = false;
-                    } else {
-                        self.black_castle_possible.1 = false;
-                    }
-                }
-            } else if piece.kind == PieceType::King {
+            if piece.kind == PieceType::King {
                 if piece.color == Color::White {
                     self.white_castle_possible = (false, false);
                 } else {
                     self.black_castle_possible = (false, false);
                 }
             }
-            self.squares[to_row][to_col] = Some(piece);
+            // A right goes once anything leaves or lands on its rook's starting square: the
+            // rook has moved or been captured there
+            for (row, col) in [(from_row, from_col), (to_row, to_col)] {
+                let rights = match row {
+                    0 => &mut self.white_castle_possible,
+                    7 => &mut self.black_castle_possible,
+                    _ => continue,
+                };
+                if col == self.rook_cols.0 {
+                    rights.0 = false;
+                }
+                if col == self.rook_cols.1 {
+                    rights.1 = false;
+                }
+            }
+            // A move without a promotion piece, as older saved games wrote it, queens
+            if piece.kind == PieceType::Pawn && (to_row == 0 || to_row == 7) {
+                piece.kind = promotion.unwrap_or(PieceType::Queen);
+            }
+            self.set_piece(to_row, to_col, Some(piece));
             // Update en passant target square
             self.en_passant_target = None; // Reset on every move
-            if piece.kind == PieceType::Pawn {
-                let row_diff = if to_row > from_row {
-                    to_row - from_row
-                } else {
-                    from_row - to_row
-                };
-
-                if row_diff == 2 {
-                    self.en_passant_target = Some(((from_row + to_row) / 2, from_col));
+            if piece.kind == PieceType::Pawn && to_row.abs_diff(from_row) == 2 {
+                // Only recorded when an enemy pawn could take there, so a double push nobody
+                // can answer leaves the same FEN and Zobrist key as any other move would
+                let target = ((from_row + to_row) / 2, from_col);
+                let enemy_pawns = self.bitboard(Piece {
+                    color: opposite_color(piece.color),
+                    kind: PieceType::Pawn,
+                });
+                if bitboard::pawn_attacks(piece.color, target.0, target.1) & enemy_pawns != 0 {
+                    self.en_passant_target = Some(target);
                 }
             }
         }
     }
 
-    pub fn is_square_under_attack(&self, row: usize, col: usize, color: Color) -> bool {
-        let opponent_color = opposite_color(color);
-        let temp_board = self.clone();
-        // temp_board.squares[row][col] = None;
-        // Check all opponent's pieces
-        for r in 0..8 {
-            for c in 0..8 {
-                if let Some(piece) = temp_board.squares[r][c] {
-                    // If the piece is of the opposite color, generate its moves
-                    if piece.color == opponent_color {
-                        let possible_moves = temp_board.generate_moves_for_piece(r, c);
-                        // println!("{:?} {:?}", piece, possible_moves);
-                        // If any move attacks the square
-                        if possible_moves.iter().any(|&(_, to)| to == (row, col)) {
-                            return true;
-                        }
-                    }
+    // Zobrist hash of the position: pieces, side to move, castling rights and en passant file
+    pub fn zobrist_key(&self) -> u64 {
+        let keys = zobrist_keys();
+        let mut key = 0;
+        for row in 0..8 {
+            for col in 0..8 {
+                if let Some(piece) = self.squares[row][col] {
+                    key ^= keys.pieces[piece_index(&piece)][row * 8 + col];
                 }
             }
         }
-
-        false
+        if self.side_to_move == Color::Black {
+            key ^= keys.black_to_move;
+        }
+        let rights = [
+            self.white_castle_possible.0,
+            self.white_castle_possible.1,
+            self.black_castle_possible.0,
+            self.black_castle_possible.1,
+        ];
+        for (right, castling_key) in rights.iter().zip(keys.castling) {
+            if *right {
+                key ^= castling_key;
+            }
+        }
+        if let Some((_, col)) = self.en_passant_target {
+            key ^= keys.en_passant_file[col];
+        }
+        key
     }
 
-    fn is_castling_move(&self, from: (usize, usize), to: (usize, usize), color: Color) -> bool {
-        let (from_row, from_col) = from;
-        let (to_row, to_col) = to;
+    // Returns true if a piece of the opponent of `color` attacks (row, col)
+    pub fn is_square_under_attack(&self, row: usize, col: usize, color: Color) -> bool {
+        self.attackers_bitboard((row, col), opposite_color(color)) != 0
+    }
 
-        // 1. Basic Castling Conditions:
-        if from_row != to_row {
-            // Must be on the same row
-            return false;
+    // Returns the squares the piece on (row, col) attacks, whether they are empty or hold a
+    // piece of either color. Pawns attack only their capture squares and kings not castling.
+    pub fn attacks_from(&self, row: usize, col: usize) -> Bitboard {
+        let square = row * 8 + col;
+        let occupied = self.occupied[0] | self.occupied[1];
+        match self.squares[row][col] {
+            Some(piece) => match piece.kind {
+                PieceType::Pawn => bitboard::pawn_attacks(piece.color, row, col),
+                PieceType::Knight => KNIGHT_ATTACKS[square],
+                PieceType::King => KING_ATTACKS[square],
+                PieceType::Rook => bitboard::rook_attacks(square, occupied),
+                PieceType::Bishop => bitboard::bishop_attacks(square, occupied),
+                PieceType::Queen => bitboard::queen_attacks(square, occupied),
+            },
+            None => 0,
         }
+    }
 
-        if from_col != 4 {
-            // King must start at column 4
-            return false;
-        }
+    // Returns the squares of all `by_color` pieces that attack `sq`, from a1 upwards. Each
+    // attack pattern is looked up from `sq` itself instead of generating every enemy move.
+    pub fn attackers_of(&self, sq: (usize, usize), by_color: Color) -> Vec<(usize, usize)> {
+        bitboard::squares(self.attackers_bitboard(sq, by_color)).collect()
+    }
 
-        if to_col != 2 && to_col != 6 {
-            // King must move to column 2 or 6
-            return false;
+    // The attackers of `sq` as a bitboard, so attack tests need not build a list
+    pub fn attackers_bitboard(&self, sq: (usize, usize), by_color: Color) -> Bitboard {
+        self.attackers_through(sq, by_color, self.occupied[0] | self.occupied[1], 0)
+    }
+
+    // The attackers of `sq` if the board's pieces stood on `occupied` instead, with the
+    // `by_color` pieces on `removed` taken off; lets a move be tested without playing it
+    fn attackers_through(
+        &self,
+        sq: (usize, usize),
+        by_color: Color,
+        occupied: Bitboard,
+        removed: Bitboard,
+    ) -> Bitboard {
+        let square = sq.0 * 8 + sq.1;
+        let pieces = |kind| {
+            self.bitboard(Piece {
+                color: by_color,
+                kind,
+            }) & !removed
+        };
+        let queens = pieces(PieceType::Queen);
+
+        // An attacking pawn stands where a pawn of the other color on `sq` would capture
+        bitboard::pawn_attacks(opposite_color(by_color), sq.0, sq.1) & pieces(PieceType::Pawn)
+            | KNIGHT_ATTACKS[square] & pieces(PieceType::Knight)
+            | KING_ATTACKS[square] & pieces(PieceType::King)
+            | bitboard::rook_attacks(square, occupied) & (pieces(PieceType::Rook) | queens)
+            | bitboard::bishop_attacks(square, occupied) & (pieces(PieceType::Bishop) | queens)
+    }
+
+    // Returns the squares of the enemy pieces giving check to the king of `color`
+    pub fn checkers(&self, color: Color) -> Vec<(usize, usize)> {
+        match self.find_king(color) {
+            Some(king_pos) => self.attackers_of(king_pos, opposite_color(color)),
+            None => Vec::new(),
         }
+    }
 
-        // 2. Color Consistency:
-        if let Some(king) = self.squares[from_row][from_col] {
-            if king.color != color || king.kind != PieceType::King {
-                return false; // Must be the player's King
-            }
-        } else {
-            return false; // King must be present at 'from'
+    // Whether `from` to `to` is `color`'s King castling, written as this board writes it
+    fn is_castling_move(&self, from: (usize, usize), to: (usize, usize), color: Color) -> bool {
+        self.castling_color(from) == Some(color) && self.castling_side(from, to).is_some()
+    }
+
+    // Which way a move from `from` to `to` castles, kingside as true, if it is written as
+    // castling: the King leaving its starting file for the g- or c-file, or in Chess960, where
+    // the King may start next to or even on those, for its own Rook's square. Neither the
+    // King nor the rights are checked.
+    fn castling_side(&self, from: (usize, usize), to: (usize, usize)) -> Option<bool> {
+        if from.1 != self.king_col || from.0 != to.0 {
+            return None;
         }
-        true
+        // Once that Rook has gone, a Chess960 King moving to its square is an ordinary move
+        let onto_own_piece = self.squares[to.0][to.1]
+            .zip(self.squares[from.0][from.1])
+            .is_some_and(|(target, mover)| target.color == mover.color);
+        if self.is_chess960() && !onto_own_piece {
+            return None;
+        }
+        [true, false]
+            .into_iter()
+            .find(|&kingside| to == self.castling_square(from.0, kingside))
     }
-    pub fn can_castle(&self, from: (usize, usize), to: (usize, usize)) -> bool {
-        let (from_row, from_col) = from;
-        let (to_row, to_col) = to;
 
-        // Basic checks: King's original position, moving two squares horizontally
-        if from_col != 4 || (to_col != 6 && to_col != 2) || from_row != to_row {
-            return false;
+    // The square a castling King move on `row` names as its destination
+    fn castling_square(&self, row: usize, kingside: bool) -> (usize, usize) {
+        match (self.is_chess960(), kingside) {
+            (true, true) => (row, self.rook_cols.1),
+            (true, false) => (row, self.rook_cols.0),
+            (false, true) => (row, 6),
+            (false, false) => (row, 2),
         }
+    }
+
+    // Whether castling is written as the King taking its own Rook, as in Chess960 positions
+    // other than the standard one
+    pub fn is_chess960(&self) -> bool {
+        self.king_col != 4 || self.rook_cols != (0, 7)
+    }
 
-        let color = if from_row == 0 {
-            Color::White
+    // The castling Rook's file, and the files the King and Rook end on
+    fn castling_cols(&self, kingside: bool) -> (usize, usize, usize) {
+        if kingside {
+            (self.rook_cols.1, 6, 5)
         } else {
-            Color::Black
-        };
-        let kingside = to_col == 6;
-        let king_path = if kingside { 4..=6 } else { 4..=2 };
+            (self.rook_cols.0, 2, 3)
+        }
+    }
+
+    pub fn can_castle(&self, from: (usize, usize), to: (usize, usize)) -> bool {
         if !self.can_castle_unsafe(from, to) {
             return false;
         }
+        let (Some(color), Some(kingside)) =
+            (self.castling_color(from), self.castling_side(from, to))
+        else {
+            return false;
+        };
+        let (row, king_col) = from;
+        let (rook_col, king_to, _) = self.castling_cols(kingside);
         // King cannot be in check, pass through check, or end in check
         if self.is_in_check(color) {
             return false;
         }
-        for col in king_path {
-            if self.is_square_under_attack(from_row, col, color) {
-                return false;
-            }
-        }
-        true
+        // In Chess960 the castling Rook may be what shields a square of the King's path, as a
+        // Rook on b1 does c1 from a Rook on a1, so the path is looked at without it
+        let mut without_rook = self.clone();
+        without_rook.set_piece(row, rook_col, None);
+        (king_col.min(king_to)..=king_col.max(king_to))
+            .all(|col| !without_rook.is_square_under_attack(row, col, color))
     }
+
     // Check if the given move (from -> to) is a valid castling move
     pub fn can_castle_unsafe(&self, from: (usize, usize), to: (usize, usize)) -> bool {
-        let (from_row, from_col) = from;
-        let (to_row, to_col) = to;
-
-        // Basic checks: King's original position, moving two squares horizontally
-        if from_col != 4 || (to_col != 6 && to_col != 2) || from_row != to_row {
-            return false;
-        }
+        let kingside = match self.castling_side(from, to) {
+            Some(kingside) => kingside,
+            None => return false,
+        };
 
-        let color = if from_row == 0 {
-            Color::White
-        } else {
-            Color::Black
+        // The color comes from the King itself, so a King on an unexpected rank can only use
+        // its own side's rights
+        let color = match self.castling_color(from) {
+            Some(color) => color,
+            None => return false, // King must be present at 'from'
         };
-        let kingside = to_col == 6;
-        let rook_col = if kingside { 7 } else { 0 };
+        let (row, king_col) = from;
+        let (rook_col, king_to, rook_to) = self.castling_cols(kingside);
 
         // Castling rights check
-        let can_castle = if color == Color::White {
-            if kingside {
-                self.white_castle_possible.1
-            } else {
-                self.white_castle_possible.0
-            }
-        } else {
-            if kingside {
-                self.black_castle_possible.1
-            } else {
-                self.black_castle_possible.0
-            }
+        let rights = match color {
+            Color::White => self.white_castle_possible,
+            Color::Black => self.black_castle_possible,
         };
-        if !can_castle {
-            return false;
-        }
-
-        // King and Rook must be present and in their starting positions
-        if let Some(piece) = self.squares[from_row][from_col] {
-            if piece.kind != PieceType::King || piece.color != color {
-                return false;
-            }
-        } else {
-            return false;
-        }
-
-        if let Some(piece) = self.squares[from_row][rook_col] {
-            if piece.kind != PieceType::Rook || piece.color != color {
-                return false;
-            }
-        } else {
+        if !(if kingside { rights.1 } else { rights.0 }) {
             return false;
         }
 
-        // Squares between King and Rook must be empty
-        let range = if kingside { 5..7 } else { 1..4 };
-        if range.clone().any(|c| self.squares[from_row][c].is_some()) {
+        // Rook must be present in its starting position
+        if !self.squares[row][rook_col]
+            .is_some_and(|piece| piece.kind == PieceType::Rook && piece.color == color)
+        {
             return false;
         }
 
-        true // All checks passed
+        // Every square the King or Rook crosses or lands on must be empty but for the two of
+        // them; in standard chess, the squares between King and Rook
+        let first = king_col.min(rook_col).min(king_to).min(rook_to);
+        let last = king_col.max(rook_col).max(king_to).max(rook_to);
+        (first..=last)
+            .all(|col| col == king_col || col == rook_col || self.squares[row][col].is_none())
     }
 
     // Execute the castling move if valid
@@ -579,141 +1501,142 @@ impl Board {
         if !self.can_castle(from, to) {
             return false;
         }
+        let Some(kingside) = self.castling_side(from, to) else {
+            return false;
+        };
 
-        let (row, from_col) = from;
-        let to_col = to.1;
-        let kingside = to_col == 6;
-        let rook_col = if kingside { 7 } else { 0 };
-        let new_rook_col = if kingside { 5 } else { 3 };
-
-        // Move the King
-        self.squares[row][to_col] = self.squares[row][from_col].take();
+        let (row, king_col) = from;
+        let (rook_col, king_to, rook_to) = self.castling_cols(kingside);
+        let (king, rook) = (self.squares[row][king_col], self.squares[row][rook_col]);
 
-        // Move the Rook
-        self.squares[row][new_rook_col] = self.squares[row][rook_col].take();
+        // Both leave before either lands, as in Chess960 each may land where the other stood
+        self.set_piece(row, king_col, None);
+        self.set_piece(row, rook_col, None);
+        self.set_piece(row, king_to, king);
+        self.set_piece(row, rook_to, rook);
 
         // Disable further castling for this player
-        if row == 0 {
-            self.white_castle_possible = (false, false);
-        } else {
-            self.black_castle_possible = (false, false);
+        match king.map(|king| king.color) {
+            Some(Color::White) => self.white_castle_possible = (false, false),
+            Some(Color::Black) => self.black_castle_possible = (false, false),
+            None => {}
         }
 
         true
     }
 
-    pub fn find_king(&self, color: Color) -> Option<(usize, usize)> {
-        for row in 0..8 {
-            for col in 0..8 {
-                if let Some(piece) = self.squares[row][col] {
-                    if piece.kind == PieceType::King && piece.color == color {
-                        return Some((row, col));
-                    }
-                }
-            }
+    // The color of the King standing on `from`, if there is one
+    fn castling_color(&self, from: (usize, usize)) -> Option<Color> {
+        match self.squares[from.0][from.1] {
+            Some(Piece {
+                color,
+                kind: PieceType::King,
+            }) => Some(color),
+            _ => None,
         }
-        None
+    }
+
+    pub fn find_king(&self, color: Color) -> Option<(usize, usize)> {
+        let king = Piece {
+            color,
+            kind: PieceType::King,
+        };
+        bitboard::squares(self.bitboard(king)).next()
     }
 
     pub fn is_checkmate(&self, color: Color) -> bool {
-        if !self.is_in_check(color) {
+        let checkers = self.checkers(color);
+        if checkers.is_empty() {
             return false; // Not in check, can't be checkmate
         }
 
         let king_pos = self.find_king(color).unwrap();
-        let king_moves = self.generate_moves_for_piece(king_pos.0, king_pos.1);
 
         // 1. King escape:
-        for (_, to) in king_moves {
-            let mut temp_board = self.clone();
-            temp_board.apply_move((king_pos, to)); // Simulate the king's move
-            if !temp_board.is_in_check(color) {
-                return false; // King can escape
-            }
+        if !self
+            .legal_moves_for_piece(king_pos.0, king_pos.1)
+            .is_empty()
+        {
+            return false; // King can escape
         }
 
-        // 2. Block/capture:
-        // let checking_pieces = self.pieces_causing_check(color); // Helper function (see previous response)
-        let moves: Vec<_> = self
-            .generate_all_moves(color)
-            .into_iter()
-            .filter(|m| self.is_valid_move(m.0, m.1))
-            .collect();
-        moves.is_empty()
+        // 2. A double check cannot be blocked or captured away, only escaped
+        if checkers.len() > 1 {
+            return true;
+        }
+
+        // 3. Block/capture:
+        self.generate_all_legal_moves(color).is_empty()
+    }
+
+    // Whether `color` is in check, mated or stalemated, generating its legal moves once.
+    // `is_checkmate` answers faster when only mate matters.
+    pub fn game_status(&self, color: Color) -> GameStatus {
+        GameStatus::new(
+            self.is_in_check(color),
+            !self.generate_all_legal_moves(color).is_empty(),
+        )
     }
 
     pub fn is_draw(&self, color: Color) -> bool {
-        self.is_stalemate(color) || !self.has_sufficient_material() || self.half_move_clock >= 50
+        // The fifty-move rule counts moves by both players, i.e. 100 half-moves
+        self.game_status(color) == GameStatus::Stalemate
+            || self.is_insufficient_material()
+            || self.half_move_clock >= 100
     }
 
-    fn is_stalemate(&self, color: Color) -> bool {
-        if self.is_in_check(color) {
-            return false;
-        }
-        let moves: Vec<_> = self
-            .generate_all_moves(color)
-            .into_iter()
-            .filter(|m| self.is_valid_move(m.0, m.1))
-            .collect();
-        moves.is_empty()
+    // Whether the current position has now occurred three times. `history` holds the Zobrist
+    // keys of every position of the game, this one last; only positions since the last pawn
+    // move or capture can repeat it.
+    pub fn is_threefold_repetition(&self, history: &[u64]) -> bool {
+        let key = self.zobrist_key();
+        let window = history
+            .len()
+            .saturating_sub(self.half_move_clock as usize + 1);
+        history[window..]
+            .iter()
+            .filter(|&&previous| previous == key)
+            .count()
+            >= 3
     }
 
-    fn has_sufficient_material(&self) -> bool {
-        let mut white_major_material = 0;
-        let mut black_major_material = 0;
-        let mut white_minor_material = 0;
-        let mut black_minor_material = 0;
+    // Whether the side to move may claim a draw, by threefold repetition or the fifty-move rule
+    pub fn can_claim_draw(&self, history: &[u64]) -> bool {
+        self.half_move_clock >= 100 || self.is_threefold_repetition(history)
+    }
 
+    // Returns true if neither side can ever checkmate: bare kings, a lone knight or bishop,
+    // two knights against a bare king, or any number of bishops that all stand on squares of
+    // one colour. (Two knights cannot force mate, though a blunder can still allow one.)
+    pub fn is_insufficient_material(&self) -> bool {
+        let mut knights = [0; 2];
+        let mut bishop_on_light = [false; 2];
+        let mut bishop_on_dark = [false; 2];
         for row in 0..8 {
             for col in 0..8 {
                 if let Some(piece) = &self.squares[row][col] {
+                    let side = piece.color as usize;
                     match piece.kind {
-                        PieceType::Pawn | PieceType::Rook | PieceType::Queen => {
-                            if piece.color == Color::White {
-                                white_major_material += 1;
-                            } else {
-                                black_major_material += 1;
-                            }
-                        }
-                        PieceType::Knight | PieceType::Bishop => {
-                            if piece.color == Color::White {
-                                white_minor_material += 1;
-                            } else {
-                                black_minor_material += 1;
-                            }
-                        }
-                        _ => {}
+                        // Any pawn, rook or queen can still lead to checkmate
+                        PieceType::Pawn | PieceType::Rook | PieceType::Queen => return false,
+                        PieceType::Knight => knights[side] += 1,
+                        // a1 is dark, so a square is light when row + col is odd
+                        PieceType::Bishop if (row + col) % 2 == 1 => bishop_on_light[side] = true,
+                        PieceType::Bishop => bishop_on_dark[side] = true,
+                        PieceType::King => {}
                     }
                 }
             }
         }
-
-        // If either side has a Pawn, Rook, or Queen, checkmate is possible
-        if white_major_material > 0 || black_major_material > 0 {
-            return true;
-        }
-
-        // Special case: If both sides have only a King, it's a draw
-        if white_minor_material == 0 && black_minor_material == 0 {
-            return false;
-        }
-
-        // Special case: A single knight or bishop cannot force checkmate alone
-        if (white_minor_material == 1 && black_major_material == 0 && black_minor_material == 0)
-            || (black_minor_material == 1 && white_major_material == 0 && white_minor_material == 0)
-        {
-            return false;
-        }
-
-        // If both sides have minor pieces but no major pieces, it's a draw unless there are at least two bishops
-        if white_major_material == 0 && black_major_material == 0 {
-            if white_minor_material <= 1 && black_minor_material <= 1 {
-                return false;
-            }
+        let any_light = bishop_on_light[0] || bishop_on_light[1];
+        let any_dark = bishop_on_dark[0] || bishop_on_dark[1];
+        match (knights, any_light || any_dark) {
+            // Bishops confined to one colour can never cover a king's escape squares
+            ([0, 0], _) => !(any_light && any_dark),
+            // One or two knights, all on one side, and nothing else
+            ([n, 0] | [0, n], false) => n <= 2,
+            _ => false,
         }
-
-        // If no early draw conditions matched, checkmate is still possible
-        true
     }
 
     pub fn is_in_check(&self, color: Color) -> bool {
@@ -731,235 +1654,2092 @@ impl Board {
     }
 
     pub fn is_valid_move(&self, from: (usize, usize), to: (usize, usize)) -> bool {
+        self.validate_move(from, to).is_ok()
+    }
+
+    // Whether the side to move may play from `from` to `to`, and if not, why not
+    pub fn validate_move(&self, from: (usize, usize), to: (usize, usize)) -> Result<(), MoveError> {
         // Ensure move is within board bounds
-        if from == to || from.0 >= 8 || from.1 >= 8 || to.0 >= 8 || to.1 >= 8 {
-            return false;
+        if from.0 >= 8 || from.1 >= 8 || to.0 >= 8 || to.1 >= 8 {
+            return Err(MoveError::OutOfBounds);
         }
 
         // Check if there is a piece at the starting position
-        let piece = match self.squares[from.0][from.1] {
-            Some(p) => p,
-            None => return false,
-        };
+        let piece = self.squares[from.0][from.1].ok_or(MoveError::NoPieceAtSource)?;
+        if piece.color != self.side_to_move {
+            return Err(MoveError::WrongColor);
+        }
+        if from == to {
+            return Err(MoveError::NotAPieceMove);
+        }
 
-        // // Check if it is castle move
-        if self.can_castle(from, to) {
-            return true;
+        // Castling has its own rules, and in Chess960 lands the King on its own Rook
+        if self.is_castling_move(from, to, piece.color) {
+            let home_row = match piece.color {
+                Color::White => 0,
+                Color::Black => 7,
+            };
+            let rights = match piece.color {
+                Color::White => self.white_castle_possible,
+                Color::Black => self.black_castle_possible,
+            };
+            let kingside = self.castling_side(from, to) == Some(true);
+            return if from.0 != home_row {
+                Err(MoveError::NotAPieceMove)
+            } else if !(if kingside { rights.1 } else { rights.0 }) {
+                Err(MoveError::NoCastlingRights)
+            } else if !self.can_castle_unsafe(from, to) {
+                Err(MoveError::BlockedPath)
+            } else if !self.can_castle(from, to) {
+                Err(MoveError::CastleThroughCheck)
+            } else {
+                Ok(())
+            };
         }
 
-        // Ensure the piece is not capturing its own color
-        if let Some(target_piece) = self.squares[to.0][to.1] {
-            if target_piece.color == piece.color {
-                return false;
-            }
+        if self.squares[to.0][to.1].is_some_and(|target| target.color == piece.color) {
+            return Err(MoveError::OwnPieceOnTarget);
         }
 
-        // Check if the move is in the piece’s legal moves
-        let legal_moves = self.generate_moves_for_piece(from.0, from.1);
-        // println!("Legal moves: {:?}", legal_moves);
-        if !legal_moves.contains(&(from, to)) {
-            return false;
+        // Check if the move is in the piece’s pseudo-legal moves. A promotion is legal to
+        // every piece alike, so the squares settle it.
+        let pseudo_moves = self.generate_moves_for_piece(from.0, from.1);
+        if !pseudo_moves.iter().any(|m| m.from == from && m.to == to) {
+            return Err(if self.is_blocked(piece, from, to) {
+                MoveError::BlockedPath
+            } else {
+                MoveError::NotAPieceMove
+            });
         }
 
-        // Simulate the move to check if it leaves the king in check
-        let mut simulated_board = self.clone();
-        simulated_board.apply_move((from, to));
-        if simulated_board.is_in_check(piece.color) {
-            println!("In check: {:?}", simulated_board);
-            return false; // Move is invalid if it leaves the king in check
+        if self.is_legal_pseudo_move(Move::new(from, to), piece.color) {
+            Ok(())
+        } else {
+            Err(MoveError::LeavesKingInCheck)
         }
+    }
 
-        true
+    // Whether `piece` would move from `from` to `to` on an empty board, but here finds a piece
+    // in the way: one between the squares, or for a pawn's push, on the target itself
+    fn is_blocked(&self, piece: Piece, from: (usize, usize), to: (usize, usize)) -> bool {
+        let dr = to.0 as isize - from.0 as isize;
+        let dc = to.1 as isize - from.1 as isize;
+        let (forward, start_row) = match piece.color {
+            Color::White => (1, 1),
+            Color::Black => (-1, 6),
+        };
+        let moves_that_way = match piece.kind {
+            PieceType::Bishop => dr.abs() == dc.abs(),
+            PieceType::Rook => dr == 0 || dc == 0,
+            PieceType::Queen => dr.abs() == dc.abs() || dr == 0 || dc == 0,
+            PieceType::Pawn => {
+                dc == 0 && (dr == forward || (dr == 2 * forward && from.0 == start_row))
+            }
+            PieceType::Knight | PieceType::King => false,
+        };
+        let pawn_push_onto_piece =
+            piece.kind == PieceType::Pawn && self.squares[to.0][to.1].is_some();
+        moves_that_way
+            && (pawn_push_onto_piece
+                || between(from, to)
+                    .iter()
+                    .any(|&(row, col)| self.squares[row][col].is_some()))
     }
-}
 
-pub fn opposite_color(color: Color) -> Color {
-    match color {
-        Color::White => Color::Black,
-        Color::Black => Color::White,
+    // `m` with a queen as its promotion piece when it takes a pawn to the last rank without
+    // naming one, as coordinate moves were written before underpromotions were generated
+    pub fn with_queen_promotion(&self, m: Move) -> Move {
+        let promotes = m.promotion.is_none()
+            && (m.to.0 == 0 || m.to.0 == 7)
+            && self
+                .piece_at(m.from.0, m.from.1)
+                .is_some_and(|piece| piece.kind == PieceType::Pawn);
+        if promotes {
+            Move::promoting(m.from, m.to, PieceType::Queen)
+        } else {
+            m
+        }
     }
-}
 
-fn get_piece_value(piece: &Piece) -> i32 {
-    match piece.kind {
-        PieceType::Pawn => 100,
-        PieceType::Knight => 320,
-        PieceType::Bishop => 330,
-        PieceType::Rook => 500,
-        PieceType::Queen => 900,
-        PieceType::King => 20000,
+    // Returns the fully legal moves of the piece on (row, col), generating its moves only once
+    pub fn legal_moves_for_piece(&self, row: usize, col: usize) -> Vec<Move> {
+        let color = match self.squares[row][col] {
+            Some(piece) => piece.color,
+            None => return Vec::new(),
+        };
+        let pins = self.pinned_pieces(color);
+        let in_check = self.is_in_check(color);
+        self.generate_moves_for_piece(row, col)
+            .into_iter()
+            .filter(|m| self.is_legal_with_pins(*m, color, &pins, in_check))
+            .collect()
     }
-}
 
-fn evaluate_position(board: &Board) -> i32 {
-    let mut score = 0;
-    for row in 0..8 {
-        for col in 0..8 {
-            if let Some(piece) = board.squares[row][col] {
-                let mut piece_score = get_piece_value(&piece);
-                if (2..=5).contains(&row) && (2..=5).contains(&col) {
-                    piece_score += 10;
-                }
-                score += if piece.color == Color::White {
-                    piece_score
-                } else {
-                    -piece_score
-                };
-            }
+    // The number of legal moves `color` has in this position
+    pub fn legal_move_count(&self, color: Color) -> usize {
+        self.generate_all_legal_moves(color).len()
+    }
+
+    // The stage of the game, from the pieces besides kings and pawns left on the board
+    pub fn game_phase(&self) -> Phase {
+        let units: u32 = [
+            (PieceType::Knight, 1),
+            (PieceType::Bishop, 1),
+            (PieceType::Rook, 2),
+            (PieceType::Queen, 4),
+        ]
+        .iter()
+        .map(|&(kind, weight)| {
+            let white = self.bitboard(Piece {
+                color: Color::White,
+                kind,
+            });
+            let black = self.bitboard(Piece {
+                color: Color::Black,
+                kind,
+            });
+            (white | black).count_ones() * weight
+        })
+        .sum();
+        Phase::from_units(units)
+    }
+
+    // Counts the leaf positions `depth` moves ahead of the side to move, for checking the move
+    // generator against published numbers
+    pub fn perft(&self, depth: u32) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+        let moves = self.generate_all_legal_moves(self.side_to_move);
+        if depth == 1 {
+            return moves.len() as u64;
         }
+        moves
+            .into_iter()
+            .map(|m| {
+                let mut next = self.clone();
+                next.apply_move(m);
+                next.perft(depth - 1)
+            })
+            .sum()
     }
-    score
-}
 
-fn score_move(board: &Board, m: &((usize, usize), (usize, usize))) -> i32 {
-    let ((_, _), (to_row, to_col)) = *m;
-    let mut score = 0;
-    if let Some(captured_piece) = board.squares[to_row][to_col] {
-        score += get_piece_value(&captured_piece);
+    // The perft count under each legal move of the side to move, in generation order, to
+    // find which move a wrong total comes from (see `format_perft_divide`)
+    pub fn perft_divide(&self, depth: u32) -> Vec<(Move, u64)> {
+        self.generate_all_legal_moves(self.side_to_move)
+            .into_iter()
+            .map(|m| {
+                let mut next = self.clone();
+                next.apply_move(m);
+                (m, next.perft(depth.saturating_sub(1)))
+            })
+            .collect()
     }
-    if (2..=5).contains(&to_row) && (2..=5).contains(&to_col) {
-        score += 10;
+
+    // Returns the fully legal destination squares of the piece on (row, col), for highlighting
+    pub fn legal_moves_from(&self, row: usize, col: usize) -> Vec<(usize, usize)> {
+        let mut targets: Vec<(usize, usize)> = self
+            .legal_moves_for_piece(row, col)
+            .into_iter()
+            .map(|m| m.to)
+            .collect();
+        // A promotion reaches its square once per piece
+        targets.dedup();
+        targets
     }
-    score
-}
 
-fn alpha_beta(
-    board: &Board,
+    // Writes a legal move in standard algebraic notation, e.g. "Nf3", "exd5", "O-O", "e8=N"
+    // or "Qxf7#", naming the file or rank it leaves when another piece of the same kind
+    // could reach the same square
+    pub fn move_to_san(&self, m: Move) -> String {
+        let piece = match self.piece_at(m.from.0, m.from.1) {
+            Some(piece) => piece,
+            None => return move_to_uci(m),
+        };
+        let mut san = if let Some(kingside) = self
+            .castling_side(m.from, m.to)
+            .filter(|_| piece.kind == PieceType::King)
+        {
+            if kingside { "O-O" } else { "O-O-O" }.to_string()
+        } else {
+            let from = square_to_algebraic(m.from);
+            let is_capture = self.piece_at(m.to.0, m.to.1).is_some()
+                || (piece.kind == PieceType::Pawn && m.from.1 != m.to.1);
+            let mut san = String::new();
+            if piece.kind == PieceType::Pawn {
+                if is_capture {
+                    san.push_str(&from[..1]);
+                }
+            } else {
+                san.push(piece.kind.letter());
+                let rivals: Vec<Move> = self
+                    .generate_all_legal_moves(piece.color)
+                    .into_iter()
+                    .filter(|other| {
+                        other.to == m.to
+                            && other.from != m.from
+                            && self.piece_at(other.from.0, other.from.1).map(|p| p.kind)
+                                == Some(piece.kind)
+                    })
+                    .collect();
+                if !rivals.is_empty() {
+                    if rivals.iter().all(|other| other.from.1 != m.from.1) {
+                        san.push_str(&from[..1]);
+                    } else if rivals.iter().all(|other| other.from.0 != m.from.0) {
+                        san.push_str(&from[1..]);
+                    } else {
+                        san.push_str(&from);
+                    }
+                }
+            }
+            if is_capture {
+                san.push('x');
+            }
+            san.push_str(&square_to_algebraic(m.to));
+            if let Some(kind) = m.promotion {
+                san.push('=');
+                san.push(kind.letter());
+            }
+            san
+        };
+        let mut after = self.clone();
+        after.apply_move(m);
+        let opponent = opposite_color(piece.color);
+        if after.is_checkmate(opponent) {
+            san.push('#');
+        } else if after.is_in_check(opponent) {
+            san.push('+');
+        }
+        san
+    }
+
+    // Reads a move in standard algebraic notation, as `move_to_san` writes it, among the legal
+    // moves of the side to move. Castling may be written with zeros, the capture sign is not
+    // checked, check and mate signs and annotation glyphs are ignored, and a pawn reaching
+    // the last rank without a promotion piece becomes a queen.
+    pub fn parse_san(&self, san: &str) -> Result<Move, ChessError> {
+        let invalid = || ChessError::InvalidNotation(san.to_string());
+        let text = san.trim().trim_end_matches(['+', '#', '!', '?']);
+        let legal = self.generate_all_legal_moves(self.side_to_move);
+
+        let castle_side = match text {
+            "O-O" | "0-0" => Some(true),
+            "O-O-O" | "0-0-0" => Some(false),
+            _ => None,
+        };
+        if let Some(kingside) = castle_side {
+            return legal
+                .into_iter()
+                .find(|m| {
+                    self.is_castling_move(m.from, m.to, self.side_to_move)
+                        && self.castling_side(m.from, m.to) == Some(kingside)
+                })
+                .ok_or_else(|| ChessError::IllegalMove(san.to_string()));
+        }
+
+        if !text.is_ascii() {
+            return Err(invalid());
+        }
+        // "e8=Q", or "e8Q" as some programs write it
+        let (body, promotion) = match text.split_once('=') {
+            Some((body, letter)) => {
+                let mut letters = letter.chars();
+                let kind = letters
+                    .next()
+                    .and_then(PieceType::from_letter)
+                    .filter(|kind| PROMOTION_PIECES.contains(kind))
+                    .ok_or_else(invalid)?;
+                if letters.next().is_some() {
+                    return Err(invalid());
+                }
+                (body, Some(kind))
+            }
+            None => match text.chars().last() {
+                Some(letter @ ('Q' | 'R' | 'B' | 'N')) if text.len() > 2 => {
+                    (&text[..text.len() - 1], PieceType::from_letter(letter))
+                }
+                _ => (text, None),
+            },
+        };
+        // Piece letters are uppercase, so a lowercase "b" is the b-file
+        let (kind, rest) = match body.chars().next() {
+            Some(letter @ ('K' | 'Q' | 'R' | 'B' | 'N')) => (
+                PieceType::from_letter(letter).ok_or_else(invalid)?,
+                &body[1..],
+            ),
+            _ => (PieceType::Pawn, body),
+        };
+        let rest = rest.replacen('x', "", 1);
+        if rest.len() < 2 {
+            return Err(invalid());
+        }
+        let (hint, target) = rest.split_at(rest.len() - 2);
+        let to = algebraic_to_square(target).ok_or_else(invalid)?;
+        // The file, rank or square the piece leaves from, when written
+        let (mut from_col, mut from_row) = (None, None);
+        for c in hint.chars() {
+            match c {
+                'a'..='h' if from_col.is_none() && from_row.is_none() => {
+                    from_col = Some(c as usize - 'a' as usize)
+                }
+                '1'..='8' if from_row.is_none() => from_row = Some(c as usize - '1' as usize),
+                _ => return Err(invalid()),
+            }
+        }
+        if kind == PieceType::Pawn && promotion.is_none() && (to.0 == 0 || to.0 == 7) {
+            return self.parse_san(&format!("{}=Q", text));
+        }
+
+        let candidates: Vec<Move> = legal
+            .into_iter()
+            .filter(|m| {
+                m.to == to
+                    && m.promotion == promotion
+                    && from_col.is_none_or(|col| m.from.1 == col)
+                    && from_row.is_none_or(|row| m.from.0 == row)
+                    && self.piece_at(m.from.0, m.from.1).map(|piece| piece.kind) == Some(kind)
+            })
+            .collect();
+        match candidates[..] {
+            [m] => Ok(m),
+            [] => Err(ChessError::IllegalMove(san.to_string())),
+            _ => Err(ChessError::AmbiguousMove(san.to_string())),
+        }
+    }
+
+    // Reads a move in coordinate notation, "e2e4" or "e7e8q", among the legal moves of the
+    // side to move; a promotion without a piece letter is to a queen
+    pub fn parse_uci_move(&self, uci: &str) -> Result<Move, ChessError> {
+        let m = uci_to_move(uci)
+            .map(|m| self.with_queen_promotion(m))
+            .ok_or_else(|| ChessError::InvalidNotation(uci.to_string()))?;
+        if self
+            .generate_all_legal_moves(self.side_to_move)
+            .contains(&m)
+        {
+            Ok(m)
+        } else {
+            Err(ChessError::IllegalMove(uci.to_string()))
+        }
+    }
+
+    // Reads a move in either coordinate or standard algebraic notation, trying coordinates
+    // first since no SAN move looks like one
+    pub fn parse_move(&self, text: &str) -> Result<Move, ChessError> {
+        self.parse_uci_move(text).or_else(|error| match error {
+            ChessError::InvalidNotation(_) => self.parse_san(text),
+            error => Err(error),
+        })
+    }
+
+    // A line of moves from this position in numbered algebraic notation, e.g. "12... Nf6
+    // 13. Nc3 d5"
+    pub fn line_to_san(&self, line: &[Move]) -> String {
+        let mut board = self.clone();
+        let mut tokens = Vec::new();
+        for (index, &m) in line.iter().enumerate() {
+            match board.side_to_move {
+                Color::White => tokens.push(format!("{}.", board.full_move_number)),
+                Color::Black if index == 0 => tokens.push(format!("{}...", board.full_move_number)),
+                Color::Black => {}
+            }
+            tokens.push(board.move_to_san(m));
+            board.apply_move(m);
+        }
+        tokens.join(" ")
+    }
+
+    // The index in `line` of the first move by the side to move that gives up at least
+    // SACRIFICE_THRESHOLD of material once the reply is played, if any. A plain exchange
+    // wins back what it gives, so only a real sacrifice counts.
+    pub fn first_sacrifice(&self, line: &[Move]) -> Option<usize> {
+        let color = self.side_to_move;
+        let sign = match color {
+            Color::White => 1,
+            Color::Black => -1,
+        };
+        let mut board = self.clone();
+        for (index, pair) in line.chunks(2).enumerate() {
+            let before = sign * compute_material_balance(&board);
+            for &m in pair {
+                board.apply_move(m);
+            }
+            if pair.len() == 2
+                && before - sign * compute_material_balance(&board) >= SACRIFICE_THRESHOLD
+            {
+                return Some(index * 2);
+            }
+        }
+        None
+    }
+
+    // Checks a move already known to be pseudo-legal: castling must not start in, pass through
+    // or end in check, and any other move must not leave the mover's king attacked. The
+    // attacks on the king are looked up on the occupancy after the move, so nothing is played.
+    fn is_legal_pseudo_move(&self, m: Move, color: Color) -> bool {
+        if self.is_castling_move(m.from, m.to, color) {
+            return self.can_castle(m.from, m.to);
+        }
+        let Move { from, to, .. } = m;
+        let piece = match self.squares[from.0][from.1] {
+            Some(piece) => piece,
+            None => return false,
+        };
+        let king = match piece.kind {
+            PieceType::King => to,
+            _ => match self.find_king(color) {
+                Some(king) => king,
+                None => return true,
+            },
+        };
+        // The captured piece, which for en passant stands beside the pawn rather than on `to`
+        let captured = if piece.kind == PieceType::Pawn && Some(to) == self.en_passant_target {
+            bitboard::square_bit(from.0, to.1)
+        } else {
+            bitboard::square_bit(to.0, to.1)
+        };
+        let occupied = (self.occupied[0] | self.occupied[1])
+            & !bitboard::square_bit(from.0, from.1)
+            & !captured
+            | bitboard::square_bit(to.0, to.1);
+        self.attackers_through(king, opposite_color(color), occupied, captured) == 0
+    }
+
+    // Like `is_legal_pseudo_move`, but uses the pins of `color` to decide most moves without
+    // looking at the attacks on the king. King moves, en passant (which can expose the king
+    // along the rank) and any move made while in check still go through the full check.
+    fn is_legal_with_pins(
+        &self,
+        m: Move,
+        color: Color,
+        pins: &[((usize, usize), Direction)],
+        in_check: bool,
+    ) -> bool {
+        let Move { from, to, .. } = m;
+        let piece = match self.squares[from.0][from.1] {
+            Some(piece) => piece,
+            None => return false,
+        };
+        let is_en_passant =
+            piece.kind == PieceType::Pawn && from.1 != to.1 && self.squares[to.0][to.1].is_none();
+        if in_check || piece.kind == PieceType::King || is_en_passant {
+            return self.is_legal_pseudo_move(m, color);
+        }
+        match pins.iter().find(|&&(square, _)| square == from) {
+            // A pinned piece may only slide along the line between its king and the pinner
+            Some(&(_, (dr, dc))) => {
+                let king = match self.find_king(color) {
+                    Some(king) => king,
+                    None => return false,
+                };
+                let (to_dr, to_dc) = (
+                    to.0 as isize - king.0 as isize,
+                    to.1 as isize - king.1 as isize,
+                );
+                to_dr * dc == to_dc * dr && to_dr.signum() == dr && to_dc.signum() == dc
+            }
+            None => true,
+        }
+    }
+
+    // Returns each piece of `color` that is absolutely pinned to its own king, with the
+    // direction (row step, col step) of the pin ray from the king towards the pinning piece.
+    // A pinned piece can still move along that ray.
+    pub fn pinned_pieces(&self, color: Color) -> Vec<((usize, usize), Direction)> {
+        let mut pinned = Vec::new();
+        let (king_row, king_col) = match self.find_king(color) {
+            Some((row, col)) => (row as isize, col as isize),
+            None => return pinned,
+        };
+        for ((dr, dc), slider) in SLIDING_RAYS {
+            // Walk outward from the king: a single friendly piece followed by an enemy slider
+            // moving along this ray is pinned
+            let mut shield = None;
+            let (mut r, mut c) = (king_row + dr, king_col + dc);
+            while (0..8).contains(&r) && (0..8).contains(&c) {
+                if let Some(piece) = self.squares[r as usize][c as usize] {
+                    match shield {
+                        None if piece.color == color => shield = Some((r as usize, c as usize)),
+                        None => break,
+                        Some((shield_row, shield_col)) => {
+                            if piece.color != color
+                                && (piece.kind == slider || piece.kind == PieceType::Queen)
+                            {
+                                pinned.push(((shield_row, shield_col), (dr, dc)));
+                            }
+                            break;
+                        }
+                    }
+                }
+                r += dr;
+                c += dc;
+            }
+        }
+        pinned
+    }
+}
+
+// What `Board::random_position` puts on the board
+#[derive(Clone, Copy, Debug)]
+pub struct PositionConstraints {
+    pub pieces: usize,     // Pieces besides the two kings, at most 30
+    pub allow_pawns: bool, // Whether any of those pieces may be pawns
+    pub castling: bool, // Grant castling rights at random where a king and rook are still at home
+}
+
+impl Default for PositionConstraints {
+    fn default() -> Self {
+        PositionConstraints {
+            pieces: 10,
+            allow_pawns: true,
+            castling: true,
+        }
+    }
+}
+
+// The stage of a game, judged by the material left: see `Board::game_phase`
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Phase {
+    Opening,
+    Middlegame,
+    Endgame,
+}
+
+impl Phase {
+    // Phase units of the start position: a knight or bishop counts 1, a rook 2, a queen 4
+    pub const FULL_UNITS: u32 = 24;
+
+    // Until more than a minor piece a side (or one rook) is gone, the game is in its opening;
+    // once no more than a rook and two minor pieces a side, or their worth, remain, it is an
+    // endgame
+    fn from_units(units: u32) -> Phase {
+        if units >= Phase::FULL_UNITS - 2 {
+            Phase::Opening
+        } else if units > 8 {
+            Phase::Middlegame
+        } else {
+            Phase::Endgame
+        }
+    }
+}
+
+// The legal moves of one side in one position, generated once and shared by everything that
+// asks about that position: the highlighting, checking a clicked move and the game-over tests
+#[derive(Clone, Debug)]
+pub struct LegalMoves {
+    key: u64, // Zobrist key of the position the moves were generated in
+    color: Color,
+    in_check: bool,
+    moves: Vec<Move>,
+}
+
+impl LegalMoves {
+    pub fn new(board: &Board, color: Color) -> Self {
+        LegalMoves {
+            key: board.zobrist_key(),
+            color,
+            in_check: board.is_in_check(color),
+            moves: board.generate_all_legal_moves(color),
+        }
+    }
+
+    // Whether these are still the moves of `color` on `board`; once a move has been played
+    // they are not, and have to be generated again
+    pub fn is_for(&self, board: &Board, color: Color) -> bool {
+        self.key == board.zobrist_key() && self.color == color
+    }
+
+    pub fn moves(&self) -> &[Move] {
+        &self.moves
+    }
+
+    pub fn contains(&self, m: Move) -> bool {
+        self.moves.contains(&m)
+    }
+
+    // The destination squares of the piece on (row, col), for highlighting
+    pub fn from(&self, row: usize, col: usize) -> Vec<(usize, usize)> {
+        let mut targets: Vec<(usize, usize)> = self
+            .moves
+            .iter()
+            .filter(|m| m.from == (row, col))
+            .map(|m| m.to)
+            .collect();
+        // A promotion reaches its square once per piece
+        targets.dedup();
+        targets
+    }
+
+    // Like `Board::game_status`, without generating the moves again
+    pub fn status(&self) -> GameStatus {
+        GameStatus::new(self.in_check, !self.moves.is_empty())
+    }
+
+    // Like `Board::is_draw` for the position the moves belong to
+    pub fn is_draw(&self, board: &Board) -> bool {
+        self.status() == GameStatus::Stalemate
+            || board.is_insufficient_material()
+            || board.half_move_clock >= 100
+    }
+}
+
+// The basic endgames, named by the pieces each side has left; the stronger side comes first,
+// whichever color it is
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum EndgameType {
+    KingVsKing,
+    KBvK,
+    KNvK,
+    KRvK,
+    KQvK,
+    KPvK,
+    KBBvK,
+    KBNvK,
+    KRPvKR,
+    KQvKR,
+    Unknown,
+}
+
+impl EndgameType {
+    pub fn name(&self) -> &str {
+        match *self {
+            EndgameType::KingVsKing => "King vs King",
+            EndgameType::KBvK => "King and Bishop vs King",
+            EndgameType::KNvK => "King and Knight vs King",
+            EndgameType::KRvK => "King and Rook vs King",
+            EndgameType::KQvK => "King and Queen vs King",
+            EndgameType::KPvK => "King and Pawn vs King",
+            EndgameType::KBBvK => "King and two Bishops vs King",
+            EndgameType::KBNvK => "King, Bishop and Knight vs King",
+            EndgameType::KRPvKR => "King, Rook and Pawn vs King and Rook",
+            EndgameType::KQvKR => "King and Queen vs King and Rook",
+            EndgameType::Unknown => "Unknown endgame",
+        }
+    }
+
+    // Neither side can force mate
+    pub fn is_theoretical_draw(&self) -> bool {
+        matches!(
+            self,
+            EndgameType::KingVsKing | EndgameType::KBvK | EndgameType::KNvK
+        )
+    }
+
+    // The stronger side can force mate within this many moves from any position of the
+    // endgame (the longest mates with best play), unless the weaker side wins material first.
+    // None when the result depends on the position.
+    pub fn moves_to_mate(&self) -> Option<u32> {
+        match *self {
+            EndgameType::KQvK => Some(10),
+            EndgameType::KRvK => Some(16),
+            EndgameType::KBBvK => Some(19),
+            EndgameType::KBNvK => Some(33),
+            EndgameType::KQvKR => Some(35),
+            _ => None,
+        }
+    }
+}
+
+// Classifies the position by the pieces left on the board. Two bishops only count as KBBvK
+// when they stand on opposite colors, since same-colored bishops cannot mate.
+pub fn classify_endgame(board: &Board) -> EndgameType {
+    // The pieces of each side besides the king, strongest first, e.g. "QR" or "BN"
+    let material = |color: Color| {
+        [
+            PieceType::Queen,
+            PieceType::Rook,
+            PieceType::Bishop,
+            PieceType::Knight,
+            PieceType::Pawn,
+        ]
+        .iter()
+        .flat_map(|&kind| {
+            let count = board.bitboard(Piece { color, kind }).count_ones() as usize;
+            std::iter::repeat_n(kind.letter(), count)
+        })
+        .collect::<String>()
+    };
+    let bishops_on_both_colors = |color: Color| {
+        let bishops = board.bitboard(Piece {
+            color,
+            kind: PieceType::Bishop,
+        });
+        // Bit row * 8 + col is a light square when row + col is odd
+        const LIGHT_SQUARES: Bitboard = 0x55AA_55AA_55AA_55AA;
+        bishops & LIGHT_SQUARES != 0 && bishops & !LIGHT_SQUARES != 0
+    };
+
+    let (white, black) = (material(Color::White), material(Color::Black));
+    for (strong, weak, color) in [
+        (&white, &black, Color::White),
+        (&black, &white, Color::Black),
+    ] {
+        let endgame = match (strong.as_str(), weak.as_str()) {
+            ("", "") => EndgameType::KingVsKing,
+            ("B", "") => EndgameType::KBvK,
+            ("N", "") => EndgameType::KNvK,
+            ("R", "") => EndgameType::KRvK,
+            ("Q", "") => EndgameType::KQvK,
+            ("P", "") => EndgameType::KPvK,
+            ("BB", "") if bishops_on_both_colors(color) => EndgameType::KBBvK,
+            ("BN", "") => EndgameType::KBNvK,
+            ("RP", "R") => EndgameType::KRPvKR,
+            ("Q", "R") => EndgameType::KQvKR,
+            _ => continue,
+        };
+        return endgame;
+    }
+    EndgameType::Unknown
+}
+
+// Returns the squares strictly between `a` and `b` if they share a rank, file or diagonal,
+// ordered from `a` towards `b`; unaligned or adjacent squares have nothing in between
+pub fn squares_between(a: (usize, usize), b: (usize, usize)) -> Vec<(usize, usize)> {
+    between(a, b).to_vec()
+}
+
+// Table lookup behind `squares_between`, avoiding the allocation
+fn between(a: (usize, usize), b: (usize, usize)) -> &'static [(usize, usize)] {
+    type BetweenTable = [[Vec<(usize, usize)>; 64]; 64];
+    static BETWEEN: OnceLock<Box<BetweenTable>> = OnceLock::new();
+    let table = BETWEEN.get_or_init(|| {
+        Box::new(std::array::from_fn(|from| {
+            std::array::from_fn(|to| {
+                compute_squares_between((from / 8, from % 8), (to / 8, to % 8))
+            })
+        }))
+    });
+    &table[a.0 * 8 + a.1][b.0 * 8 + b.1]
+}
+
+fn compute_squares_between(a: (usize, usize), b: (usize, usize)) -> Vec<(usize, usize)> {
+    let dr = b.0 as isize - a.0 as isize;
+    let dc = b.1 as isize - a.1 as isize;
+    // Aligned only along a rank, a file or a diagonal
+    if (dr == 0 && dc == 0) || (dr != 0 && dc != 0 && dr.abs() != dc.abs()) {
+        return Vec::new();
+    }
+    let (step_r, step_c) = (dr.signum(), dc.signum());
+    let mut squares = Vec::new();
+    let (mut r, mut c) = (a.0 as isize + step_r, a.1 as isize + step_c);
+    while (r, c) != (b.0 as isize, b.1 as isize) {
+        squares.push((r as usize, c as usize));
+        r += step_r;
+        c += step_c;
+    }
+    squares
+}
+
+// Formats a square in algebraic notation, e.g. (1, 4) -> "e2"
+pub fn square_to_algebraic(square: (usize, usize)) -> String {
+    let (row, col) = square;
+    format!("{}{}", (b'a' + col as u8) as char, row + 1)
+}
+
+// Parses a square in algebraic notation, e.g. "e2" -> (1, 4)
+pub fn algebraic_to_square(square: &str) -> Option<(usize, usize)> {
+    let bytes = square.as_bytes();
+    if bytes.len() != 2 || !(b'a'..=b'h').contains(&bytes[0]) || !(b'1'..=b'8').contains(&bytes[1])
+    {
+        return None;
+    }
+    Some(((bytes[1] - b'1') as usize, (bytes[0] - b'a') as usize))
+}
+
+// Lists a perft divide one move per line, "e2e4: 20", sorted by move, followed by the total
+pub fn format_perft_divide(divide: &[(Move, u64)]) -> String {
+    let mut lines: Vec<String> = divide
+        .iter()
+        .map(|&(m, nodes)| format!("{}: {}", move_to_uci(m), nodes))
+        .collect();
+    lines.sort();
+    let total: u64 = divide.iter().map(|&(_, nodes)| nodes).sum();
+    format!("{}\n\nNodes searched: {}", lines.join("\n"), total)
+}
+
+// Formats a move in coordinate notation, e.g. "e2e4", or "e7e8n" for a promotion
+pub fn move_to_uci(m: Move) -> String {
+    let promotion = m.promotion.map_or(String::new(), |kind| {
+        kind.letter().to_ascii_lowercase().to_string()
+    });
+    format!(
+        "{}{}{}",
+        square_to_algebraic(m.from),
+        square_to_algebraic(m.to),
+        promotion
+    )
+}
+
+// Reads a move in coordinate notation, "e2e4" or "e7e8n" with the promotion letter in either
+// case. Whether the move can be played is up to the caller, see `Board::with_queen_promotion`.
+pub fn uci_to_move(uci: &str) -> Option<Move> {
+    if !uci.is_ascii() || !(4..=5).contains(&uci.len()) {
+        return None;
+    }
+    let from = algebraic_to_square(&uci[..2])?;
+    let to = algebraic_to_square(&uci[2..4])?;
+    match uci[4..].chars().next() {
+        None => Some(Move::new(from, to)),
+        Some(letter) => PieceType::from_letter(letter)
+            .filter(|kind| PROMOTION_PIECES.contains(kind))
+            .map(|kind| Move::promoting(from, to, kind)),
+    }
+}
+
+pub fn opposite_color(color: Color) -> Color {
+    match color {
+        Color::White => Color::Black,
+        Color::Black => Color::White,
+    }
+}
+
+// Random keys used to hash positions, see `Board::zobrist_key`
+struct ZobristKeys {
+    pieces: [[u64; 64]; 12],
+    black_to_move: u64,
+    castling: [u64; 4],
+    en_passant_file: [u64; 8],
+}
+
+fn zobrist_keys() -> &'static ZobristKeys {
+    static KEYS: OnceLock<ZobristKeys> = OnceLock::new();
+    KEYS.get_or_init(|| {
+        // SplitMix64 with a fixed seed, so hashes are identical across runs
+        let mut state: u64 = 0x9E37_79B9_7F4A_7C15;
+        let mut next = || {
+            state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+            let mut z = state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+            z ^ (z >> 31)
+        };
+        let mut keys = ZobristKeys {
+            pieces: [[0; 64]; 12],
+            black_to_move: 0,
+            castling: [0; 4],
+            en_passant_file: [0; 8],
+        };
+        for piece_keys in keys.pieces.iter_mut() {
+            for key in piece_keys.iter_mut() {
+                *key = next();
+            }
+        }
+        keys.black_to_move = next();
+        for key in keys
+            .castling
+            .iter_mut()
+            .chain(keys.en_passant_file.iter_mut())
+        {
+            *key = next();
+        }
+        keys
+    })
+}
+
+// Index of a piece in the Zobrist piece table: White pieces first, then Black
+fn piece_index(piece: &Piece) -> usize {
+    let kind = match piece.kind {
+        PieceType::King => 0,
+        PieceType::Queen => 1,
+        PieceType::Rook => 2,
+        PieceType::Bishop => 3,
+        PieceType::Knight => 4,
+        PieceType::Pawn => 5,
+    };
+    match piece.color {
+        Color::White => kind,
+        Color::Black => kind + 6,
+    }
+}
+
+fn get_piece_value(piece: &Piece) -> i32 {
+    match piece.kind {
+        PieceType::Pawn => 100,
+        PieceType::Knight => 320,
+        PieceType::Bishop => 330,
+        PieceType::Rook => 500,
+        PieceType::Queen => 900,
+        PieceType::King => 20000,
+    }
+}
+
+// Bonus for a piece standing on (row, col): the centre squares are worth holding
+fn piece_square_value(row: usize, col: usize) -> i32 {
+    if (2..=5).contains(&row) && (2..=5).contains(&col) {
+        10
+    } else {
+        0
+    }
+}
+
+// Static evaluation from White's point of view: the network set by `nnue::set_network` when
+// built with the `nnue` feature and one has been set, the hand-crafted evaluation otherwise
+pub fn evaluate_position(board: &Board) -> i32 {
+    #[cfg(feature = "nnue")]
+    if let Some(network) = nnue::network() {
+        return network.evaluate(board);
+    }
+    hand_crafted_evaluation(board)
+}
+
+// The hand-crafted evaluation, from the sums `set_piece` keeps
+fn hand_crafted_evaluation(board: &Board) -> i32 {
+    let white = board.material[Color::White as usize] + board.placement[Color::White as usize];
+    let black = board.material[Color::Black as usize] + board.placement[Color::Black as usize];
+    white - black
+}
+
+// The same evaluation summed over every square, to check the incremental one against
+pub fn evaluate_position_from_scratch(board: &Board) -> i32 {
+    let mut score = 0;
+    for row in 0..8 {
+        for col in 0..8 {
+            if let Some(piece) = board.squares[row][col] {
+                let piece_score = get_piece_value(&piece) + piece_square_value(row, col);
+                score += if piece.color == Color::White {
+                    piece_score
+                } else {
+                    -piece_score
+                };
+            }
+        }
+    }
+    score
+}
+
+// White's material minus Black's in centipawns, summed from `get_piece_value` alone
+pub fn compute_material_balance(board: &Board) -> i32 {
+    board.material[Color::White as usize] - board.material[Color::Black as usize]
+}
+
+// The number of pieces each side has on the board, kings and pawns included: (White, Black)
+pub fn compute_piece_count(board: &Board) -> (u32, u32) {
+    (
+        board.occupancy(Color::White).count_ones(),
+        board.occupancy(Color::Black).count_ones(),
+    )
+}
+
+fn score_move(board: &Board, m: &Move) -> i32 {
+    let (to_row, to_col) = m.to;
+    let mut score = 0;
+    // A Chess960 King castles onto its own Rook, which is no capture
+    if let Some(captured_piece) =
+        board.squares[to_row][to_col].filter(|piece| piece.color != board.side_to_move)
+    {
+        score += get_piece_value(&captured_piece);
+    }
+    // A queen is nearly always the piece to promote to; a knight, which can fork or avoid
+    // stalemate, comes next, and a rook or bishop only when nothing else does
+    score += match m.promotion {
+        Some(PieceType::Queen) => 900,
+        Some(PieceType::Knight) => 800,
+        _ => 0,
+    };
+    if (2..=5).contains(&to_row) && (2..=5).contains(&to_col) {
+        score += 10;
+    }
+    score
+}
+
+// Score of a checkmate at the root; mates found `ply` moves deep score MATE_VALUE - ply so that
+// shorter mates are preferred
+pub const MATE_VALUE: i32 = 20000;
+const DRAW_SCORE: i32 = 0;
+
+// Mutable state shared by every node of a single search
+struct SearchContext<'a> {
+    shared: &'a SharedSearch,
+    nodes: u64,
+    cutoffs: u64, // Beta cutoffs, a rough measure of move-ordering quality
+    // Triangular principal variation table: pv[ply] holds the best line found from that ply
+    pv: Vec<Vec<Move>>,
+    // Zobrist keys of the game so far followed by the positions on the current search line
+    path: Vec<u64>,
+    game_len: usize, // How many keys of `path` come from the game, the last being the root
+    // Whether the current node lies on the first line searched, taken as the expected
+    // principal variation; such nodes are never futility pruned
+    pv_node: bool,
+    move_buffers: Vec<Vec<Move>>,    // Reusable move lists, one per ply
+    killers: Vec<[Option<Move>; 2]>, // The last two quiet moves to cause a cutoff, per ply
+    // Singularity searches under way on the current line; while one is, no other starts
+    singular_depth: u32,
+}
+
+impl<'a> SearchContext<'a> {
+    fn new(history: &[u64], shared: &'a SharedSearch) -> Self {
+        SearchContext {
+            shared,
+            nodes: 0,
+            cutoffs: 0,
+            pv: Vec::new(),
+            path: history.to_vec(),
+            game_len: history.len(),
+            pv_node: true,
+            move_buffers: Vec::new(),
+            killers: Vec::new(),
+            singular_depth: 0,
+        }
+    }
+
+    // A position reached by the fifty-move rule or by repetition is scored as a draw. Repeating
+    // the root or a position of the search line is enough, since the side that allowed it could
+    // repeat again; a position from before the root must already have occurred twice, making
+    // this its third occurrence. Only positions since the last pawn move or capture can repeat.
+    fn is_draw_by_rule(&self, board: &Board, key: u64) -> bool {
+        if board.half_move_clock >= 100 {
+            return true;
+        }
+        let window = self
+            .path
+            .len()
+            .saturating_sub(board.half_move_clock as usize);
+        let mut earlier_occurrences = 0;
+        for (index, &previous) in self.path.iter().enumerate().skip(window) {
+            if previous == key {
+                if index + 1 >= self.game_len {
+                    return true;
+                }
+                earlier_occurrences += 1;
+            }
+        }
+        earlier_occurrences >= 2
+    }
+
+    // Records `m` followed by the line found one ply deeper as the best line at `ply`
+    fn update_pv(&mut self, ply: usize, m: Move) {
+        let (head, tail) = self.pv.split_at_mut(ply + 1);
+        head[ply].clear();
+        head[ply].push(m);
+        head[ply].extend_from_slice(&tail[0]);
+    }
+}
+
+// State shared by every thread taking part in one search
+struct SharedSearch {
+    tt: TranspositionTable,
+    stop: AtomicBool, // Set once the main search is done, telling the helpers to give up
+    cancel: Option<Arc<AtomicBool>>, // The caller's stop flag, see `SearchLimits::stop`
+    draw_score: i32,  // What a draw is worth from White's point of view, after contempt
+    tie_margin: i32,  // Root moves this close to the best score may be chosen instead
+    // The root moves from best to worst as the last completed iteration scored them; the next
+    // iteration searches them in this order
+    root_order: Mutex<Vec<Move>>,
+    max_extensions: Option<u32>, // See `SearchLimits::max_extensions`
+    #[cfg(not(target_arch = "wasm32"))]
+    pool: Option<rayon::ThreadPool>, // Searches the root moves in parallel; None searches in turn
+}
+
+impl SharedSearch {
+    fn stopped(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
+            || self
+                .cancel
+                .as_ref()
+                .is_some_and(|cancel| cancel.load(Ordering::Relaxed))
+    }
+
+    // The plies an iteration to `depth` may extend any one line by
+    fn extensions(&self, depth: u32) -> u32 {
+        self.max_extensions.unwrap_or_else(|| max_extensions(depth))
+    }
+}
+
+// The result of a search: the chosen move together with the line the engine expects
+#[derive(Clone, Debug)]
+pub struct SearchResult {
+    pub best_move: Move,
+    pub score: i32, // From White's point of view, in centipawns
+    pub pv: Vec<Move>,
+    pub depth: u32,
+    pub nodes: u64,
+    pub cutoffs: u64,
+    pub time: Duration,
+    pub hash_full: u32, // Permille of the transposition table in use when the search returned
+}
+
+impl SearchResult {
+    // Nodes searched per second
+    pub fn nps(&self) -> u64 {
+        let micros = self.time.as_micros().max(1);
+        (self.nodes as u128 * 1_000_000 / micros) as u64
+    }
+}
+
+// Parameters controlling a search
+#[derive(Clone, Default, Debug)]
+pub struct SearchLimits {
+    pub depth: u32, // Maximum iterative-deepening depth; 0 plays a random legal move
+    pub seed: u64,  // Seeds the choice between equally good root moves
+    // Lazy-SMP helper threads searching alongside the main search, sharing its transposition
+    // table. With helpers the result can depend on thread timing.
+    pub helper_threads: usize,
+    // Threads searching the root moves side by side, in a pool of the search's own rather than
+    // rayon's global one: 1 searches them in turn on the calling thread, 0 uses one thread per
+    // core. Any count gives the same move and score; the node count may differ, as the threads
+    // fill the transposition table in a different order. Browsers have no threads, so wasm
+    // builds always search in turn.
+    pub threads: usize,
+    // Megabytes for the search's transposition table, rounded down to a power-of-two number
+    // of entries; 0 for DEFAULT_HASH_MB
+    pub hash_mb: usize,
+    // Setting this flag from another thread stops the search, which then returns the last
+    // iteration it completed (None if it had not completed any)
+    pub stop: Option<Arc<AtomicBool>>,
+    // Centipawns the searching side deducts from a draw by stalemate, repetition or the
+    // fifty-move rule: positive contempt steers away from draws, negative towards them
+    pub contempt: i32,
+    // Centipawns a root move may score below the best and still be picked at random, on top
+    // of the margin for near-equal moves; makes a weaker, more varied opponent
+    pub random_margin: i32,
+    // Deliberate weakening, from 0 (weakest) to MAX_SKILL_LEVEL (full strength); None plays
+    // at full strength. The lower the level, the more often the engine settles for a move up
+    // to a few pawns worse than its best, see `weaken`.
+    pub skill_level: Option<u32>,
+    // Plays a move of the built-in opening book, picked by `seed`, without searching while the
+    // game is still in it; see `book::Book`
+    pub book: bool,
+    // A book read from a Polyglot file, see `book::Book::open`, played from the same way and
+    // looked in before the built-in one, whether or not `book` is set
+    pub book_file: Option<Arc<book::Book>>,
+    // Plies checks and other forcing moves may extend any one line by, see `extension`; None
+    // allows half of each iteration's depth, and Some(0) searches every line to the nominal depth
+    pub max_extensions: Option<u32>,
+}
+
+impl SearchLimits {
+    pub fn depth(depth: u32) -> Self {
+        SearchLimits {
+            depth,
+            ..Default::default()
+        }
+    }
+
+    // The random generator for a search from `board`: a fixed seed gives the same choices in
+    // the same position, while different positions still get different streams
+    fn rng(&self, board: &Board) -> StdRng {
+        StdRng::seed_from_u64(self.seed ^ board.zobrist_key())
+    }
+}
+
+// Material, in centipawns, a move must give up for `Board::first_sacrifice` to call it a
+// sacrifice: more than a pawn or the exchange
+pub const SACRIFICE_THRESHOLD: i32 = 200;
+
+// Root moves scoring within this many centipawns of the best are considered equally good
+const ROOT_TIE_MARGIN: i32 = 5;
+
+// The full-strength skill level, see `SearchLimits::skill_level`
+pub const MAX_SKILL_LEVEL: u32 = 20;
+// Centipawns a weakened move may lose per skill level below the maximum
+const SKILL_MARGIN_STEP: i32 = 15;
+
+// The deepest search the difficulty levels go to; deeper searches count as this level
+pub const MAX_DIFFICULTY: u32 = 7;
+
+// A rough Elo rating for the engine searching to `depth`, 0 being random moves. These are
+// estimates from games against players of known strength, not measurements of this engine.
+pub fn estimate_elo(depth: u32) -> u32 {
+    match depth.min(MAX_DIFFICULTY) {
+        0 => 250,
+        1 => 600,
+        2 => 800,
+        3 => 1000,
+        4 => 1200,
+        5 => 1500,
+        6 => 1800,
+        _ => 2100,
+    }
+}
+
+// The name of the level of play searching to `depth` stands for
+pub fn difficulty_level_name(depth: u32) -> &'static str {
+    match depth.min(MAX_DIFFICULTY) {
+        0 => "Random",
+        1 => "Beginner",
+        2 => "Casual",
+        3 => "Intermediate",
+        4 => "Club Player",
+        5 => "Advanced",
+        6 => "Expert",
+        _ => "Master",
+    }
+}
+
+// The depth to play at next for adaptive difficulty, from the player's current run of wins
+// or losses against `depth`: two wins in a row step it up a level, two losses step it down,
+// and anything else keeps it
+pub fn auto_adjust_difficulty(depth: u32, win_streak: u32, loss_streak: u32) -> u32 {
+    if win_streak >= 2 {
+        (depth + 1).min(MAX_DIFFICULTY)
+    } else if loss_streak >= 2 {
+        depth.saturating_sub(1).max(1)
+    } else {
+        depth
+    }
+}
+
+// Progress hook called once per completed iterative-deepening iteration, with the nodes and
+// time accumulated so far
+pub type SearchInfo<'a> = dyn FnMut(&SearchResult) + 'a;
+
+// A move that neither captures nor promotes
+fn is_quiet_move(board: &Board, m: Move) -> bool {
+    let ((from_row, from_col), (to_row, to_col)) = (m.from, m.to);
+    // A Chess960 King castles onto its own Rook, which is no capture
+    if board.squares[to_row][to_col].is_some_and(|piece| piece.color != board.side_to_move) {
+        return false;
+    }
+    match board.squares[from_row][from_col] {
+        // A diagonal pawn move onto an empty square captures en passant
+        Some(piece) if piece.kind == PieceType::Pawn => {
+            from_col == to_col && to_row != 0 && to_row != 7
+        }
+        _ => true,
+    }
+}
+
+// `extensions` is how many plies the line from here may still be extended by, see `extension`
+#[allow(clippy::too_many_arguments)]
+fn alpha_beta(
+    ctx: &mut SearchContext,
+    board: &Board,
     depth: u32,
+    ply: usize,
     mut alpha: i32,
     mut beta: i32,
-    maximizing_player: bool,
     color: Color,
+    extensions: u32,
 ) -> i32 {
+    ctx.nodes += 1;
+    if ctx.pv.len() < ply + 2 {
+        ctx.pv.resize(ply + 2, Vec::new());
+    }
+    ctx.pv[ply].clear();
+    ctx.pv[ply + 1].clear();
+
+    // A helper whose result is no longer wanted unwinds without searching further
+    if ctx.shared.stopped() {
+        return DRAW_SCORE;
+    }
+
     if depth == 0 {
         return evaluate_position(board);
     }
 
-    let mut moves = board.generate_all_moves(color);
-    moves.sort_by_key(|m| -score_move(board, m));
+    // Mate-distance pruning: nothing from here scores better than mating on the next ply or
+    // worse than being mated on this one, so once the window lies outside those scores a
+    // shorter mate has already been found and this node cannot change the result
+    if ply > 0 {
+        let mate_bound = MATE_VALUE - ply as i32;
+        alpha = alpha.max(-mate_bound);
+        beta = beta.min(mate_bound);
+        if alpha >= beta {
+            return alpha;
+        }
+    }
 
-    if maximizing_player {
-        let mut max_eval = i32::MIN;
-        for m in moves {
-            let mut new_board = board.clone();
-            if new_board.is_castling_move(m.0, m.1, color) && !new_board.can_castle(m.0, m.1) {
-                continue;
+    // The caller pushes this position's key onto the path before recursing
+    let key = match ctx.path.last() {
+        Some(&key) => key,
+        None => board.zobrist_key(),
+    };
+    let tt_entry = ctx.shared.tt.probe(key, ply);
+    if let Some(entry) = tt_entry {
+        // The root always searches, so that it produces a move and a line. Only entries of the
+        // same depth are used: the root moves run in parallel, and whether a sibling has
+        // already stored a deeper result would otherwise change the scores from run to run.
+        if ply > 0 && entry.depth == depth {
+            let usable = match entry.bound {
+                Bound::Exact => true,
+                Bound::Lower => entry.score >= beta,
+                Bound::Upper => entry.score <= alpha,
+            };
+            if usable {
+                return entry.score;
             }
-            new_board.apply_move(m);
+        }
+    }
+    let (original_alpha, original_beta) = (alpha, beta);
 
-            if let Some(king_pos) = new_board.find_king(color) {
-                if new_board.is_square_under_attack(king_pos.0, king_pos.1, color) {
-                    continue;
-                }
-            } else {
+    // Each ply generates into its own buffer, kept in the context between nodes. The best move
+    // from an earlier search of this position is tried first.
+    if ctx.move_buffers.len() <= ply {
+        ctx.move_buffers.resize_with(ply + 1, Vec::new);
+        ctx.killers.resize(ply + 1, [None; 2]);
+    }
+    let mut picker = MovePicker::new(
+        color,
+        tt_entry.and_then(|entry| entry.best_move),
+        ctx.killers[ply],
+        std::mem::take(&mut ctx.move_buffers[ply]),
+    );
+    let mut best_move = None;
+
+    // White maximizes the score, Black minimizes it
+    let maximizing_player = color == Color::White;
+    let mut best_eval = if maximizing_player {
+        i32::MIN
+    } else {
+        i32::MAX
+    };
+
+    // Futility pruning: one or two plies from the horizon, a quiet move is not searched when
+    // the static evaluation plus a margin still cannot bring the score inside the window
+    let pv_node = ctx.pv_node;
+    let futility_bound = if depth <= 2 && !pv_node && !board.is_in_check(color) {
+        let futility_margin = if depth == 1 { 100 } else { 300 };
+        let static_eval = evaluate_position(board);
+        if maximizing_player {
+            Some(static_eval + futility_margin).filter(|&bound| bound <= alpha)
+        } else {
+            Some(static_eval - futility_margin).filter(|&bound| bound >= beta)
+        }
+    } else {
+        None
+    };
+
+    // Only a side in check is likely to have a single legal move, so only then are they counted
+    let single_reply = board.is_in_check(color) && board.legal_move_count(color) == 1;
+    let singular_move = tt_entry
+        .filter(|_| ply > 0 && extensions > 0)
+        .and_then(|entry| singular_move(ctx, board, entry, depth, ply, color));
+
+    let mut has_legal_move = false;
+    let mut searched_moves = 0;
+    while let Some(m) = picker.next(board) {
+        let mut new_board = board.clone();
+        if new_board.is_castling_move(m.from, m.to, color) && !new_board.can_castle(m.from, m.to) {
+            continue;
+        }
+        new_board.apply_move(m);
+
+        if let Some(king_pos) = new_board.find_king(color) {
+            if new_board.is_square_under_attack(king_pos.0, king_pos.1, color) {
+                continue;
+            }
+        } else {
+            continue;
+        }
+        has_legal_move = true;
+        let gives_check = new_board.is_in_check(opposite_color(color));
+
+        if let Some(bound) = futility_bound {
+            if is_quiet_move(board, m) && !gives_check {
+                // The skipped move is assumed to score no better than the bound
+                best_eval = if maximizing_player {
+                    best_eval.max(bound)
+                } else {
+                    best_eval.min(bound)
+                };
                 continue;
             }
+        }
 
+        let key = new_board.zobrist_key();
+        let eval = if ctx.is_draw_by_rule(&new_board, key) {
+            ctx.pv[ply + 1].clear();
+            ctx.shared.draw_score
+        } else {
+            ctx.path.push(key);
+            ctx.pv_node = pv_node && searched_moves == 0;
+            let extension = if singular_move == Some(m) {
+                1
+            } else {
+                extension(board, m, color, gives_check, single_reply, extensions)
+            };
             let eval = alpha_beta(
+                ctx,
                 &new_board,
-                depth - 1,
+                depth - 1 + extension,
+                ply + 1,
                 alpha,
                 beta,
-                false,
                 opposite_color(color),
+                extensions - extension,
             );
-            max_eval = max_eval.max(eval);
+            ctx.pv_node = pv_node;
+            ctx.path.pop();
+            eval
+        };
+        searched_moves += 1;
+
+        if (maximizing_player && eval > best_eval) || (!maximizing_player && eval < best_eval) {
+            best_eval = eval;
+            best_move = Some(m);
+            ctx.update_pv(ply, m);
+        }
+        if maximizing_player {
             alpha = alpha.max(eval);
-            if beta <= alpha {
-                break;
+        } else {
+            beta = beta.min(eval);
+        }
+        if beta <= alpha {
+            ctx.cutoffs += 1;
+            // A quiet move refuting this position is likely to refute its siblings too
+            if is_quiet_move(board, m) && ctx.killers[ply][0] != Some(m) {
+                ctx.killers[ply] = [Some(m), ctx.killers[ply][0]];
             }
+            break;
         }
-        max_eval
-    } else {
-        let mut min_eval = i32::MAX;
-        for m in moves {
-            let mut new_board = board.clone();
-            new_board.apply_move(m);
+    }
+    ctx.move_buffers[ply] = picker.into_buffer();
+
+    if !has_legal_move {
+        // Checkmate or stalemate
+        return if !board.is_in_check(color) {
+            ctx.shared.draw_score
+        } else if maximizing_player {
+            -(MATE_VALUE - ply as i32)
+        } else {
+            MATE_VALUE - ply as i32
+        };
+    }
 
-            if let Some(king_pos) = new_board.find_king(color) {
-                if new_board.is_square_under_attack(king_pos.0, king_pos.1, color) {
-                    continue;
-                }
-            } else {
-                continue;
-            }
+    // An interrupted search returns a meaningless score that must not be stored
+    if !ctx.shared.stopped() {
+        let bound = if best_eval <= original_alpha {
+            Bound::Upper
+        } else if best_eval >= original_beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        ctx.shared.tt.store(
+            key,
+            ply,
+            TtEntry {
+                best_move,
+                score: best_eval,
+                depth,
+                bound,
+            },
+        );
+    }
+    best_eval
+}
 
+// Singular extensions look for a lone good move only this deep or deeper. Shallower, the
+// half-depth verification search is two plies or less, too noisy to show the other moves fall
+// short, and without reductions it costs more than the extension finds.
+const SINGULAR_MIN_DEPTH: u32 = 6;
+// Centipawns every other move must fall short of the table move's score by for it to be
+// singular
+const SINGULAR_MARGIN: i32 = 80;
+
+// The table's move for this position if it is singular: its score, a cutoff or exact, came
+// from a search at most three plies shallower, and in a search to half the depth no other
+// move gets within SINGULAR_MARGIN of it. Such a move is the only one holding the position,
+// and is searched a ply deeper. The moves are tried in a null window just below its score,
+// and a singularity search never starts inside another.
+fn singular_move(
+    ctx: &mut SearchContext,
+    board: &Board,
+    entry: TtEntry,
+    depth: u32,
+    ply: usize,
+    color: Color,
+) -> Option<Move> {
+    let maximizing_player = color == Color::White;
+    let cut_bound = if maximizing_player {
+        Bound::Lower
+    } else {
+        Bound::Upper
+    };
+    let tt_move = entry.best_move?;
+    if depth < SINGULAR_MIN_DEPTH
+        || ctx.singular_depth > 0
+        || entry.depth + 3 < depth
+        || !(entry.bound == Bound::Exact || entry.bound == cut_bound)
+        || entry.score.abs() >= MATE_VALUE - 1000
+    {
+        return None;
+    }
+    let singular_beta = if maximizing_player {
+        entry.score - SINGULAR_MARGIN
+    } else {
+        entry.score + SINGULAR_MARGIN
+    };
+    let (alpha, beta) = if maximizing_player {
+        (singular_beta - 1, singular_beta)
+    } else {
+        (singular_beta, singular_beta + 1)
+    };
+
+    ctx.singular_depth += 1;
+    let pv_node = ctx.pv_node;
+    ctx.pv_node = false;
+    let mut singular = true;
+    for m in board.generate_all_legal_moves(color) {
+        if m == tt_move {
+            continue;
+        }
+        let mut new_board = board.clone();
+        new_board.apply_move(m);
+        let key = new_board.zobrist_key();
+        let eval = if ctx.is_draw_by_rule(&new_board, key) {
+            ctx.shared.draw_score
+        } else {
+            ctx.path.push(key);
             let eval = alpha_beta(
+                ctx,
                 &new_board,
-                depth - 1,
+                depth / 2 - 1,
+                ply + 1,
                 alpha,
                 beta,
-                true,
                 opposite_color(color),
+                0,
             );
-            min_eval = min_eval.min(eval);
-            beta = beta.min(eval);
-            if beta <= alpha {
-                break;
+            ctx.path.pop();
+            eval
+        };
+        let reaches = if maximizing_player {
+            eval >= singular_beta
+        } else {
+            eval <= singular_beta
+        };
+        if reaches {
+            singular = false;
+            break;
+        }
+    }
+    ctx.pv_node = pv_node;
+    ctx.singular_depth -= 1;
+    // An interrupted search proves nothing
+    (singular && !ctx.shared.stopped()).then_some(tt_move)
+}
+
+// The most plies a search to `depth` may extend any one line by, so that a run of checks
+// cannot make it explode
+fn max_extensions(depth: u32) -> u32 {
+    depth / 2
+}
+
+// Forcing moves are searched a ply deeper, so their consequences do not fall just past the
+// horizon: a check, a pawn stepping onto the seventh rank, or the only legal move. Returns
+// the plies to extend `m` by, 0 once the line's `extensions` are used up.
+fn extension(
+    board: &Board,
+    m: Move,
+    color: Color,
+    gives_check: bool,
+    single_reply: bool,
+    extensions: u32,
+) -> u32 {
+    let seventh_rank = match color {
+        Color::White => 6,
+        Color::Black => 1,
+    };
+    let pawn_push = m.to.0 == seventh_rank
+        && board.squares[m.from.0][m.from.1].is_some_and(|piece| piece.kind == PieceType::Pawn);
+    u32::from(extensions > 0 && (gives_check || pawn_push || single_reply))
+}
+
+// Picks a uniformly random legal move, used for the "Random" difficulty level
+pub fn random_legal_move(board: &Board, color: Color, rng: &mut impl Rng) -> Option<Move> {
+    let moves = board.generate_all_legal_moves(color);
+    moves.choose(rng).copied()
+}
+
+// What the side that searched does next, see `reply_after_search`
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Reply {
+    Play(Move),
+    Checkmated, // No legal move, in check: the game is lost
+    Stalemated, // No legal move, not in check: the game is drawn
+}
+
+// Turns a finished search for `color` into its reply: the move the search found, or when it was
+// stopped before finding one, the first legal move, as the UCI and XBoard front ends play.
+// Only a side without any legal move has no reply, and then the game is over.
+pub fn reply_after_search(board: &Board, color: Color, found: Option<Move>) -> Reply {
+    let legal = LegalMoves::new(board, color);
+    if let Some(m) = found.or_else(|| legal.moves().first().copied()) {
+        return Reply::Play(m);
+    }
+    match legal.status() {
+        GameStatus::Checkmate => Reply::Checkmated,
+        GameStatus::Stalemate => Reply::Stalemated,
+        GameStatus::Ongoing | GameStatus::Check => {
+            unreachable!("a side with a legal move plays it")
+        }
+    }
+}
+
+// Scores playing `m` for `color` followed by a search of the reply, from White's point of view
+pub fn evaluate_move(board: &Board, color: Color, m: Move, depth: u32, history: &[u64]) -> i32 {
+    let mut new_board = board.clone();
+    new_board.apply_move(m);
+    if depth <= 1 {
+        return evaluate_position(&new_board);
+    }
+    let opponent = opposite_color(color);
+    let mut history = history.to_vec();
+    history.push(new_board.zobrist_key());
+    match improved_best_move_for_color(
+        &new_board,
+        opponent,
+        &SearchLimits::depth(depth - 1),
+        &history,
+    ) {
+        Some(result) => result.score,
+        // The opponent has no reply: checkmate is decisive, stalemate is a draw
+        None if new_board.is_in_check(opponent) => {
+            if color == Color::White {
+                MATE_VALUE
+            } else {
+                -MATE_VALUE
             }
         }
-        min_eval
+        None => DRAW_SCORE,
     }
 }
 
+// Half-width of the aspiration window around the previous iteration's score, in centipawns
+const ASPIRATION_WINDOW: i32 = 50;
+// The first iterations are cheap and their scores unstable, so they search the full window
+const FULL_WIDTH_DEPTHS: u32 = 2;
+
+// `history` holds the Zobrist keys of the game's positions, ending with the current one, so the
+// search can recognise repetitions
 pub fn improved_best_move_for_color(
     board: &Board,
     color: Color,
-    depth: u32,
-) -> Option<((usize, usize), (usize, usize))> {
-    // Main search logic with thread pool (Rayon example)
-    let best_move = Arc::new(Mutex::new(None));
-    let best_value = Arc::new(Mutex::new(if color == Color::White {
-        i32::MIN
-    } else {
-        i32::MAX
-    }));
+    limits: &SearchLimits,
+    history: &[u64],
+) -> Option<SearchResult> {
+    search_with_info(board, color, limits, history, &mut |_| {})
+}
 
-    let mut moves = board.generate_all_moves(color);
-    moves.sort_by_key(|m| -score_move(board, m));
+// Like `improved_best_move_for_color`, reporting each iteration's result to `on_iteration`
+pub fn search_with_info(
+    board: &Board,
+    color: Color,
+    limits: &SearchLimits,
+    history: &[u64],
+    on_iteration: &mut SearchInfo,
+) -> Option<SearchResult> {
+    let start = Instant::now();
+    let mut rng = limits.rng(board);
+
+    // A move played without a search, scored by the position it leads to
+    let unsearched = |m: Move| {
+        let mut new_board = board.clone();
+        new_board.apply_move(m);
+        SearchResult {
+            best_move: m,
+            score: evaluate_position(&new_board),
+            pv: vec![m],
+            depth: 0,
+            nodes: 0,
+            cutoffs: 0,
+            time: start.elapsed(),
+            hash_full: 0,
+        }
+    };
 
-    // Using Rayon for parallel iteration over moves
-    let _handles: Vec<_> = moves
-        .into_par_iter()
-        .map(|m| {
-            let best_move = Arc::clone(&best_move);
-            let best_value = Arc::clone(&best_value);
-            let mut new_board = board.clone();
-            if new_board.is_castling_move(m.0, m.1, color) && !new_board.can_castle(m.0, m.1) {
-                return ();
-            }
-            new_board.apply_move(m);
-
-            if let Some(king_pos) = new_board.find_king(color) {
-                if new_board.is_square_under_attack(king_pos.0, king_pos.1, color) {
-                    return (); // Skip invalid move
-                }
-            } else {
-                return (); // Skip invalid move
+    // Depth 0 means the bot plays random (but legal) moves
+    if limits.depth == 0 {
+        return random_legal_move(board, color, &mut rng).map(unsearched);
+    }
+    if color == board.side_to_move {
+        let books = limits
+            .book_file
+            .as_deref()
+            .into_iter()
+            .chain(limits.book.then(book::Book::builtin));
+        for book in books {
+            if let Some(m) = book.probe(board, &mut rng) {
+                return Some(unsearched(m));
             }
+        }
+    }
 
-            let eval = alpha_beta(
-                &new_board,
-                depth - 1,
-                i32::MIN + 1,
-                i32::MAX - 1,
-                color == Color::Black,
-                opposite_color(color),
-            );
+    let shared = SharedSearch {
+        tt: TranspositionTable::new(match limits.hash_mb {
+            0 => DEFAULT_HASH_MB,
+            megabytes => megabytes,
+        }),
+        stop: AtomicBool::new(false),
+        cancel: limits.stop.clone(),
+        draw_score: match color {
+            Color::White => DRAW_SCORE - limits.contempt,
+            Color::Black => DRAW_SCORE + limits.contempt,
+        },
+        tie_margin: ROOT_TIE_MARGIN + limits.random_margin.max(0),
+        root_order: Mutex::new(Vec::new()),
+        max_extensions: limits.max_extensions,
+        // A pool that cannot be built leaves the root moves to be searched in turn
+        #[cfg(not(target_arch = "wasm32"))]
+        pool: (limits.threads != 1)
+            .then(|| {
+                rayon::ThreadPoolBuilder::new()
+                    .num_threads(limits.threads)
+                    .build()
+                    .ok()
+            })
+            .flatten(),
+    };
+    let best = std::thread::scope(|scope| {
+        // Browsers cannot spawn threads, so wasm builds always search alone
+        let helper_threads = if cfg!(target_arch = "wasm32") {
+            0
+        } else {
+            limits.helper_threads
+        };
+        for index in 0..helper_threads {
+            let shared = &shared;
+            scope.spawn(move || helper_search(board, color, limits.depth, history, shared, index));
+        }
 
-            let mut best_value = best_value.lock().unwrap();
-            let mut best_move = best_move.lock().unwrap();
-            if (color == Color::White && eval > *best_value)
-                || (color == Color::Black && eval < *best_value)
-            {
-                *best_value = eval;
-                *best_move = Some(m);
+        // Iterative deepening: each iteration seeds the aspiration window of the next one
+        let mut best: Option<SearchResult> = None;
+        let mut nodes = 0;
+        let mut cutoffs = 0;
+        for current_depth in 1..=limits.depth {
+            let iteration = match &best {
+                Some(previous) if current_depth > FULL_WIDTH_DEPTHS => aspiration_search(
+                    board,
+                    color,
+                    current_depth,
+                    history,
+                    previous.score,
+                    &shared,
+                    &mut rng,
+                ),
+                _ => search_root(
+                    board,
+                    color,
+                    current_depth,
+                    history,
+                    (i32::MIN + 1, i32::MAX - 1),
+                    &shared,
+                    &mut rng,
+                ),
+            };
+            // An interrupted iteration is incomplete, so the previous one stands
+            if shared.stopped() {
+                break;
             }
+            if let Some(result) = &iteration {
+                nodes += result.nodes;
+                cutoffs += result.cutoffs;
+            }
+            match iteration {
+                Some(result) => {
+                    let result = SearchResult {
+                        nodes,
+                        cutoffs,
+                        time: start.elapsed(),
+                        ..result
+                    };
+                    on_iteration(&result);
+                    best = Some(result);
+                }
+                None => break, // No legal moves at the root
+            }
+        }
+        shared.stop.store(true, Ordering::Relaxed);
+        best
+    });
+
+    // The helpers have finished, so the weakening rescores the root alone. A cancelled search
+    // answers with its best move as it is.
+    shared.stop.store(false, Ordering::Relaxed);
+    match (best, limits.skill_level) {
+        (Some(result), Some(level)) if !shared.stopped() => Some(weaken(
+            board, color, history, result, level, &shared, &mut rng,
+        )),
+        (best, _) => best,
+    }
+}
+
+// Swaps the search's best move for a worse one, as often and by as much as `level` allows:
+// at level L the swap happens with probability (MAX - L) / MAX, to a move losing at most
+// (MAX - L) * SKILL_MARGIN_STEP centipawns. The weakest level favours developing moves among
+// those, so the engine plays like a beginner rather than at random. Uses the search's `rng`,
+// so the choice is fixed for a given seed.
+fn weaken(
+    board: &Board,
+    color: Color,
+    history: &[u64],
+    best: SearchResult,
+    level: u32,
+    shared: &SharedSearch,
+    rng: &mut StdRng,
+) -> SearchResult {
+    let missing = MAX_SKILL_LEVEL - level.min(MAX_SKILL_LEVEL);
+    if rng.random_range(0..MAX_SKILL_LEVEL) >= missing {
+        return best;
+    }
+    let margin = missing as i32 * SKILL_MARGIN_STEP;
+    // A window just wide enough for every move within the margin to get an exact score
+    let window = match color {
+        Color::White => (best.score - margin - 1, best.score + 1),
+        Color::Black => (best.score - 1, best.score + margin + 1),
+    };
+    let roots = score_root_moves(board, color, best.depth, history, window, shared);
+    let nodes = best.nodes + roots.iter().map(|root| root.nodes).sum::<u64>();
+    let candidates: Vec<&RootMove> = roots
+        .iter()
+        .filter(|root| root.eval > window.0 && root.eval < window.1)
+        .collect();
+    let developing: Vec<&RootMove> = candidates
+        .iter()
+        .copied()
+        .filter(|root| is_developing_move(board, root.pv[0]))
+        .collect();
+    let pool = if level == 0 && !developing.is_empty() {
+        developing
+    } else {
+        candidates
+    };
+    match pool.choose(rng) {
+        Some(root) => SearchResult {
+            best_move: root.pv[0],
+            score: root.eval,
+            pv: root.pv.clone(),
+            nodes,
+            ..best
+        },
+        None => SearchResult { nodes, ..best },
+    }
+}
+
+// A knight or bishop leaving its back rank, a centre pawn's first step, or castling
+fn is_developing_move(board: &Board, m: Move) -> bool {
+    let (from_row, from_col) = m.from;
+    let piece = match board.piece_at(from_row, from_col) {
+        Some(piece) => piece,
+        None => return false,
+    };
+    let (back_rank, pawn_rank) = match piece.color {
+        Color::White => (0, 1),
+        Color::Black => (7, 6),
+    };
+    match piece.kind {
+        PieceType::Knight | PieceType::Bishop => from_row == back_rank,
+        PieceType::Pawn => from_row == pawn_rank && (3..=4).contains(&from_col),
+        PieceType::King => board.is_castling_move(m.from, m.to, piece.color),
+        _ => false,
+    }
+}
+
+// A lazy-SMP helper: runs its own iterative deepening on the same position, contributing to
+// the main search only through the shared transposition table, until told to stop
+fn helper_search(
+    board: &Board,
+    color: Color,
+    depth: u32,
+    history: &[u64],
+    shared: &SharedSearch,
+    index: usize,
+) {
+    let mut ctx = SearchContext::new(history, shared);
+    // Every other helper searches one ply deeper, filling the table ahead of the main search
+    let max_depth = depth + (index % 2) as u32;
+    for current_depth in 1..=max_depth {
+        alpha_beta(
+            &mut ctx,
+            board,
+            current_depth,
+            0,
+            i32::MIN + 1,
+            i32::MAX - 1,
+            color,
+            shared.extensions(current_depth),
+        );
+        if shared.stopped() {
+            break;
+        }
+    }
+}
+
+// Searches with a narrow window around `previous_score`, widening it after each fail-high or
+// fail-low until the score falls inside the window. The returned node and cutoff counts cover
+// every attempt.
+fn aspiration_search(
+    board: &Board,
+    color: Color,
+    depth: u32,
+    history: &[u64],
+    previous_score: i32,
+    shared: &SharedSearch,
+    rng: &mut StdRng,
+) -> Option<SearchResult> {
+    let mut window = ASPIRATION_WINDOW;
+    let (mut nodes, mut cutoffs) = (0, 0);
+    loop {
+        // After a few failures fall back to a full-width search, which always succeeds
+        let (alpha, beta) = if window > ASPIRATION_WINDOW * 16 {
+            (i32::MIN + 1, i32::MAX - 1)
+        } else {
+            (
+                previous_score.saturating_sub(window),
+                previous_score.saturating_add(window),
+            )
+        };
+        let result = search_root(board, color, depth, history, (alpha, beta), shared, rng)?;
+        nodes += result.nodes;
+        cutoffs += result.cutoffs;
+        if (result.score > alpha && result.score < beta) || alpha == i32::MIN + 1 {
+            return Some(SearchResult {
+                nodes,
+                cutoffs,
+                ..result
+            });
+        }
+        window *= 4;
+    }
+}
+
+// Searches every root move to `depth` within the (alpha, beta) window, choosing between moves
+// that score (nearly) the same with `rng`
+fn search_root(
+    board: &Board,
+    color: Color,
+    depth: u32,
+    history: &[u64],
+    (alpha, beta): (i32, i32),
+    shared: &SharedSearch,
+    rng: &mut StdRng,
+) -> Option<SearchResult> {
+    let start = Instant::now();
+    let previous_best = shared.root_order.lock().unwrap().first().copied();
+    let scored_moves = score_root_moves(board, color, depth, history, (alpha, beta), shared);
+
+    // The results keep the move ordering, whichever thread finished first
+    let nodes = scored_moves.iter().map(|root| root.nodes).sum::<u64>() + 1;
+    let cutoffs = scored_moves.iter().map(|root| root.cutoffs).sum();
+    let evals = scored_moves.iter().map(|root| root.eval);
+    let score = if color == Color::White {
+        evals.max()?
+    } else {
+        evals.min()?
+    };
+    // Only scores inside the window are exact, so bounds never count as ties
+    let ties: Vec<&RootMove> = scored_moves
+        .iter()
+        .filter(|root| {
+            root.eval == score
+                || (root.eval.abs_diff(score) <= shared.tie_margin as u32
+                    && root.eval > alpha
+                    && root.eval < beta)
         })
         .collect();
+    // The last iteration's move stands while it is still among the best, so the best move
+    // changes between depths only when a deeper search finds a better one
+    let chosen = ties
+        .iter()
+        .find(|root| Some(root.pv[0]) == previous_best)
+        .or_else(|| ties.choose(rng))?;
+
+    // The next iteration searches the chosen move first, then the rest from best to worst.
+    // The sort is stable, so moves scoring the same keep their order from run to run. The
+    // scores of an interrupted iteration mean nothing, so it leaves the order alone.
+    if !shared.stopped() {
+        let mut ranked: Vec<&RootMove> = scored_moves.iter().collect();
+        ranked.sort_by_key(|root| match (root.pv[0] == chosen.pv[0], color) {
+            (true, _) => i32::MIN,
+            (false, Color::White) => -root.eval,
+            (false, Color::Black) => root.eval,
+        });
+        *shared.root_order.lock().unwrap() = ranked.iter().map(|root| root.pv[0]).collect();
+    }
+
+    Some(SearchResult {
+        best_move: chosen.pv[0],
+        score: chosen.eval,
+        pv: chosen.pv.clone(),
+        depth,
+        nodes,
+        cutoffs,
+        time: start.elapsed(),
+        hash_full: shared.tt.hash_full(),
+    })
+}
 
-    match Arc::try_unwrap(best_move) {
-        Ok(best_move) => best_move.into_inner().unwrap_or(None),
-        Err(_) => None,
+// Searches each legal root move to `depth` within the (alpha, beta) window, keeping the move
+// ordering: the moves the last iteration ranked first, best first, then the rest by
+// `score_move`. Scores outside the window are only bounds.
+fn score_root_moves(
+    board: &Board,
+    color: Color,
+    depth: u32,
+    history: &[u64],
+    (alpha, beta): (i32, i32),
+    shared: &SharedSearch,
+) -> Vec<RootMove> {
+    let mut moves = board.generate_all_moves(color);
+    moves.sort_by_key(|m| -score_move(board, m));
+    let root_order = shared.root_order.lock().unwrap().clone();
+    moves.sort_by_key(|m| {
+        root_order
+            .iter()
+            .position(|ranked| ranked == m)
+            .unwrap_or(usize::MAX)
+    });
+
+    let single_reply = board.legal_move_count(color) == 1;
+
+    // Each root move is searched independently: the map step yields its score, line and
+    // statistics, or nothing if it is illegal
+    let search_move = |m: Move| {
+        let mut new_board = board.clone();
+        if new_board.is_castling_move(m.from, m.to, color) && !new_board.can_castle(m.from, m.to) {
+            return None;
+        }
+        new_board.apply_move(m);
+        let king_pos = new_board.find_king(color)?;
+        if new_board.is_square_under_attack(king_pos.0, king_pos.1, color) {
+            return None; // Skip invalid move
+        }
+
+        let mut ctx = SearchContext::new(history, shared);
+        let key = new_board.zobrist_key();
+        let eval = if ctx.is_draw_by_rule(&new_board, key) {
+            ctx.pv.resize(2, Vec::new());
+            shared.draw_score
+        } else {
+            ctx.path.push(key);
+            let gives_check = new_board.is_in_check(opposite_color(color));
+            let extensions = shared.extensions(depth);
+            let extension = extension(board, m, color, gives_check, single_reply, extensions);
+            alpha_beta(
+                &mut ctx,
+                &new_board,
+                depth - 1 + extension,
+                1,
+                alpha,
+                beta,
+                opposite_color(color),
+                extensions - extension,
+            )
+        };
+        let mut pv = vec![m];
+        pv.append(&mut ctx.pv[1]);
+        Some(RootMove {
+            eval,
+            pv,
+            nodes: ctx.nodes,
+            cutoffs: ctx.cutoffs,
+        })
+    };
+    // The search's own pool spreads the moves over its threads; without one they are searched
+    // in turn
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(pool) = &shared.pool {
+        return pool.install(|| moves.into_par_iter().filter_map(search_move).collect());
     }
+    moves.into_iter().filter_map(search_move).collect()
+}
+
+// The outcome of searching one root move
+struct RootMove {
+    eval: i32,
+    pv: Vec<Move>, // Starts with the root move itself
+    nodes: u64,
+    cutoffs: u64,
 }
@@ -0,0 +1,133 @@
+// bitboard.rs
+// Bitboards: one bit per square, bit `row * 8 + col`, so a1 is bit 0 and h8 is bit 63.
+use super::{Color, KING_OFFSETS, KNIGHT_OFFSETS};
+
+pub type Bitboard = u64;
+
+pub fn square_bit(row: usize, col: usize) -> Bitboard {
+    1 << (row * 8 + col)
+}
+
+// The (row, col) squares of the set bits, from a1 upwards
+pub fn squares(mut bitboard: Bitboard) -> impl Iterator<Item = (usize, usize)> {
+    std::iter::from_fn(move || {
+        if bitboard == 0 {
+            return None;
+        }
+        let index = bitboard.trailing_zeros() as usize;
+        bitboard &= bitboard - 1;
+        Some((index / 8, index % 8))
+    })
+}
+
+// Squares a knight on each square attacks
+pub static KNIGHT_ATTACKS: [Bitboard; 64] = offset_table(&KNIGHT_OFFSETS);
+// Squares a king on each square attacks, not counting castling
+pub static KING_ATTACKS: [Bitboard; 64] = offset_table(&KING_OFFSETS);
+// Squares a pawn of each color (White first) captures on from each square
+pub static PAWN_ATTACKS: [[Bitboard; 64]; 2] = [
+    offset_table(&[(1, -1), (1, 1)]),
+    offset_table(&[(-1, -1), (-1, 1)]),
+];
+
+pub fn pawn_attacks(color: Color, row: usize, col: usize) -> Bitboard {
+    let side = match color {
+        Color::White => 0,
+        Color::Black => 1,
+    };
+    PAWN_ATTACKS[side][row * 8 + col]
+}
+
+// Squares a rook, bishop or queen on `square` (row * 8 + col) attacks given the `occupied`
+// squares; each ray stops at, and includes, the first occupied square
+pub fn rook_attacks(square: usize, occupied: Bitboard) -> Bitboard {
+    (0..4)
+        .map(|direction| ray_attacks(direction, square, occupied))
+        .fold(0, |attacks, ray| attacks | ray)
+}
+
+pub fn bishop_attacks(square: usize, occupied: Bitboard) -> Bitboard {
+    (4..8)
+        .map(|direction| ray_attacks(direction, square, occupied))
+        .fold(0, |attacks, ray| attacks | ray)
+}
+
+pub fn queen_attacks(square: usize, occupied: Bitboard) -> Bitboard {
+    rook_attacks(square, occupied) | bishop_attacks(square, occupied)
+}
+
+// The rook directions come first, then the bishop ones
+const RAY_OFFSETS: [(isize, isize); 8] = [
+    (1, 0),
+    (-1, 0),
+    (0, 1),
+    (0, -1),
+    (1, 1),
+    (1, -1),
+    (-1, 1),
+    (-1, -1),
+];
+
+// For each direction and square, every square up to the edge of the board
+static RAYS: [[Bitboard; 64]; 8] = {
+    let mut rays = [[0; 64]; 8];
+    let mut direction = 0;
+    while direction < 8 {
+        rays[direction] = ray_table(RAY_OFFSETS[direction]);
+        direction += 1;
+    }
+    rays
+};
+
+// The ray from `square` in `direction`, cut off behind its first blocker: the part of the ray
+// beyond the blocker is the blocker's own ray in the same direction
+fn ray_attacks(direction: usize, square: usize, occupied: Bitboard) -> Bitboard {
+    let ray = RAYS[direction][square];
+    let blockers = ray & occupied;
+    if blockers == 0 {
+        return ray;
+    }
+    let (dr, dc) = RAY_OFFSETS[direction];
+    // Bit indices grow along rays pointing up the board (or right along a rank), so the
+    // nearest blocker is the lowest set bit on those and the highest on the others
+    let nearest = if dr * 8 + dc > 0 {
+        blockers.trailing_zeros()
+    } else {
+        63 - blockers.leading_zeros()
+    };
+    ray ^ RAYS[direction][nearest as usize]
+}
+
+const fn ray_table((dr, dc): (isize, isize)) -> [Bitboard; 64] {
+    let mut table = [0; 64];
+    let mut square = 0;
+    while square < 64 {
+        let (mut r, mut c) = ((square / 8) as isize + dr, (square % 8) as isize + dc);
+        while r >= 0 && r < 8 && c >= 0 && c < 8 {
+            table[square] |= 1 << (r * 8 + c);
+            r += dr;
+            c += dc;
+        }
+        square += 1;
+    }
+    table
+}
+
+// For every square, the squares reached by the (row, col) offsets that stay on the board
+const fn offset_table(offsets: &[(isize, isize)]) -> [Bitboard; 64] {
+    let mut table = [0; 64];
+    let mut square = 0;
+    while square < 64 {
+        let (row, col) = ((square / 8) as isize, (square % 8) as isize);
+        let mut i = 0;
+        while i < offsets.len() {
+            let (r, c) = (row + offsets[i].0, col + offsets[i].1);
+            if r >= 0 && r < 8 && c >= 0 && c < 8 {
+                table[square] |= 1 << (r * 8 + c);
+            }
+            i += 1;
+        }
+        square += 1;
+    }
+    table
+}
@@ -0,0 +1,173 @@
+// book.rs
+// A small opening book compiled into the engine, so the bot varies its openings instead of
+// searching its way into the same one every game. The book is a list of mainstream lines, each
+// with a weight for how often it should come up; they are replayed once, on first use, into a
+// table from each position's Zobrist key to the moves played there. A move's weight is the sum
+// of the weights of the lines playing it, so a position reached by different move orders
+// offers the moves of all of them. A book can also be read from a Polyglot `.bin` file, see
+// `polyglot`.
+use super::polyglot::{decode_move, polyglot_key};
+use super::{Board, Move};
+use rand::Rng;
+use std::collections::HashMap;
+use std::io::{self, Read};
+use std::path::Path;
+use std::sync::OnceLock;
+
+// (weight, moves in SAN from the initial position)
+const LINES: &[(u32, &str)] = &[
+    // Ruy Lopez
+    (10, "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6"),
+    // Italian Game
+    (8, "e4 e5 Nf3 Nc6 Bc4 Bc5 c3 Nf6"),
+    // Scotch Game
+    (4, "e4 e5 Nf3 Nc6 d4 exd4 Nxd4 Nf6"),
+    // Petrov Defence
+    (3, "e4 e5 Nf3 Nf6 Nxe5 d6 Nf3 Nxe4"),
+    // Sicilian Defence, Najdorf and Classical
+    (8, "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 a6"),
+    (5, "e4 c5 Nf3 Nc6 d4 cxd4 Nxd4 Nf6 Nc3 d6"),
+    // French Defence
+    (5, "e4 e6 d4 d5 Nc3 Nf6 Bg5 Be7"),
+    // Caro-Kann Defence
+    (5, "e4 c6 d4 d5 Nc3 dxe4 Nxe4 Bf5"),
+    // Queen's Gambit Declined, Slav and Accepted
+    (8, "d4 d5 c4 e6 Nc3 Nf6 Bg5 Be7"),
+    (6, "d4 d5 c4 c6 Nf3 Nf6 Nc3 dxc4"),
+    (3, "d4 d5 c4 dxc4 Nf3 Nf6 e3 e6"),
+    // King's Indian, Grünfeld, Nimzo-Indian and Queen's Indian Defences
+    (6, "d4 Nf6 c4 g6 Nc3 Bg7 e4 d6"),
+    (4, "d4 Nf6 c4 g6 Nc3 d5 cxd5 Nxd5"),
+    (6, "d4 Nf6 c4 e6 Nc3 Bb4 e3 O-O"),
+    (4, "d4 Nf6 c4 e6 Nf3 b6 g3 Bb7"),
+    // English Opening
+    (5, "c4 e5 Nc3 Nf6 Nf3 Nc6 g3 d5"),
+    // Réti Opening
+    (4, "Nf3 d5 g3 Nf6 Bg2 e6 O-O Be7"),
+];
+
+pub struct Book {
+    source: Source,
+}
+
+enum Source {
+    Lines(HashMap<u64, Vec<(Move, u32)>>), // The weighted moves of each position, by key
+    Polyglot(Vec<(u64, u16, u16)>),        // A file's (key, move, weight) entries, by key
+}
+
+// A Polyglot book can hold a million entries, so only their number is shown
+impl std::fmt::Debug for Book {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match &self.source {
+            Source::Lines(positions) => write!(f, "Book({} positions)", positions.len()),
+            Source::Polyglot(entries) => write!(f, "Book({} Polyglot entries)", entries.len()),
+        }
+    }
+}
+
+impl Book {
+    // The book compiled into the engine, built on first use
+    pub fn builtin() -> &'static Book {
+        static BOOK: OnceLock<Book> = OnceLock::new();
+        BOOK.get_or_init(|| Book::from_lines(LINES))
+    }
+
+    // Replays every line from the initial position. The lines are part of the program, so one
+    // that cannot be played is a bug, caught in debug builds; a release build keeps the line's
+    // moves up to the one it cannot play.
+    fn from_lines(lines: &[(u32, &str)]) -> Book {
+        let mut positions: HashMap<u64, Vec<(Move, u32)>> = HashMap::new();
+        for &(weight, line) in lines {
+            let mut board = Board::new();
+            for text in line.split_whitespace() {
+                let parsed = board.parse_move(text);
+                debug_assert!(parsed.is_ok(), "book line '{}': {:?}", line, parsed);
+                let Ok(m) = parsed else {
+                    break;
+                };
+                let moves = positions.entry(board.zobrist_key()).or_default();
+                match moves.iter_mut().find(|(known, _)| *known == m) {
+                    Some((_, total)) => *total += weight,
+                    None => moves.push((m, weight)),
+                }
+                board.apply_move(m);
+            }
+        }
+        Book {
+            source: Source::Lines(positions),
+        }
+    }
+
+    // Reads a book in the Polyglot format
+    pub fn open(path: &Path) -> Result<Book, io::Error> {
+        let mut bytes = Vec::new();
+        std::fs::File::open(path)?.read_to_end(&mut bytes)?;
+        Book::from_polyglot(&bytes)
+    }
+
+    pub fn from_polyglot(bytes: &[u8]) -> Result<Book, io::Error> {
+        let (entries, rest) = bytes.as_chunks::<16>();
+        if !rest.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a Polyglot book: its length is not a multiple of 16 bytes",
+            ));
+        }
+        let mut entries: Vec<(u64, u16, u16)> = entries
+            .iter()
+            .map(|entry| {
+                (
+                    u64::from_be_bytes(entry[..8].try_into().expect("8 of the 16 bytes")),
+                    u16::from_be_bytes([entry[8], entry[9]]),
+                    u16::from_be_bytes([entry[10], entry[11]]),
+                )
+            })
+            .collect();
+        // Books are written sorted, which the binary search in `moves` relies on
+        entries.sort_by_key(|&(key, _, _)| key);
+        Ok(Book {
+            source: Source::Polyglot(entries),
+        })
+    }
+
+    // The book's moves in `board` with their weights, none once the game has left the book. A
+    // Polyglot book's moves that are not legal in `board` are left out.
+    pub fn moves(&self, board: &Board) -> Vec<(Move, u32)> {
+        match &self.source {
+            Source::Lines(positions) => positions
+                .get(&board.zobrist_key())
+                .cloned()
+                .unwrap_or_default(),
+            Source::Polyglot(entries) => {
+                let key = polyglot_key(board);
+                let first = entries.partition_point(|&(entry_key, _, _)| entry_key < key);
+                entries[first..]
+                    .iter()
+                    .take_while(|&&(entry_key, _, _)| entry_key == key)
+                    .filter_map(|&(_, raw, weight)| {
+                        decode_move(board, raw).map(|m| (m, u32::from(weight)))
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    // Picks one of the book's moves in `board`, each as likely as its weight, or None if the
+    // book does not have the position. The same generator state picks the same move.
+    pub fn probe(&self, board: &Board, rng: &mut impl Rng) -> Option<Move> {
+        let moves = self.moves(board);
+        let total: u32 = moves.iter().map(|&(_, weight)| weight).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut pick = rng.random_range(0..total);
+        moves.into_iter().find_map(|(m, weight)| {
+            if pick < weight {
+                Some(m)
+            } else {
+                pick -= weight;
+                None
+            }
+        })
+    }
+}
@@ -0,0 +1,148 @@
+// movepick.rs
+// Staged move ordering for the search: the transposition table move first, then captures by
+// most valuable victim and least valuable attacker, then the killer moves, then the quiet
+// moves. A stage is only generated once the one before it runs out, so a node that cuts off
+// on the table move or a capture never generates its quiet moves.
+use super::{score_move, Board, Color, Move, MoveKinds, PieceType};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Stage {
+    TtMove,
+    GenerateCaptures,
+    Captures,
+    Killers,
+    Quiets,
+}
+
+pub struct MovePicker {
+    color: Color,
+    stage: Stage,
+    tt_move: Option<Move>,
+    killers: [Option<Move>; 2], // Quiet moves that caused a cutoff at the same ply elsewhere
+    moves: Vec<Move>,           // The moves of the current stage, in the order they are tried
+    index: usize,               // The next move of `moves`, or the next killer
+}
+
+impl MovePicker {
+    // `buffer` holds the generated moves, so the caller can reuse it through `into_buffer`
+    pub fn new(
+        color: Color,
+        tt_move: Option<Move>,
+        mut killers: [Option<Move>; 2],
+        mut buffer: Vec<Move>,
+    ) -> Self {
+        buffer.clear();
+        if killers[1] == killers[0] {
+            killers[1] = None;
+        }
+        MovePicker {
+            color,
+            stage: Stage::TtMove,
+            tt_move,
+            killers,
+            moves: buffer,
+            index: 0,
+        }
+    }
+
+    // Returns the next pseudo-legal move, each exactly once, or None when all have been tried
+    pub fn next(&mut self, board: &Board) -> Option<Move> {
+        loop {
+            match self.stage {
+                Stage::TtMove => {
+                    self.stage = Stage::GenerateCaptures;
+                    if let Some(m) = self.tt_move {
+                        // A move stored for another position that collided with this one
+                        // would not be playable here
+                        self.moves.clear();
+                        board.generate_moves_for_piece_into(m.from.0, m.from.1, &mut self.moves);
+                        let playable = board.piece_at(m.from.0, m.from.1).map(|p| p.color)
+                            == Some(self.color)
+                            && self.moves.contains(&m);
+                        if playable {
+                            return Some(m);
+                        }
+                        self.tt_move = None;
+                    }
+                }
+                Stage::GenerateCaptures => {
+                    self.moves.clear();
+                    board.generate_moves_of_kinds(self.color, MoveKinds::Captures, &mut self.moves);
+                    self.moves.sort_by_key(|&m| -mvv_lva(board, m));
+                    self.index = 0;
+                    self.stage = Stage::Captures;
+                }
+                Stage::Captures => match self.moves.get(self.index).copied() {
+                    Some(m) => {
+                        self.index += 1;
+                        if Some(m) != self.tt_move {
+                            return Some(m);
+                        }
+                    }
+                    None => {
+                        // The killers are checked against the quiet moves, so those are
+                        // generated before the killers are tried
+                        self.moves.clear();
+                        board.generate_moves_of_kinds(
+                            self.color,
+                            MoveKinds::Quiets,
+                            &mut self.moves,
+                        );
+                        self.moves.sort_by_key(|m| -score_move(board, m));
+                        self.index = 0;
+                        self.stage = Stage::Killers;
+                    }
+                },
+                Stage::Killers => match self.killers.get(self.index).copied() {
+                    Some(killer) => {
+                        self.index += 1;
+                        match killer {
+                            Some(m) if Some(m) != self.tt_move && self.moves.contains(&m) => {
+                                return Some(m);
+                            }
+                            // Not a quiet move in this position, so never yielded
+                            _ => self.killers[self.index - 1] = None,
+                        }
+                    }
+                    None => {
+                        self.index = 0;
+                        self.stage = Stage::Quiets;
+                    }
+                },
+                Stage::Quiets => {
+                    let m = *self.moves.get(self.index)?;
+                    self.index += 1;
+                    if Some(m) != self.tt_move && !self.killers.contains(&Some(m)) {
+                        return Some(m);
+                    }
+                }
+            }
+        }
+    }
+
+    // Hands back the move buffer for the next picker
+    pub fn into_buffer(self) -> Vec<Move> {
+        self.moves
+    }
+}
+
+// Orders captures by the value of the captured piece, then the cheapest attacker first
+fn mvv_lva(board: &Board, m: Move) -> i32 {
+    let rank = |kind: PieceType| match kind {
+        PieceType::Pawn => 1,
+        PieceType::Knight => 2,
+        PieceType::Bishop => 3,
+        PieceType::Rook => 4,
+        PieceType::Queen => 5,
+        PieceType::King => 6,
+    };
+    let ((from_row, from_col), (to_row, to_col)) = (m.from, m.to);
+    // An en passant capture lands on an empty square and takes a pawn
+    let victim = board
+        .piece_at(to_row, to_col)
+        .map_or(PieceType::Pawn, |piece| piece.kind);
+    let attacker = board
+        .piece_at(from_row, from_col)
+        .map_or(PieceType::Pawn, |piece| piece.kind);
+    rank(victim) * 8 - rank(attacker)
+}
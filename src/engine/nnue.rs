@@ -0,0 +1,222 @@
+// nnue.rs
+// An experimental neural network evaluation, built with the `nnue` feature. The network is
+// fully connected: 768 inputs, one for each piece on each square (12 pieces by 64 squares),
+// any number of hidden layers with clipped ReLU, and a single output, White's score in
+// centipawns. Only the inputs for pieces on the board are set, so the first layer adds up one
+// row of weights per piece instead of multiplying the whole input. Each board keeps those sums,
+// the accumulator, as pieces come and go (see `Board::set_piece`), so an evaluation only runs
+// the layers after the first.
+//
+// A model file is little-endian throughout:
+//   b"NNUE", the number of hidden layers (u32), each hidden layer's size (u32),
+//   then for each layer in turn its weights (f32, input by input, each input's row holding
+//   its weight into every output) followed by its biases (f32, one per output).
+use super::{piece_index, Board, Piece};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::sync::OnceLock;
+
+const MAGIC: &[u8; 4] = b"NNUE";
+// Inputs are indexed piece * 64 + row * 8 + col, pieces as in the Zobrist table
+pub const INPUTS: usize = 768;
+// The architecture of a full network; smaller ones load just the same
+pub const DEFAULT_HIDDEN_LAYERS: [usize; 2] = [256, 256];
+// No sensible network has layers this wide, so a larger size means a corrupt file
+const MAX_LAYER_SIZE: usize = 4096;
+
+struct Layer {
+    outputs: usize,
+    weights: Vec<f32>, // inputs * outputs, input by input
+    biases: Vec<f32>,
+}
+
+pub struct NnueEvaluator {
+    layers: Vec<Layer>, // The hidden layers, then the output layer
+}
+
+impl NnueEvaluator {
+    // Reads a model in the format described at the top of this file
+    pub fn new(model_path: &Path) -> Result<NnueEvaluator, io::Error> {
+        let mut bytes = Vec::new();
+        std::fs::File::open(model_path)?.read_to_end(&mut bytes)?;
+        NnueEvaluator::from_bytes(&bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<NnueEvaluator, io::Error> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message);
+        let mut rest = bytes
+            .strip_prefix(MAGIC)
+            .ok_or_else(|| invalid("not an NNUE model"))?;
+        let mut next_word = || -> Result<[u8; 4], io::Error> {
+            let (word, tail) = rest
+                .split_first_chunk::<4>()
+                .ok_or_else(|| invalid("model file ends early"))?;
+            rest = tail;
+            Ok(*word)
+        };
+
+        let hidden = u32::from_le_bytes(next_word()?) as usize;
+        let mut sizes = vec![INPUTS];
+        for _ in 0..hidden {
+            let size = u32::from_le_bytes(next_word()?) as usize;
+            if size == 0 || size > MAX_LAYER_SIZE {
+                return Err(invalid("hidden layer size out of range"));
+            }
+            sizes.push(size);
+        }
+        sizes.push(1);
+
+        let mut layers = Vec::new();
+        for pair in sizes.windows(2) {
+            let (inputs, outputs) = (pair[0], pair[1]);
+            let mut read = |count: usize| -> Result<Vec<f32>, io::Error> {
+                (0..count)
+                    .map(|_| next_word().map(f32::from_le_bytes))
+                    .collect()
+            };
+            let weights = read(inputs * outputs)?;
+            let biases = read(outputs)?;
+            layers.push(Layer {
+                outputs,
+                weights,
+                biases,
+            });
+        }
+        if !rest.is_empty() {
+            return Err(invalid("unexpected data after the output layer"));
+        }
+        Ok(NnueEvaluator { layers })
+    }
+
+    // A network of the given hidden layer sizes with weights drawn from `seed`; it plays no
+    // better than random, but exercises the format and the inference. `random(&[64], seed)`
+    // gives the smallest useful one, 768 -> 64 -> 1.
+    pub fn random(hidden_layers: &[usize], seed: u64) -> NnueEvaluator {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let sizes: Vec<usize> = std::iter::once(INPUTS)
+            .chain(hidden_layers.iter().copied())
+            .chain(std::iter::once(1))
+            .collect();
+        let layers = sizes
+            .windows(2)
+            .map(|pair| {
+                let (inputs, outputs) = (pair[0], pair[1]);
+                // Scaled so a hidden layer's outputs stay around the clipped ReLU's range, and
+                // the output around a pawn either way
+                let scale = if outputs == 1 { 100.0 } else { 1.0 } / (inputs as f32).sqrt();
+                Layer {
+                    outputs,
+                    weights: (0..inputs * outputs)
+                        .map(|_| rng.random_range(-scale..scale))
+                        .collect(),
+                    biases: (0..outputs)
+                        .map(|_| rng.random_range(-scale..scale))
+                        .collect(),
+                }
+            })
+            .collect();
+        NnueEvaluator { layers }
+    }
+
+    // Writes the model in the format `new` reads
+    pub fn save(&self, model_path: &Path) -> Result<(), io::Error> {
+        let mut bytes = MAGIC.to_vec();
+        let hidden = &self.layers[..self.layers.len() - 1];
+        bytes.extend((hidden.len() as u32).to_le_bytes());
+        for layer in hidden {
+            bytes.extend((layer.outputs as u32).to_le_bytes());
+        }
+        for layer in &self.layers {
+            for value in layer.weights.iter().chain(&layer.biases) {
+                bytes.extend(value.to_le_bytes());
+            }
+        }
+        std::fs::File::create(model_path)?.write_all(&bytes)
+    }
+
+    // The first layer's sums with no piece on the board: its biases
+    pub fn empty_accumulator(&self) -> Vec<f32> {
+        self.layers[0].biases.clone()
+    }
+
+    // The first layer's sums for the pieces on `board`, added up afresh
+    pub fn accumulate(&self, board: &Board) -> Vec<f32> {
+        let mut accumulator = self.empty_accumulator();
+        for row in 0..8 {
+            for col in 0..8 {
+                if let Some(piece) = board.piece_at(row, col) {
+                    self.update(&mut accumulator, &piece, (row, col), 1.0);
+                }
+            }
+        }
+        accumulator
+    }
+
+    // Adds the weights of `piece` on `square` to the sums, times `sign`: 1.0 for a piece put
+    // down, -1.0 for one taken away
+    pub fn update(
+        &self,
+        accumulator: &mut [f32],
+        piece: &Piece,
+        square: (usize, usize),
+        sign: f32,
+    ) {
+        let first = &self.layers[0];
+        let input = piece_index(piece) * 64 + square.0 * 8 + square.1;
+        let weights = &first.weights[input * first.outputs..][..first.outputs];
+        for (value, weight) in accumulator.iter_mut().zip(weights) {
+            *value += sign * weight;
+        }
+    }
+
+    // White's score in centipawns, from the board's accumulator when it has one for this
+    // network
+    pub fn evaluate(&self, board: &Board) -> i32 {
+        match board.accumulator() {
+            accumulator if accumulator.len() == self.layers[0].outputs => {
+                self.evaluate_accumulator(accumulator)
+            }
+            _ => self.evaluate_from_scratch(board),
+        }
+    }
+
+    // The same score with the first layer summed afresh
+    pub fn evaluate_from_scratch(&self, board: &Board) -> i32 {
+        self.evaluate_accumulator(&self.accumulate(board))
+    }
+
+    fn evaluate_accumulator(&self, accumulator: &[f32]) -> i32 {
+        let mut values = accumulator.to_vec();
+        for layer in &self.layers[1..] {
+            let mut next = layer.biases.clone();
+            for (input, value) in values.iter().enumerate() {
+                let activation = value.clamp(0.0, 1.0);
+                if activation == 0.0 {
+                    continue;
+                }
+                let weights = &layer.weights[input * layer.outputs..][..layer.outputs];
+                for (output, weight) in next.iter_mut().zip(weights) {
+                    *output += activation * weight;
+                }
+            }
+            values = next;
+        }
+        debug_assert_eq!(values.len(), 1);
+        values[0].round() as i32
+    }
+}
+
+static NETWORK: OnceLock<NnueEvaluator> = OnceLock::new();
+
+// Makes `evaluate_position` use `network` from now on. Only the first network set is kept;
+// returns false if one was set already.
+pub fn set_network(network: NnueEvaluator) -> bool {
+    NETWORK.set(network).is_ok()
+}
+
+// The network `evaluate_position` uses, if one has been set
+pub fn network() -> Option<&'static NnueEvaluator> {
+    NETWORK.get()
+}
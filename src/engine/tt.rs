@@ -0,0 +1,190 @@
+// tt.rs
+// A transposition table shared by every search thread. Each slot stores the key XORed with
+// its data next to the data itself, so a slot torn by two threads writing at once fails the
+// key check on probe instead of returning a mix of two entries. Slots come in buckets of two:
+// the first keeps the deepest entry of the positions hashing there, the second always takes
+// the latest entry that did not go into the first.
+use super::{Move, MATE_VALUE, PROMOTION_PIECES};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// Default table size, in megabytes
+pub const DEFAULT_HASH_MB: usize = 16;
+
+// How the stored score relates to the true score of the position
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Bound {
+    Exact,
+    Lower, // The search failed high: the true score is at least `score`
+    Upper, // The search failed low: the true score is at most `score`
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct TtEntry {
+    pub best_move: Option<Move>,
+    pub score: i32, // From White's point of view, mate scores relative to the stored position
+    pub depth: u32,
+    pub bound: Bound,
+}
+
+struct Slot {
+    check: AtomicU64, // key ^ data
+    data: AtomicU64,
+}
+
+pub struct TranspositionTable {
+    slots: Vec<Slot>,
+    mask: usize, // Selects a bucket from a key
+}
+
+impl TranspositionTable {
+    // Allocates the largest power-of-two number of slots that fits in `megabytes`, at least one
+    // bucket
+    pub fn new(megabytes: usize) -> Self {
+        let capacity = (megabytes.max(1) * 1024 * 1024 / std::mem::size_of::<Slot>()).max(2);
+        let len = if capacity.is_power_of_two() {
+            capacity
+        } else {
+            capacity.next_power_of_two() / 2
+        };
+        TranspositionTable {
+            slots: (0..len)
+                .map(|_| Slot {
+                    check: AtomicU64::new(0),
+                    data: AtomicU64::new(0),
+                })
+                .collect(),
+            mask: len / 2 - 1,
+        }
+    }
+
+    // The depth-preferred and always-replace slots `key` hashes to
+    fn bucket(&self, key: u64) -> &[Slot] {
+        let index = (key as usize & self.mask) * 2;
+        &self.slots[index..index + 2]
+    }
+
+    pub fn probe(&self, key: u64, ply: usize) -> Option<TtEntry> {
+        self.bucket(key).iter().find_map(|slot| {
+            let mut entry = unpack(slot.load(key)?);
+            entry.score = score_from_tt(entry.score, ply);
+            Some(entry)
+        })
+    }
+
+    // Keeps the entry in the depth-preferred slot unless that holds a deeper search of another
+    // position, in which case the always-replace slot takes it
+    pub fn store(&self, key: u64, ply: usize, entry: TtEntry) {
+        let data = pack(TtEntry {
+            score: score_to_tt(entry.score, ply),
+            ..entry
+        });
+        let [preferred, always] = self.bucket(key) else {
+            unreachable!("a bucket holds two slots")
+        };
+        let stored = preferred.data.load(Ordering::Relaxed);
+        let keep_preferred =
+            stored != 0 && preferred.load(key).is_none() && unpack(stored).depth > entry.depth;
+        let slot = if keep_preferred { always } else { preferred };
+        slot.check.store(key ^ data, Ordering::Relaxed);
+        slot.data.store(data, Ordering::Relaxed);
+    }
+
+    // Permille of the table in use, from a sample of its first thousand slots
+    pub fn hash_full(&self) -> u32 {
+        let sample = &self.slots[..self.slots.len().min(1000)];
+        let used = sample
+            .iter()
+            .filter(|slot| slot.data.load(Ordering::Relaxed) != 0)
+            .count();
+        (used * 1000 / sample.len()) as u32
+    }
+}
+
+impl Slot {
+    // The slot's data if it holds an entry for `key`
+    fn load(&self, key: u64) -> Option<u64> {
+        let data = self.data.load(Ordering::Relaxed);
+        (data != 0 && self.check.load(Ordering::Relaxed) ^ data == key).then_some(data)
+    }
+}
+
+// Mate scores count plies from the root, but an entry can be reached at any ply, so they are
+// stored relative to the position itself
+const MATE_THRESHOLD: i32 = MATE_VALUE - 1000;
+
+fn score_to_tt(score: i32, ply: usize) -> i32 {
+    if score > MATE_THRESHOLD {
+        score + ply as i32
+    } else if score < -MATE_THRESHOLD {
+        score - ply as i32
+    } else {
+        score
+    }
+}
+
+fn score_from_tt(score: i32, ply: usize) -> i32 {
+    if score > MATE_THRESHOLD {
+        score - ply as i32
+    } else if score < -MATE_THRESHOLD {
+        score + ply as i32
+    } else {
+        score
+    }
+}
+
+// Layout: score (16 bits) | depth (8) | bound (2) | promotion (3) | move present (1) |
+// from (6) | to (6). The promotion field is 0 for none, else one more than the piece's index
+// in PROMOTION_PIECES. A stored entry always has a non-zero bound field, so 0 marks an empty
+// slot.
+fn pack(entry: TtEntry) -> u64 {
+    let bound = match entry.bound {
+        Bound::Exact => 1,
+        Bound::Lower => 2,
+        Bound::Upper => 3,
+    };
+    let mv = match entry.best_move {
+        Some(Move {
+            from: (fr, fc),
+            to: (tr, tc),
+            promotion,
+        }) => {
+            let promotion = promotion.map_or(0, |kind| {
+                PROMOTION_PIECES.iter().position(|&p| p == kind).unwrap() + 1
+            });
+            (promotion << 13 | 1 << 12 | (fr * 8 + fc) << 6 | (tr * 8 + tc)) as u64
+        }
+        None => 0,
+    };
+    (entry.score as i16 as u16 as u64)
+        | (entry.depth.min(255) as u64) << 16
+        | bound << 24
+        | mv << 26
+}
+
+fn unpack(data: u64) -> TtEntry {
+    let bound = match (data >> 24) & 3 {
+        1 => Bound::Exact,
+        2 => Bound::Lower,
+        _ => Bound::Upper,
+    };
+    let mv = data >> 26;
+    let best_move = if mv & (1 << 12) != 0 {
+        let (from, to) = (((mv >> 6) & 63) as usize, (mv & 63) as usize);
+        Some(Move {
+            from: (from / 8, from % 8),
+            to: (to / 8, to % 8),
+            promotion: match (mv >> 13) & 7 {
+                0 => None,
+                index => Some(PROMOTION_PIECES[index as usize - 1]),
+            },
+        })
+    } else {
+        None
+    };
+    TtEntry {
+        best_move,
+        score: data as u16 as i16 as i32,
+        depth: ((data >> 16) & 255) as u32,
+        bound,
+    }
+}
@@ -0,0 +1,278 @@
+// uci_client.rs
+// Another engine as the opponent, spoken to over UCI: `UciEngine::spawn` starts the executable
+// (Stockfish, say) and does the handshake, and `best_move` sends it the game so far with
+// `go movetime`, reading its `bestmove` back as a `Move`. Chess960 games, where castling is
+// written as the King taking its own Rook, are played with `UCI_Chess960` set. The engine's
+// output is read on a thread of its own, so an engine that hangs or dies is reported instead
+// of waited on. In a browser nothing can be spawned, and `spawn` always fails.
+use super::{move_to_uci, Board, Color, Duration, Instant, Move, SearchResult, MATE_VALUE};
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+
+// How long the engine has to answer `uci` and `isready` after starting
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+// How long past its move time the engine has to answer before it counts as hung
+const ANSWER_GRACE: Duration = Duration::from_secs(5);
+// How often a waiting `best_move` looks at its stop flag
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+// Why the engine could not give a move
+#[derive(Debug)]
+pub enum UciClientError {
+    Spawn(io::Error),      // The executable could not be started
+    Io(io::Error),         // Writing to the engine failed, as when it has exited
+    Exited,                // The engine closed its output, having crashed or quit
+    Timeout(&'static str), // The engine did not send this reply in time
+    IllegalMove(String),   // The engine's best move cannot be played in the position
+    NoChess960,            // The game is Chess960 and the engine has no `UCI_Chess960` option
+}
+
+impl fmt::Display for UciClientError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UciClientError::Spawn(error) => write!(f, "could not start the engine: {}", error),
+            UciClientError::Io(error) => write!(f, "could not talk to the engine: {}", error),
+            UciClientError::Exited => write!(f, "the engine exited"),
+            UciClientError::Timeout(reply) => write!(f, "the engine did not send {}", reply),
+            UciClientError::IllegalMove(text) => {
+                write!(f, "the engine played an illegal move '{}'", text)
+            }
+            UciClientError::NoChess960 => write!(f, "the engine does not play Chess960"),
+        }
+    }
+}
+
+pub struct UciEngine {
+    path: PathBuf,
+    name: Option<String>,  // From `id name`, when the engine sent one
+    offers_chess960: bool, // Whether it listed the `UCI_Chess960` option
+    chess960: bool,        // Whether `UCI_Chess960` is set
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>, // The engine's output, a line at a time
+}
+
+impl UciEngine {
+    // Starts the engine at `path` and waits until it has said `uciok` and `readyok`
+    pub fn spawn(path: &Path) -> Result<UciEngine, UciClientError> {
+        let mut child = Command::new(path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(UciClientError::Spawn)?;
+        let (stdin, stdout) = match (child.stdin.take(), child.stdout.take()) {
+            (Some(stdin), Some(stdout)) => (stdin, stdout),
+            _ => return Err(UciClientError::Exited),
+        };
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        let mut engine = UciEngine {
+            path: path.to_path_buf(),
+            name: None,
+            offers_chess960: false,
+            chess960: false,
+            child,
+            stdin,
+            lines,
+        };
+
+        let deadline = Instant::now() + HANDSHAKE_TIMEOUT;
+        engine.send("uci")?;
+        loop {
+            let line = engine.next_line(deadline, "uciok")?;
+            if let Some(name) = line.strip_prefix("id name ") {
+                engine.name = Some(name.trim().to_string());
+            } else if line.starts_with("option name UCI_Chess960 ") {
+                engine.offers_chess960 = true;
+            } else if line.trim() == "uciok" {
+                break;
+            }
+        }
+        engine.send("isready")?;
+        while engine.next_line(deadline, "readyok")?.trim() != "readyok" {}
+        Ok(engine)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    // Asks for a move in the position reached by playing `moves` from `start`, letting the
+    // engine think for `move_time`. Setting `stop` tells it to answer at once. The result
+    // carries the last `info` line's depth, score, nodes and line, with the score turned to
+    // White's point of view. An engine without `UCI_Chess960` cannot be asked for a move in a
+    // Chess960 game.
+    pub fn best_move(
+        &mut self,
+        start: &Board,
+        moves: &[Move],
+        move_time: Duration,
+        stop: &AtomicBool,
+    ) -> Result<SearchResult, UciClientError> {
+        let chess960 = start.is_chess960();
+        if chess960 != self.chess960 {
+            if !self.offers_chess960 {
+                return Err(UciClientError::NoChess960);
+            }
+            self.send(&format!("setoption name UCI_Chess960 value {}", chess960))?;
+            self.chess960 = chess960;
+        }
+        let mut board = start.clone();
+        for &m in moves {
+            board.apply_move(m);
+        }
+        let mut position = format!("position fen {}", start.to_fen());
+        if !moves.is_empty() {
+            let moves: Vec<String> = moves.iter().map(|&m| move_to_uci(m)).collect();
+            position = format!("{} moves {}", position, moves.join(" "));
+        }
+        self.send(&position)?;
+        self.send(&format!("go movetime {}", move_time.as_millis()))?;
+
+        let started = Instant::now();
+        let deadline = started + move_time + ANSWER_GRACE;
+        let mut report = SearchReport::default();
+        let mut stop_sent = false;
+        loop {
+            if !stop_sent && stop.load(Ordering::Relaxed) {
+                self.send("stop")?;
+                stop_sent = true;
+            }
+            let line = match self.lines.recv_timeout(POLL_INTERVAL) {
+                Ok(line) => line,
+                Err(RecvTimeoutError::Timeout) if Instant::now() < deadline => continue,
+                Err(RecvTimeoutError::Timeout) => return Err(UciClientError::Timeout("bestmove")),
+                Err(RecvTimeoutError::Disconnected) => return Err(UciClientError::Exited),
+            };
+            let mut words = line.split_whitespace();
+            match words.next() {
+                Some("info") => report.read_info(&board, words),
+                Some("bestmove") => {
+                    let text = words.next().unwrap_or("");
+                    let best_move = board
+                        .parse_uci_move(text)
+                        .map_err(|_| UciClientError::IllegalMove(text.to_string()))?;
+                    // The line only stands if it starts with the move played
+                    if report.pv.first() != Some(&best_move) {
+                        report.pv = vec![best_move];
+                    }
+                    return Ok(SearchResult {
+                        best_move,
+                        score: report.score,
+                        pv: report.pv,
+                        depth: report.depth,
+                        nodes: report.nodes,
+                        cutoffs: 0,
+                        time: started.elapsed(),
+                        hash_full: report.hash_full,
+                    });
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn send(&mut self, command: &str) -> Result<(), UciClientError> {
+        writeln!(self.stdin, "{}", command)
+            .and_then(|_| self.stdin.flush())
+            .map_err(UciClientError::Io)
+    }
+
+    // The engine's next line, waiting until `deadline` for it; `reply` names what is awaited
+    fn next_line(&self, deadline: Instant, reply: &'static str) -> Result<String, UciClientError> {
+        let wait = deadline.saturating_duration_since(Instant::now());
+        self.lines.recv_timeout(wait).map_err(|error| match error {
+            RecvTimeoutError::Timeout => UciClientError::Timeout(reply),
+            RecvTimeoutError::Disconnected => UciClientError::Exited,
+        })
+    }
+}
+
+// Asks the engine to quit, and ends it if it has not after a moment
+impl Drop for UciEngine {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        let deadline = Instant::now() + Duration::from_millis(200);
+        while Instant::now() < deadline {
+            if let Ok(Some(_)) = self.child.try_wait() {
+                return;
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+// What the engine's `info` lines have said so far about its search
+#[derive(Default)]
+struct SearchReport {
+    depth: u32,
+    score: i32, // From White's point of view
+    nodes: u64,
+    hash_full: u32,
+    pv: Vec<Move>,
+}
+
+impl SearchReport {
+    // Takes in one `info` line from a search of `board`. Scores come for the side to move,
+    // "cp 34" or "mate -3" in moves; a line is kept as far as its moves are legal.
+    fn read_info<'a>(&mut self, board: &Board, mut words: impl Iterator<Item = &'a str>) {
+        let for_white = |score: i32| match board.side_to_move {
+            Color::White => score,
+            Color::Black => -score,
+        };
+        while let Some(word) = words.next() {
+            match word {
+                "depth" => self.depth = words.next().and_then(|n| n.parse().ok()).unwrap_or(0),
+                "nodes" => self.nodes = words.next().and_then(|n| n.parse().ok()).unwrap_or(0),
+                "hashfull" => {
+                    self.hash_full = words.next().and_then(|n| n.parse().ok()).unwrap_or(0)
+                }
+                "score" => match (
+                    words.next(),
+                    words.next().and_then(|n| n.parse::<i32>().ok()),
+                ) {
+                    (Some("cp"), Some(centipawns)) => self.score = for_white(centipawns),
+                    // Mate in n moves is 2n - 1 plies away, being mated in n is 2n
+                    (Some("mate"), Some(moves)) if moves > 0 => {
+                        self.score = for_white(MATE_VALUE - (2 * moves - 1))
+                    }
+                    (Some("mate"), Some(moves)) => self.score = for_white(-MATE_VALUE - 2 * moves),
+                    _ => {}
+                },
+                // The line runs to the end of the info
+                "pv" => {
+                    let mut position = board.clone();
+                    self.pv.clear();
+                    for text in words.by_ref() {
+                        let Ok(m) = position.parse_uci_move(text) else {
+                            break;
+                        };
+                        position.apply_move(m);
+                        self.pv.push(m);
+                    }
+                }
+                // Free text runs to the end of the info too
+                "string" => break,
+                _ => {}
+            }
+        }
+    }
+}
@@ -0,0 +1,295 @@
+// epd.rs
+// Extended Position Description records, read and written, and a runner for test suites of
+// them such as WAC or Bratko-Kopec, for `chess --epd FILE`. An EPD line is the first four FEN
+// fields followed by operations ending in semicolons, `bm Qg6; id "WAC.001";`; the runner
+// searches each position and counts the best moves found.
+use crate::clock::TIMED_SEARCH_DEPTH;
+use crate::engine::{improved_best_move_for_color, Board, Move, SearchLimits};
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+// The engine depth when none is given, deep enough for the simpler tactical suites
+pub const DEFAULT_DEPTH: u32 = 4;
+
+#[derive(Clone, Debug)]
+pub struct Epd {
+    pub board: Board,
+    pub id: Option<String>,          // The `id` operation, naming the position
+    pub best_moves: Vec<Move>,       // `bm`: any of these solves the position
+    pub avoid_moves: Vec<Move>,      // `am`: none of these may be played
+    pub centipawn_eval: Option<i32>, // `ce`: the position's score for the side to move
+}
+
+impl Epd {
+    // Reads one EPD record. Moves are in SAN, or in coordinates as some suites write them,
+    // and must be legal in the position; operations other than bm, am, id and ce are skipped.
+    pub fn parse_line(line: &str) -> Result<Epd, String> {
+        let mut fields = line.trim().splitn(5, char::is_whitespace);
+        let position: Vec<&str> = fields.by_ref().take(4).collect();
+        let board = Board::from_fen(&position.join(" "))?;
+        board.validate()?;
+        let mut epd = Epd {
+            board,
+            id: None,
+            best_moves: Vec::new(),
+            avoid_moves: Vec::new(),
+            centipawn_eval: None,
+        };
+
+        for operation in split_operations(fields.next().unwrap_or("")) {
+            let (opcode, operands) = operation
+                .split_once(char::is_whitespace)
+                .unwrap_or((operation, ""));
+            let operands = operands.trim();
+            let moves = || -> Result<Vec<Move>, String> {
+                operands
+                    .split_whitespace()
+                    .map(|text| {
+                        epd.board
+                            .parse_move(text)
+                            .map_err(|error| error.to_string())
+                    })
+                    .collect()
+            };
+            match opcode {
+                "bm" => epd.best_moves = moves()?,
+                "am" => epd.avoid_moves = moves()?,
+                "id" => epd.id = Some(operands.trim_matches('"').to_string()),
+                "ce" => {
+                    epd.centipawn_eval = Some(
+                        operands
+                            .parse()
+                            .map_err(|_| format!("invalid ce '{}'", operands))?,
+                    )
+                }
+                _ => {}
+            }
+        }
+        Ok(epd)
+    }
+
+    // Whether playing `m` passes the position: it is one of the best moves, if any are
+    // given, and none of the moves to avoid
+    pub fn is_solved_by(&self, m: Move) -> bool {
+        (self.best_moves.is_empty() || self.best_moves.contains(&m))
+            && !self.avoid_moves.contains(&m)
+    }
+}
+
+// Writes the record back as one EPD line, the position's first four FEN fields followed by
+// its operations, moves in SAN: `... w - - bm Qg6; id "WAC.001";`. Reading it gives the same
+// record back.
+impl fmt::Display for Epd {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let fen = self.board.to_fen();
+        let position: Vec<&str> = fen.split_whitespace().take(4).collect();
+        write!(f, "{}", position.join(" "))?;
+        for (opcode, moves) in [("bm", &self.best_moves), ("am", &self.avoid_moves)] {
+            if !moves.is_empty() {
+                let moves: Vec<String> = moves.iter().map(|&m| self.board.move_to_san(m)).collect();
+                write!(f, " {} {};", opcode, moves.join(" "))?;
+            }
+        }
+        if let Some(centipawns) = self.centipawn_eval {
+            write!(f, " ce {};", centipawns)?;
+        }
+        if let Some(id) = &self.id {
+            write!(f, " id \"{}\";", id)?;
+        }
+        Ok(())
+    }
+}
+
+// The operations of a record, without their semicolons; a quoted operand may hold one
+fn split_operations(text: &str) -> Vec<&str> {
+    let mut operations = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+    for (i, c) in text.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ';' if !quoted => {
+                operations.push(text[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    operations.push(text[start..].trim());
+    operations.retain(|operation| !operation.is_empty());
+    operations
+}
+
+// How the engine did on one position of a suite
+#[derive(Clone, Debug)]
+pub struct PositionReport {
+    pub id: String,            // The `id` operation, or the position's line number
+    pub found: Option<String>, // The engine's move in SAN, None if it had none
+    pub expected: String,      // What would have passed, "bm Qg6" or "am Rxb2"
+    pub passed: bool,
+    pub time: Duration,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SuiteReport {
+    pub positions: Vec<PositionReport>,
+}
+
+impl SuiteReport {
+    pub fn passed(&self) -> usize {
+        self.positions.iter().filter(|report| report.passed).count()
+    }
+
+    pub fn total_time(&self) -> Duration {
+        self.positions.iter().map(|report| report.time).sum()
+    }
+}
+
+// One line per position, then the score:
+//
+// WAC.001  pass  Qg6    bm Qg6   0.21s
+// WAC.002  FAIL  Kf7    bm Rxb2  0.35s
+// Solved 1 of 2 in 0.56s
+impl fmt::Display for SuiteReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for report in &self.positions {
+            writeln!(
+                f,
+                "{:<10} {}  {:<7} {:<12} {:.2}s",
+                report.id,
+                if report.passed { "pass" } else { "FAIL" },
+                report.found.as_deref().unwrap_or("-"),
+                report.expected,
+                report.time.as_secs_f64()
+            )?;
+        }
+        write!(
+            f,
+            "Solved {} of {} in {:.2}s",
+            self.passed(),
+            self.positions.len(),
+            self.total_time().as_secs_f64()
+        )
+    }
+}
+
+// Searches every position of the EPD file at `path` with `limits`, and for at most
+// `move_time` each when given, reporting which were solved. Blank lines and lines starting
+// with '#' are skipped; any other line that is not a valid record is an error naming it.
+pub fn run_epd_suite(
+    path: &Path,
+    limits: &SearchLimits,
+    move_time: Option<Duration>,
+) -> Result<SuiteReport, io::Error> {
+    let text = std::fs::read_to_string(path)?;
+    let mut report = SuiteReport::default();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let epd = Epd::parse_line(line).map_err(|error| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("line {}: {}", number + 1, error),
+            )
+        })?;
+        report
+            .positions
+            .push(run_position(&epd, limits, move_time, number + 1));
+    }
+    Ok(report)
+}
+
+fn run_position(
+    epd: &Epd,
+    limits: &SearchLimits,
+    move_time: Option<Duration>,
+    line_number: usize,
+) -> PositionReport {
+    let board = &epd.board;
+    let limits = SearchLimits {
+        stop: move_time.map(|move_time| {
+            let stop = Arc::new(AtomicBool::new(false));
+            let timer = Arc::clone(&stop);
+            std::thread::spawn(move || {
+                std::thread::sleep(move_time);
+                timer.store(true, Ordering::Relaxed);
+            });
+            stop
+        }),
+        ..limits.clone()
+    };
+    let start = Instant::now();
+    let found =
+        improved_best_move_for_color(board, board.side_to_move, &limits, &[board.zobrist_key()])
+            .map(|result| result.best_move);
+    let time = start.elapsed();
+
+    let san = |moves: &[Move]| {
+        moves
+            .iter()
+            .map(|&m| board.move_to_san(m))
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    let expected = match (epd.best_moves.is_empty(), epd.avoid_moves.is_empty()) {
+        (false, true) => format!("bm {}", san(&epd.best_moves)),
+        (true, false) => format!("am {}", san(&epd.avoid_moves)),
+        (false, false) => format!("bm {} am {}", san(&epd.best_moves), san(&epd.avoid_moves)),
+        (true, true) => "any move".to_string(),
+    };
+    PositionReport {
+        id: epd
+            .id
+            .clone()
+            .unwrap_or_else(|| format!("line {}", line_number)),
+        found: found.map(|m| board.move_to_san(m)),
+        expected,
+        passed: found.is_some_and(|m| epd.is_solved_by(m)),
+        time,
+    }
+}
+
+// Reads `--depth N` and `--time SECONDS` from the arguments after `--epd FILE`: the search
+// limits and the time for each position. With only a time, the search goes as deep as it
+// gets in that time.
+pub fn options_from_args(args: &[String]) -> Result<(SearchLimits, Option<Duration>), String> {
+    let mut depth = None;
+    let mut move_time = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| format!("{} needs a value", arg))?;
+        match arg.as_str() {
+            "--depth" => {
+                depth = Some(
+                    value
+                        .parse()
+                        .map_err(|_| format!("invalid depth '{}'", value))?,
+                )
+            }
+            "--time" => {
+                let seconds: f64 = value
+                    .parse()
+                    .ok()
+                    .filter(|seconds| (0.0..1e6).contains(seconds))
+                    .ok_or_else(|| format!("invalid time '{}'", value))?;
+                move_time = Some(Duration::from_secs_f64(seconds));
+            }
+            other => return Err(format!("unknown option '{}'", other)),
+        }
+    }
+    let depth = depth.unwrap_or(match move_time {
+        Some(_) => TIMED_SEARCH_DEPTH,
+        None => DEFAULT_DEPTH,
+    });
+    Ok((SearchLimits::depth(depth), move_time))
+}
+
+pub const USAGE: &str = "usage: chess --epd FILE [--depth N] [--time SECONDS]";
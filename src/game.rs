@@ -0,0 +1,535 @@
+// game.rs
+// A game as a list of moves from the start position, each with an optional comment, its
+// annotation glyphs and the variations played instead of it, and the game's PGN tags, read
+// from and written to PGN. Moves are written in standard algebraic notation ("Nf3"), and read
+// in either that or coordinate notation.
+use crate::engine::{Board, ChessError, Color, Move};
+use std::collections::HashMap;
+use std::fmt;
+
+// The move-quality Numeric Annotation Glyphs, $1 to $6 in PGN and in that order
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Nag {
+    Good,        // !
+    Mistake,     // ?
+    Brilliant,   // !!
+    Blunder,     // ??
+    Interesting, // !?
+    Dubious,     // ?!
+}
+
+impl Nag {
+    pub const ALL: [Nag; 6] = [
+        Nag::Good,
+        Nag::Mistake,
+        Nag::Brilliant,
+        Nag::Blunder,
+        Nag::Interesting,
+        Nag::Dubious,
+    ];
+
+    pub fn symbol(&self) -> &'static str {
+        match *self {
+            Nag::Good => "!",
+            Nag::Brilliant => "!!",
+            Nag::Mistake => "?",
+            Nag::Blunder => "??",
+            Nag::Interesting => "!?",
+            Nag::Dubious => "?!",
+        }
+    }
+
+    // The glyph's number, written as `$n` in PGN
+    pub fn number(&self) -> u8 {
+        Nag::ALL.iter().position(|nag| nag == self).unwrap() as u8 + 1
+    }
+
+    pub fn from_number(number: u8) -> Option<Nag> {
+        Nag::ALL.get((number as usize).checked_sub(1)?).copied()
+    }
+
+    pub fn from_symbol(symbol: &str) -> Option<Nag> {
+        Nag::ALL.iter().find(|nag| nag.symbol() == symbol).copied()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameMove {
+    pub mv: Move,
+    pub annotation: Option<String>, // The comment after the move
+    // Every Numeric Annotation Glyph after the move, `$n` in PGN, in the order given: the
+    // move-quality ones `Nag` names and any others, such as position assessments
+    pub nags: Vec<u8>,
+    // Lines played instead of this move, each from the position before it
+    pub variations: Vec<Vec<GameMove>>,
+}
+
+impl GameMove {
+    // A move without comment, glyphs or variations
+    pub fn new(mv: Move) -> Self {
+        GameMove {
+            mv,
+            annotation: None,
+            nags: Vec::new(),
+            variations: Vec::new(),
+        }
+    }
+
+    // The move's quality glyph, the first of its glyphs that is one
+    pub fn nag(&self) -> Option<Nag> {
+        self.nags
+            .iter()
+            .find_map(|&number| Nag::from_number(number))
+    }
+
+    // Replaces the move's quality glyph, keeping its other glyphs
+    pub fn set_nag(&mut self, nag: Option<Nag>) {
+        self.nags
+            .retain(|&number| Nag::from_number(number).is_none());
+        if let Some(nag) = nag {
+            self.nags.insert(0, nag.number());
+        }
+    }
+}
+
+// The game's PGN tags: the Seven Tag Roster, then any others by name. A tag missing from the
+// PGN, or given as "?", is left empty here and written as the PGN "unknown" value.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GameMetadata {
+    pub event: String,
+    pub site: String,
+    pub date: String, // "YYYY.MM.DD", with "??" for unknown parts
+    pub round: String,
+    pub white: String,
+    pub black: String,
+    pub result: String, // "1-0", "0-1", "1/2-1/2" or "*"
+    pub extra_tags: HashMap<String, String>,
+}
+
+impl GameMetadata {
+    // Stores a tag in its field, or in `extra_tags` if it is not one of the seven
+    pub fn set_tag(&mut self, name: &str, value: String) {
+        match name {
+            "Event" => self.event = value,
+            "Site" => self.site = value,
+            "Date" => self.date = value,
+            "Round" => self.round = value,
+            "White" => self.white = value,
+            "Black" => self.black = value,
+            "Result" => self.result = value,
+            _ => {
+                self.extra_tags.insert(name.to_string(), value);
+            }
+        }
+    }
+
+    // The tags in export order: the seven in their standard order, then the others by name
+    pub fn tags<'a>(&'a self) -> Vec<(&'a str, &'a str)> {
+        let or_unknown = |unknown, field: &'a str| if field.is_empty() { unknown } else { field };
+        let mut tags = vec![
+            ("Event", or_unknown("?", &self.event)),
+            ("Site", or_unknown("?", &self.site)),
+            ("Date", or_unknown("????.??.??", &self.date)),
+            ("Round", or_unknown("?", &self.round)),
+            ("White", or_unknown("?", &self.white)),
+            ("Black", or_unknown("?", &self.black)),
+            ("Result", or_unknown("*", &self.result)),
+        ];
+        let mut extra: Vec<_> = self
+            .extra_tags
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect();
+        extra.sort();
+        tags.extend(extra);
+        tags
+    }
+}
+
+// Why a PGN game could not be read
+#[derive(Clone, Debug, PartialEq)]
+pub enum PgnParseError {
+    MalformedTag(String), // A tag pair not of the form [Name "Value"]
+    // A token that is neither a move nor movetext punctuation. This and the other move errors
+    // hold the token with the number of the move it stands for, "12... Nxe5".
+    UnrecognisedMove(String),
+    IllegalMove(String),   // A move that cannot be played in its position
+    AmbiguousMove(String), // A SAN move that more than one legal move fits
+    InvalidFen(String),    // A FEN tag that is not a legal position
+}
+
+impl fmt::Display for PgnParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PgnParseError::MalformedTag(tag) => write!(f, "malformed tag '{}'", tag),
+            PgnParseError::UnrecognisedMove(token) => write!(f, "unrecognised move '{}'", token),
+            PgnParseError::IllegalMove(token) => write!(f, "illegal move '{}'", token),
+            PgnParseError::AmbiguousMove(token) => write!(f, "ambiguous move '{}'", token),
+            PgnParseError::InvalidFen(error) => write!(f, "invalid FEN tag: {}", error),
+        }
+    }
+}
+
+// Export Format lines are kept under 80 characters
+const PGN_LINE_WIDTH: usize = 79;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Game {
+    pub metadata: GameMetadata,
+    pub moves: Vec<GameMove>,
+}
+
+impl Game {
+    // A game of the given moves, without annotations
+    pub fn from_moves(moves: &[Move]) -> Self {
+        Game {
+            metadata: GameMetadata::default(),
+            moves: moves.iter().map(|&mv| GameMove::new(mv)).collect(),
+        }
+    }
+
+    // The position the game starts from: the FEN tag's, if it has a valid one, or else the
+    // initial position. A Chess960 game's castling follows from the FEN, and its Chess960 tag
+    // numbers the position.
+    pub fn start_position(&self) -> Board {
+        let tags = &self.metadata.extra_tags;
+        let mut board: Board = tags
+            .get("FEN")
+            .and_then(|fen| Board::from_fen(fen).ok())
+            .unwrap_or_default();
+        if tags
+            .get("Variant")
+            .is_some_and(|variant| variant == "Chess960")
+        {
+            board.chess960_id = tags
+                .get("Chess960")
+                .and_then(|id| id.parse().ok())
+                .filter(|&id| id < 960);
+        }
+        board
+    }
+
+    // The position after the first `count` moves
+    pub fn board_after(&self, count: usize) -> Board {
+        let mut board = self.start_position();
+        for game_move in self.moves.iter().take(count) {
+            board.apply_move(game_move.mv);
+        }
+        board
+    }
+
+    // PGN: the tag pairs, a blank line, then the numbered moves in SAN, each followed by its
+    // glyphs as `$n`, its comment in braces and its variations in parentheses, ending with the
+    // result. A game with a FEN tag gets the SetUp tag that goes with it. The movetext is
+    // wrapped at PGN_LINE_WIDTH.
+    pub fn to_pgn(&self) -> String {
+        let mut metadata = self.metadata.clone();
+        if metadata.extra_tags.contains_key("FEN") {
+            metadata
+                .extra_tags
+                .entry("SetUp".to_string())
+                .or_insert_with(|| "1".to_string());
+        }
+        let mut pgn = String::new();
+        for (name, value) in metadata.tags() {
+            let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+            pgn.push_str(&format!("[{} \"{}\"]\n", name, value));
+        }
+        pgn.push('\n');
+        let mut tokens = Vec::new();
+        push_movetext(&self.moves, self.start_position(), &mut tokens);
+        tokens.push(match self.metadata.result.as_str() {
+            result @ ("1-0" | "0-1" | "1/2-1/2") => result.to_string(),
+            _ => "*".to_string(),
+        });
+        let mut line = String::new();
+        for token in tokens {
+            if !line.is_empty() && line.len() + 1 + token.len() > PGN_LINE_WIDTH {
+                pgn.push_str(&line);
+                pgn.push('\n');
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&token);
+        }
+        pgn.push_str(&line);
+        pgn.push('\n');
+        pgn
+    }
+
+    // Reads one PGN game with coordinate or SAN moves: its tag pairs, and the glyphs, comments
+    // and variations after each move. Move numbers and comments before the first move of the
+    // game or of a variation are skipped. Every move must be legal in the position it is
+    // played from, in the variations too.
+    pub fn from_pgn(pgn: &str) -> Result<Game, PgnParseError> {
+        let mut metadata = GameMetadata::default();
+        // The main line, then each variation being read inside the one before it
+        let mut lines = vec![Line::new(Board::new())];
+        let mut chars = pgn.chars().peekable();
+        while let Some(&c) = chars.peek() {
+            let line = lines.last_mut().expect("the main line is never closed");
+            match c {
+                '{' => {
+                    chars.next();
+                    let mut comment = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => comment.push(c),
+                            // A comment never closed would swallow the rest of the game
+                            None => return Err(PgnParseError::UnrecognisedMove("{".to_string())),
+                        }
+                    }
+                    if let Some(last) = line.moves.last_mut() {
+                        let comment = comment.split_whitespace().collect::<Vec<_>>().join(" ");
+                        last.annotation = match last.annotation.take() {
+                            Some(earlier) => Some(format!("{} {}", earlier, comment)),
+                            None => Some(comment),
+                        };
+                    }
+                }
+                '[' => {
+                    let tag: String = chars.by_ref().take_while(|&c| c != '\n').collect();
+                    let (name, value) =
+                        parse_tag(&tag).ok_or(PgnParseError::MalformedTag(tag.clone()))?;
+                    // The moves of a game set up from a position are played from it
+                    if name == "FEN" {
+                        *line =
+                            Line::new(Board::from_fen(&value).map_err(PgnParseError::InvalidFen)?);
+                    }
+                    // The PGN "unknown" values read as missing tags
+                    if value != "?" && value != "????.??.??" {
+                        metadata.set_tag(name, value);
+                    }
+                }
+                // A variation replaces the last move, so it starts from the position before it
+                '(' => {
+                    chars.next();
+                    if line.moves.is_empty() {
+                        return Err(PgnParseError::UnrecognisedMove("(".to_string()));
+                    }
+                    let start = line.before_last.clone();
+                    lines.push(Line::new(start));
+                }
+                ')' => {
+                    chars.next();
+                    if lines.len() == 1 {
+                        return Err(PgnParseError::UnrecognisedMove(")".to_string()));
+                    }
+                    let variation = lines.pop().expect("a variation is open").moves;
+                    let parent = lines.last_mut().expect("the main line is never closed");
+                    if let Some(last) = parent.moves.last_mut() {
+                        if !variation.is_empty() {
+                            last.variations.push(variation);
+                        }
+                    }
+                }
+                // Rest-of-line comments
+                ';' => {
+                    chars.by_ref().take_while(|&c| c != '\n').for_each(drop);
+                }
+                c if c.is_whitespace() => {
+                    chars.next();
+                }
+                _ => {
+                    let mut token = String::new();
+                    while let Some(&c) = chars.peek() {
+                        if c.is_whitespace() || matches!(c, '{' | '(' | ')') {
+                            break;
+                        }
+                        token.push(c);
+                        chars.next();
+                    }
+                    read_token(&token, line, &mut metadata)?;
+                }
+            }
+        }
+        if lines.len() > 1 {
+            return Err(PgnParseError::UnrecognisedMove("(".to_string()));
+        }
+        let main_line = lines.pop().expect("the main line is never closed");
+        Ok(Game {
+            metadata,
+            moves: main_line.moves,
+        })
+    }
+
+    // Reads every game of a PGN file, one result for each. A game ends with its result, or
+    // where its movetext is followed by a blank line or by the next game's tags.
+    pub fn all_from_pgn(pgn: &str) -> Vec<Result<Game, PgnParseError>> {
+        split_games(pgn).into_iter().map(Game::from_pgn).collect()
+    }
+}
+
+// Adds the movetext of `moves`, played from `board`, to `tokens`, variations and all. A
+// variation's parentheses are joined to its first and last tokens.
+fn push_movetext(moves: &[GameMove], mut board: Board, tokens: &mut Vec<String>) {
+    // Black's move needs its number too when it opens the movetext or follows a comment or
+    // a variation
+    let mut number_black_move = true;
+    for game_move in moves {
+        match board.side_to_move {
+            Color::White => tokens.push(format!("{}.", board.full_move_number)),
+            Color::Black if number_black_move => {
+                tokens.push(format!("{}...", board.full_move_number))
+            }
+            Color::Black => {}
+        }
+        tokens.push(board.move_to_san(game_move.mv));
+        let before = board.clone();
+        board.apply_move(game_move.mv);
+        number_black_move = game_move.annotation.is_some() || !game_move.variations.is_empty();
+        for nag in &game_move.nags {
+            tokens.push(format!("${}", nag));
+        }
+        if let Some(annotation) = &game_move.annotation {
+            // A closing brace would end the comment early
+            tokens.push(format!("{{{}}}", annotation.replace('}', ")")));
+        }
+        for variation in &game_move.variations {
+            let start = tokens.len();
+            push_movetext(variation, before.clone(), tokens);
+            if tokens.len() > start {
+                tokens[start].insert(0, '(');
+                tokens.last_mut().unwrap().push(')');
+            }
+        }
+    }
+}
+
+// A line of play being read: its moves so far, the position after them, and the position
+// before the last of them, where a variation on that move starts
+struct Line {
+    moves: Vec<GameMove>,
+    board: Board,
+    before_last: Board,
+}
+
+impl Line {
+    fn new(board: Board) -> Self {
+        Line {
+            moves: Vec::new(),
+            before_last: board.clone(),
+            board,
+        }
+    }
+}
+
+// Splits a PGN file into its games' text. A blank line inside a comment does not end a game.
+fn split_games(pgn: &str) -> Vec<&str> {
+    let mut games = Vec::new();
+    let mut start = 0;
+    let mut offset = 0;
+    let mut in_movetext = false;
+    let mut in_comment = false;
+    let mut ended = false; // The movetext so far closed with a result
+    for line in pgn.split_inclusive('\n') {
+        let trimmed = line.trim();
+        let next_game = trimmed.is_empty() || trimmed.starts_with('[') || ended;
+        if in_movetext && !in_comment && next_game {
+            games.push(&pgn[start..offset]);
+            start = offset;
+            in_movetext = false;
+            ended = false;
+        }
+        if !in_comment && (trimmed.starts_with('[') || trimmed.starts_with(';')) {
+            // Tag pairs and comment lines are not movetext
+        } else if !trimmed.is_empty() {
+            in_movetext = true;
+            for c in trimmed.chars() {
+                match c {
+                    '{' => in_comment = true,
+                    '}' => in_comment = false,
+                    _ => {}
+                }
+            }
+            ended = !in_comment
+                && matches!(
+                    trimmed.split_whitespace().last(),
+                    Some("1-0" | "0-1" | "1/2-1/2" | "*")
+                );
+        }
+        offset += line.len();
+    }
+    games.push(&pgn[start..]);
+    games.retain(|game| !game.trim().is_empty());
+    games
+}
+
+// Reads the name and value of a tag pair, `[Name "Value"]`, whose value may escape quotes
+// and backslashes with a backslash
+fn parse_tag(line: &str) -> Option<(&str, String)> {
+    let inner = line.trim().strip_prefix('[')?.strip_suffix(']')?.trim();
+    let (name, rest) = inner.split_once(char::is_whitespace)?;
+    let mut chars = rest.trim().strip_prefix('"')?.chars();
+    let mut value = String::new();
+    loop {
+        match chars.next()? {
+            '\\' => value.push(chars.next()?),
+            '"' => break,
+            c => value.push(c),
+        }
+    }
+    chars.as_str().trim().is_empty().then_some((name, value))
+}
+
+// Reads a token of the movetext into `line`, the line being read, or for the result into
+// `metadata`
+fn read_token(
+    token: &str,
+    line: &mut Line,
+    metadata: &mut GameMetadata,
+) -> Result<(), PgnParseError> {
+    if matches!(token, "1-0" | "0-1" | "1/2-1/2" | "*") {
+        // The result closing the movetext stands in for a missing Result tag
+        if metadata.result.is_empty() {
+            metadata.result = token.to_string();
+        }
+        return Ok(());
+    }
+    if let Some(number) = token.strip_prefix('$') {
+        // A glyph before the first move has nothing to annotate
+        if let (Ok(number), Some(last)) = (number.parse(), line.moves.last_mut()) {
+            last.nags.push(number);
+        }
+        return Ok(());
+    }
+    // A move number, possibly run together with the move as in "1.e2e4": digits followed by
+    // dots, so that castling written with zeros ("0-0") keeps its own
+    let unnumbered = token.trim_start_matches(|c: char| c.is_ascii_digit());
+    let token = if unnumbered.is_empty() || unnumbered.starts_with('.') {
+        unnumbered.trim_start_matches('.')
+    } else {
+        token
+    };
+    if token.is_empty() {
+        return Ok(());
+    }
+    // A glyph may be written as a suffix, "e2e4!?"
+    let split = token.find(['!', '?']).unwrap_or(token.len());
+    let (text, suffix) = token.split_at(split);
+    let board = &mut line.board;
+    // A move that cannot be read is named with its number, "12... Nxe5"
+    let numbered = format!(
+        "{}{} {}",
+        board.full_move_number,
+        match board.side_to_move {
+            Color::White => ".",
+            Color::Black => "...",
+        },
+        token
+    );
+    let mv = board.parse_move(text).map_err(|error| match error {
+        ChessError::InvalidNotation(_) => PgnParseError::UnrecognisedMove(numbered),
+        ChessError::IllegalMove(_) => PgnParseError::IllegalMove(numbered),
+        ChessError::AmbiguousMove(_) => PgnParseError::AmbiguousMove(numbered),
+    })?;
+    line.before_last = board.clone();
+    board.apply_move(mv);
+    let mut game_move = GameMove::new(mv);
+    game_move.set_nag(Nag::from_symbol(suffix));
+    line.moves.push(game_move);
+    Ok(())
+}
@@ -0,0 +1,18 @@
+// lib.rs
+// The engine as a library, shared by the desktop and browser builds of the GUI
+pub mod cli;
+pub mod clock;
+pub mod engine;
+// Times its searches with a thread, which browsers do not have
+#[cfg(not(target_arch = "wasm32"))]
+pub mod epd;
+pub mod game;
+pub mod record;
+// Searches on a thread of its own, which browsers do not have
+#[cfg(not(target_arch = "wasm32"))]
+pub mod uci;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+// Needs a thread to time its moves, which browsers do not have
+#[cfg(not(target_arch = "wasm32"))]
+pub mod xboard;
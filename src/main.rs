@@ -1,11 +1,35 @@
+use chess::cli::{self, CliOptions};
+use chess::engine::book::Book;
+use chess::engine::uci_client::{UciClientError, UciEngine};
+use chess::engine::{
+    classify_endgame, compute_material_balance, compute_piece_count, difficulty_level_name,
+    estimate_elo, evaluate_move, improved_best_move_for_color, move_to_uci, opposite_color,
+    reply_after_search, search_with_info, Board, Color, EndgameType, GameStatus, LegalMoves, Move,
+    MoveError, PieceType, Reply, SearchLimits, SearchResult, DEFAULT_HASH_MB, MATE_VALUE,
+    MAX_DIFFICULTY, PROMOTION_PIECES,
+};
+use chess::game::{Game, GameMetadata, Nag};
+use chess::record::{Outcome, PlayerRecord};
+use iced::futures::SinkExt;
+use iced::keyboard;
+use iced::task;
+use iced::widget::canvas::{self, Canvas, Frame, Geometry, Path, Stroke};
 use iced::widget::Image;
 use iced::{
     border::Radius,
-    widget::{button, image, slider, Button, Column, Container, Row, Text},
-    Background, Border, Color as IcedColor, Element, Length, Shadow, Task, Theme,
+    font, mouse,
+    widget::{
+        button, checkbox, container, image, pick_list, radio, slider, text_input, Button, Column,
+        Container, Row, Stack, Text,
+    },
+    Background, Border, Color as IcedColor, Element, Font, Length, Point, Rectangle, Renderer,
+    Shadow, Subscription, Task, Theme,
 };
-mod engine;
-use engine::{improved_best_move_for_color, opposite_color, Board, Color, PieceType};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+// The standard clock panics in the browser, iced's works everywhere
+use iced::time::{Duration, Instant};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 enum GameResult {
@@ -13,11 +37,29 @@ enum GameResult {
     Draw,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 enum AppState {
     SelectingDifficulty,
     Playing,
     GameOver(GameResult),
+    // Stepping through a finished game. `boards` holds the start position followed by the
+    // position after each of `moves`; `index` is the board on screen. `evals` (White's point of
+    // view) and `best_alternatives` (the engine's move in each position) fill up in board order
+    // as the background review gets through them.
+    GameReview {
+        metadata: GameMetadata,
+        index: usize,
+        boards: Vec<Board>,
+        moves: Vec<Move>,
+        evals: Vec<i32>,
+        best_alternatives: Vec<Option<Move>>,
+    },
+    // Annotating a game move by move. `annotation_index` is the move being annotated, whose
+    // position is on screen.
+    Studying {
+        game: Game,
+        annotation_index: usize,
+    },
 }
 
 #[derive(Debug, Clone)]
@@ -25,9 +67,84 @@ enum Message {
     SquareClicked(usize, usize),
     BotMove,
     DifficultySelected,
+    PresetSelected(Preset),
+    // The side the human plays, the bot taking the other
+    HumanColorSelected(Color),
     SliderChanged(f32),
+    RandomOpeningChanged(f32),
+    ToggleEngineSuggestion(bool),
+    ToggleBlindfold(bool),
+    TogglePreMoves(bool),
+    // Whether the bot opens from its opening book
+    ToggleBook(bool),
+    ThreadsChanged(f32),
+    // The transposition table size, as a power of two megabytes
+    HashSizeChanged(f32),
+    // The bot's contempt for draws, in centipawns
+    ContemptChanged(i32),
+    // Whether an external UCI engine plays for the bot, the path to its executable, and how long it
+    // thinks per move, in seconds
+    ToggleExternalEngine(bool),
+    ExternalEnginePathChanged(String),
+    ExternalMoveTimeChanged(f32),
+    // The external engine could not move, for this reason; the built-in engine moves instead
+    ExternalEngineFailed(String),
+    // Switches blindfold play between hiding the pieces entirely and marking their squares
+    ToggleHalfBlind,
+    // (the human's move, the engine's preferred move, centipawns lost by the human's move)
+    EngineSuggestion(Move, Move, i32),
+    // Progress of the bot's search: the best result of each completed depth
+    BotThinking(SearchResult),
+    // The bot's finished search, None when it was stopped before finding a move or has none
+    BotSearchDone(Option<SearchResult>),
+    CancelBotSearch,
+    // The human claims a draw by threefold repetition or the fifty-move rule
+    ClaimDraw,
     EndGame(GameResult),
     Restart,
+    // Plays the bot again from the initial position with the sides swapped
+    RematchwithSwap,
+    // Starts the game again from the position it began in, keeping the settings and sides,
+    // to retry a set-up position
+    ResetToStartPosition,
+    FlipBoard,
+    ReviewGame,
+    // The review of the next board: its score and the engine's best move, None if the game
+    // is over in that position
+    ReviewEvaluated(i32, Option<Move>),
+    ReviewGoTo(usize),
+    StudyGame,
+    StudyGoTo(usize),
+    // The comment on a move of the study; empty text removes it
+    AddAnnotation(usize, String),
+    // Marks a move of the study with a glyph, or clears the glyph if it already has it
+    SetNag(usize, Nag),
+    CopyPgn,
+    CopyFen,
+    // Reads the clipboard as a FEN position to play from, or else as a PGN game to study
+    PasteFromClipboard,
+    ClipboardPasted(Option<String>),
+    // Sets up one of the 960 Chess960 starting positions, picked at random
+    RandomPosition,
+    // A key press no widget has taken, checked against the keyboard shortcuts
+    KeyPressed(keyboard::Key, keyboard::Modifiers),
+    // Takes back the human's last move, with the bot's reply to it
+    Undo,
+    SaveGame,
+    LoadGame,
+    RequestHint,
+    // The engine's move for the human in the position with the given Zobrist key
+    HintFound(u64, Move),
+    // Asks before restarting
+    RequestRestart,
+    // Closes the open overlay, or else drops the selection and any pre-move
+    Escape,
+    ToggleShortcuts,
+    // The piece chosen for the human's promotion waiting on the promotion card
+    Promote(PieceType),
+    Resign,
+    // The player's record has taken in the game just ended, and is saved
+    GameRecordUpdated,
 }
 
 // #[derive(Debug)]
@@ -36,25 +153,177 @@ struct ChessApp {
     // The currently selected square by the human player, if any.
     selected: Option<(usize, usize)>,
     selected_moves: Option<Vec<(usize, usize)>>,
-    // Whose turn it is. We assume the human plays White.
+    // Why the human's last attempted move was refused, shown until their next click
+    move_error: Option<MoveError>,
+    // The legal moves of the side to move, read through `legal_moves` so they are generated
+    // once per position
+    legal_moves: LegalMoves,
+    // Whose turn it is
     current_turn: Color,
-    // Difficulty (minimax depth) for the bot.
+    human_color: Color, // The side the human plays, White unless chosen otherwise
+    previous_human_color: Color, // The side the human played in the last game to end
+    // Difficulty (minimax depth) for the bot. 0 means random moves.
     difficulty: u32,
+    preset: Preset, // The chosen bundle of bot settings, or Advanced for the raw sliders
     slider_value: f32,
-    state: AppState,                // Add a state tracker
+    // Number of opening moves the bot plays randomly before using the engine.
+    random_opening_moves: u32,
+    bot_moves_played: u32,
+    random_margin: i32, // Centipawns of slack the bot allows itself between root moves
+    skill_level: Option<u32>, // See `SearchLimits::skill_level`
+    state: AppState,    // Add a state tracker
     captured_white: Vec<PieceType>, // Captured white pieces
     captured_black: Vec<PieceType>, // Captured black pieces
-    last_move: Option<String>,      // The last move made
+    last_move: Option<String>, // The last move made
+    // The bot's last search, including its expected line, and the position it searched
+    last_search: Option<(Board, SearchResult)>,
+    position_history: Vec<u64>, // Zobrist keys of every position reached, for repetitions
+    start_position: Board,      // Where the game began, the initial position unless pasted
+    // Whether to show what the engine would have played after each human move.
+    show_engine_suggestion: bool,
+    last_human_move: Option<Move>,
+    engine_suggestion: Option<Move>,
+    suggestion_loss: Option<i32>, // Centipawns lost compared to the engine's move
+    search_seed: u64,             // Drawn per game so the bot varies between equally good moves
+    flipped: bool,                // Show the board from Black's side
+    move_history: Vec<Move>,      // Every move played, White's first
+    move_times: Vec<Duration>,    // Time spent on each move in `move_history`
+    move_start_time: Instant,     // When the side to move started thinking
+    bot_search: Option<BackgroundSearch>, // The search currently running for the bot, if any
+    bot_progress: Option<SearchResult>, // Deepest completed iteration of that search
+    bot_cancelled: bool,          // The human stopped the bot's last search before it moved
+    review_search: Option<BackgroundSearch>, // The game review's searches, while they run
+    pgn_error: Option<String>,    // Why the last paste or loaded PGN could not be read
+    blindfold_mode: bool,         // Hide the pieces while the game is played
+    half_blind: bool,             // In blindfold play, mark occupied squares with "?"
+    premoves_enabled: bool,       // Let the human queue a move while the bot thinks
+    book: bool,                   // The bot plays its book moves, see `SearchLimits::book`
+    book_file: Option<Arc<Book>>, // The book read from BOOK_FILE, played from when `book` is set
+    search_threads: usize,        // Threads the bot searches with, see `SearchLimits::threads`
+    hash_mb: usize,               // The bot's transposition table size, see `SearchLimits::hash_mb`
+    contempt: i32, // What the bot gives up to avoid a draw, see `SearchLimits::contempt`
+    external_engine: bool, // An external UCI engine moves for the bot
+    external_engine_path: String, // Its executable
+    external_move_time: Duration, // How long it thinks per move
+    // The external engine once started, kept running from one move to the next
+    uci_engine: Arc<Mutex<Option<UciEngine>>>,
+    external_engine_error: Option<String>, // Why it last failed to move, until the next try
+    // The human's queued move, (from_row, from_col, to_row, to_col), tried once the bot moves
+    pre_move: Option<(usize, usize, usize, usize)>,
+    hint: Option<Move>,      // The engine's move for the human, drawn as an arrow
+    shortcuts_visible: bool, // The keyboard shortcut overlay is open
+    confirm_restart: bool,   // The overlay asking whether to restart is open
+    // The human's promoting move, (from, to), while the card asks which piece it makes
+    promotion_pending: Option<((usize, usize), (usize, usize))>,
+    record: PlayerRecord, // The player's results against the bot, kept in RECORD_FILE
+    new_best: bool,       // The game just won beat a higher difficulty than any before
+    resigned: bool,       // The human resigned the game that has just ended
+    notice: Option<String>, // How the last save or load went
+}
+
+// Handles for stopping a search running in the background
+struct BackgroundSearch {
+    stop: Arc<AtomicBool>, // Tells the engine to stop searching
+    task: task::Handle,    // Stops delivering the search's messages
+}
+
+// What the bot's search needs to ask the external engine for its move instead
+struct ExternalMove {
+    engine: Arc<Mutex<Option<UciEngine>>>,
+    path: PathBuf,
+    move_time: Duration,
+    start_position: Board,
+    moves: Vec<Move>, // Every move played from `start_position`
+}
+
+impl ExternalMove {
+    // Asks the engine for its move, first starting it if none is running or another one is
+    // set. An engine that fails is dropped, to be started afresh for the next move, unless it
+    // only cannot play Chess960.
+    fn play(&self, stop: &AtomicBool) -> Result<SearchResult, UciClientError> {
+        let mut engine = self
+            .engine
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if engine
+            .as_ref()
+            .is_none_or(|engine| engine.path() != self.path)
+        {
+            *engine = None;
+            *engine = Some(UciEngine::spawn(&self.path)?);
+        }
+        let result = match engine.as_mut() {
+            Some(engine) => {
+                engine.best_move(&self.start_position, &self.moves, self.move_time, stop)
+            }
+            None => Err(UciClientError::Exited),
+        };
+        if result
+            .as_ref()
+            .is_err_and(|error| !matches!(error, UciClientError::NoChess960))
+        {
+            *engine = None;
+        }
+        result
+    }
 }
 
+// Width of the rank labels and height of the file labels around the board
+const COORDINATE_SIZE: f32 = 20.0;
+
+// Where Ctrl+S saves the game and Ctrl+O loads it from, as PGN
+const SAVE_FILE: &str = "saved_game.pgn";
+
+// Where the player's record is kept between runs, as JSON
+#[cfg(not(target_arch = "wasm32"))]
+const RECORD_FILE: &str = "player_record.json";
+
+// A Polyglot opening book the bot opens from when there is one, see `SearchLimits::book_file`
+#[cfg(not(target_arch = "wasm32"))]
+const BOOK_FILE: &str = "book.bin";
+
+// Where the `nnue` build reads the evaluation network from, if it is there
+#[cfg(feature = "nnue")]
+const NNUE_FILE: &str = "network.nnue";
+
+// The largest transposition table the settings offer, in megabytes
+const MAX_HASH_MB: usize = 256;
+
+// The bot's contempt until the slider moves it, and the slider's reach either way, in
+// centipawns; see `SearchLimits::contempt`
+const DEFAULT_CONTEMPT: i32 = 25;
+const MAX_CONTEMPT: i32 = 100;
+
+// How long an external engine thinks per move until the slider moves it, and the slider's
+// reach, in seconds
+const DEFAULT_EXTERNAL_MOVE_TIME: Duration = Duration::from_secs(1);
+const MAX_EXTERNAL_MOVE_SECONDS: f32 = 10.0;
+
+// Search depth used to evaluate each position of a game review
+const REVIEW_DEPTH: u32 = 3;
+// Centipawns a move must lose to be called a blunder in the review
+const BLUNDER_THRESHOLD: i32 = 100;
+
 impl ChessApp {
-    fn board_view(&self) -> Column<'_, Message> {
+    fn board_view(&self) -> Element<'_, Message> {
+        self.render_board(&self.board, None, self.hint.or(self.engine_suggestion))
+    }
+
+    // Draws `board` in the current orientation with `last_move` highlighted and `arrow`
+    // drawn over the squares
+    fn render_board(
+        &self,
+        board: &Board,
+        last_move: Option<Move>,
+        arrow: Option<Move>,
+    ) -> Element<'_, Message> {
         let mut board_view = Column::new().spacing(0);
+        // Blindfold play hides the pieces only while the game goes on
+        let blindfold = self.blindfold_mode && matches!(self.state, AppState::Playing);
 
-        for r in (0..8).rev() {
-            // Iterate rows from 7 to 0 to make white pieces on the bottom
+        for r in self.display_rows() {
             let mut row_view = Row::new().spacing(0);
-            for c in 0..8 {
+            for c in self.display_cols() {
                 let is_light = (r + c) % 2 == 0;
                 let square_color = if is_light { "#F0D9B5" } else { "#B58863" };
 
@@ -72,28 +341,32 @@ impl ChessApp {
                     } else {
                         square_color // Default square color
                     }
+                } else if self
+                    .pre_move
+                    .is_some_and(|(from_row, from_col, to_row, to_col)| {
+                        (from_row, from_col) == (r, c) || (to_row, to_col) == (r, c)
+                    })
+                {
+                    "#B19CD9" // Purple for the squares of a queued pre-move
+                } else if last_move.is_some_and(|m| m.from == (r, c) || m.to == (r, c)) {
+                    "#F6F669" // Yellow for the squares of the highlighted move
                 } else {
                     square_color // Default square color if nothing is selected
                 };
 
-                let square_content: Element<'static, Message> = self.board.squares[r][c]
-                    .and_then(|piece| {
-                        let asset: &str = match (piece.color, piece.kind) {
-                            (Color::White, PieceType::Pawn) => "assets/white_pawn.jpeg",
-                            (Color::Black, PieceType::Pawn) => "assets/black_pawn.png",
-                            (Color::White, PieceType::King) => "assets/white_king.jpeg",
-                            (Color::Black, PieceType::King) => "assets/black_king.png",
-                            (Color::White, PieceType::Queen) => "assets/white_queen.jpeg",
-                            (Color::Black, PieceType::Queen) => "assets/black_queen.jpeg",
-                            (Color::White, PieceType::Rook) => "assets/white_rook.png",
-                            (Color::Black, PieceType::Rook) => "assets/black_rook.png",
-                            (Color::White, PieceType::Knight) => "assets/white_knight.jpeg",
-                            (Color::Black, PieceType::Knight) => "assets/black_knight.jpeg",
-                            (Color::White, PieceType::Bishop) => "assets/white_bishop.jpeg",
-                            (Color::Black, PieceType::Bishop) => "assets/black_bishop.png",
-                        };
-                        let handle = image::Handle::from_path(asset); // Create the handle
-                        Some(Image::new(handle).into())
+                let square_content: Element<'static, Message> = board
+                    .piece_at(r, c)
+                    .filter(|_| !blindfold || self.half_blind)
+                    .map(|piece| {
+                        if blindfold {
+                            Container::new(Text::new("?").size(32))
+                                .center_x(Length::Fill)
+                                .center_y(Length::Fill)
+                                .into()
+                        } else {
+                            let handle = piece_image(piece.color, piece.kind);
+                            Image::new(handle).into()
+                        }
                     })
                     .unwrap_or_else(|| {
                         // Handle the None case directly
@@ -120,22 +393,538 @@ impl ChessApp {
             }
             board_view = board_view.push(row_view); // Reassign board_view
         }
-        board_view
+
+        // Overlay the arrow, such as the engine's suggested move, on top of the squares
+        let squares: Element<'_, Message> = match arrow {
+            Some(mv) => Stack::new()
+                .push(board_view)
+                .push(
+                    Canvas::new(MoveArrow {
+                        mv,
+                        color: IcedColor::from_rgba8(255, 140, 0, 0.6),
+                        flipped: self.flipped,
+                    })
+                    .width(Length::Fill)
+                    .height(Length::Fill),
+                )
+                .into(),
+            None => board_view.into(),
+        };
+
+        // Rank numbers down the left and file letters along the bottom, following the orientation
+        let label = |text: String| {
+            Container::new(Text::new(text).size(14))
+                .center_x(Length::Fill)
+                .center_y(Length::Fill)
+        };
+        let ranks = self.display_rows().fold(Column::new(), |column, r| {
+            column.push(label((r + 1).to_string()).height(Length::FillPortion(1)))
+        });
+        let files = self.display_cols().fold(Row::new(), |row, c| {
+            row.push(label(((b'a' + c as u8) as char).to_string()).width(Length::FillPortion(1)))
+        });
+        Row::new()
+            .push(
+                Column::new()
+                    .push(ranks.height(Length::Fill))
+                    .push(Container::new(Text::new("")).height(Length::Fixed(COORDINATE_SIZE)))
+                    .width(Length::Fixed(COORDINATE_SIZE)),
+            )
+            .push(
+                Column::new()
+                    .push(Container::new(squares).height(Length::Fill))
+                    .push(files.height(Length::Fixed(COORDINATE_SIZE))),
+            )
+            .into()
+    }
+
+    // Board rows from the top of the screen down: White sits at the bottom unless flipped
+    fn display_rows(&self) -> impl Iterator<Item = usize> {
+        let flipped = self.flipped;
+        (0..8).map(move |i| if flipped { i } else { 7 - i })
+    }
+
+    // Board columns from left to right
+    fn display_cols(&self) -> impl Iterator<Item = usize> {
+        let flipped = self.flipped;
+        (0..8).map(move |i| if flipped { 7 - i } else { i })
+    }
+
+    // Appends a move to the history with the time taken to find it, starting the opponent's clock
+    fn record_move(&mut self, m: Move) {
+        self.move_history.push(m);
+        self.move_times.push(self.move_start_time.elapsed());
+        self.move_start_time = Instant::now();
+    }
+
+    // The moves played so far, numbered in pairs, with the time spent on each and each side's
+    // average; the longest think of the game is shown in bold
+    fn move_history_view(&self) -> Column<'_, Message> {
+        let longest = self
+            .move_times
+            .iter()
+            .enumerate()
+            .max_by_key(|&(_, time)| *time)
+            .map(|(index, _)| index);
+        let mut board = self.start_position.clone();
+        let sans: Vec<String> = self
+            .move_history
+            .iter()
+            .map(|&m| {
+                let san = board.move_to_san(m);
+                board.apply_move(m);
+                san
+            })
+            .collect();
+        let entry = |index: usize| {
+            let text = Text::new(format!(
+                "{}  ({:.1}s)",
+                sans[index],
+                self.move_times[index].as_secs_f32()
+            ))
+            .width(Length::Fixed(140.0));
+            if Some(index) == longest {
+                text.font(Font {
+                    weight: font::Weight::Bold,
+                    ..Font::DEFAULT
+                })
+            } else {
+                text
+            }
+        };
+
+        let mut history = Column::new().push(Text::new("Move History"));
+        for index in (0..self.move_history.len()).step_by(2) {
+            let row = Row::new()
+                .spacing(10)
+                .push(Text::new(format!("{}.", index / 2 + 1)).width(Length::Fixed(30.0)))
+                .push(entry(index));
+            history = history.push(if index + 1 < self.move_history.len() {
+                row.push(entry(index + 1))
+            } else {
+                row
+            });
+        }
+
+        // White's moves sit at even indices, Black's at odd ones
+        let average = |parity: usize| {
+            let times: Vec<Duration> = self
+                .move_times
+                .iter()
+                .skip(parity)
+                .step_by(2)
+                .copied()
+                .collect();
+            if times.is_empty() {
+                "-".to_string()
+            } else {
+                format!(
+                    "{:.1}s",
+                    (times.iter().sum::<Duration>() / times.len() as u32).as_secs_f32()
+                )
+            }
+        };
+        history
+            .push(Text::new(format!(
+                "Average per move: White {}, Black {}",
+                average(0),
+                average(1)
+            )))
+            .push(Text::new(format!(
+                "Half-moves played: {}",
+                self.board.half_moves_played()
+            )))
+    }
+
+    fn search_limits(&self, depth: u32) -> SearchLimits {
+        SearchLimits {
+            depth,
+            seed: self.search_seed,
+            // The root moves are already spread over the search threads, so helpers add only
+            // half as many again
+            helper_threads: self.search_threads / 2,
+            threads: self.search_threads,
+            hash_mb: self.hash_mb,
+            stop: None,
+            contempt: 0,
+            random_margin: 0,
+            skill_level: None,
+            book: false,
+            book_file: None,
+            max_extensions: None,
+        }
+    }
+
+    // The game played so far, with its players and result as PGN tags
+    fn finished_game(&self) -> Game {
+        let mut game = Game::from_moves(&self.move_history);
+        let bot = match self.preset {
+            Preset::Advanced => format!("Bot ({})", difficulty_label(self.difficulty)),
+            preset => format!("Bot ({})", preset),
+        };
+        let (white, black) = match self.human_color {
+            Color::White => ("Player".to_string(), bot),
+            Color::Black => (bot, "Player".to_string()),
+        };
+        game.metadata = GameMetadata {
+            event: "Rust Chess game".to_string(),
+            white,
+            black,
+            result: match self.state {
+                AppState::GameOver(GameResult::Winner(Color::White)) => "1-0",
+                AppState::GameOver(GameResult::Winner(Color::Black)) => "0-1",
+                AppState::GameOver(GameResult::Draw) => "1/2-1/2",
+                _ => "*",
+            }
+            .to_string(),
+            ..GameMetadata::default()
+        };
+        if let Some(id) = self.start_position.chess960_id {
+            game.metadata.set_tag("Variant", "Chess960".to_string());
+            game.metadata.set_tag("Chess960", id.to_string());
+        }
+        if self.start_position.to_fen() != Board::new().to_fen() {
+            game.metadata.set_tag("FEN", self.start_position.to_fen());
+        }
+        game
+    }
+
+    // Plays the human's move, which must be legal, and hands over to the bot unless it ends
+    // the game
+    fn play_human_move(&mut self, played: Move) -> Task<Message> {
+        self.last_move = Some(format!(
+            "{:?} played {}",
+            self.human_color,
+            self.board.move_to_san(played)
+        ));
+        self.record_capture(played);
+        self.last_human_move = Some(played);
+        self.hint = None;
+        // Ask the engine what it would have played from the position before the move
+        let suggestion_task = if self.show_engine_suggestion {
+            let board_before = self.board.clone();
+            let history = self.position_history.clone();
+            let depth = self.difficulty.max(1);
+            let limits = self.search_limits(depth);
+            let color = self.human_color;
+            Task::future(async move {
+                improved_best_move_for_color(&board_before, color, &limits, &history).map(|best| {
+                    let loss = if best.best_move == played {
+                        0
+                    } else {
+                        let played_score =
+                            evaluate_move(&board_before, color, played, depth, &history);
+                        // Scores are White's, so Black loses what they rise by
+                        match color {
+                            Color::White => best.score.saturating_sub(played_score),
+                            Color::Black => played_score.saturating_sub(best.score),
+                        }
+                    };
+                    (best.best_move, loss.max(0))
+                })
+            })
+            .and_then(move |(suggestion, loss)| {
+                Task::done(Message::EngineSuggestion(played, suggestion, loss))
+            })
+        } else {
+            Task::none()
+        };
+        self.board.apply_move(played);
+        self.position_history.push(self.board.zobrist_key());
+        self.record_move(played);
+        self.selected = None;
+        self.current_turn = opposite_color(self.current_turn);
+        if let Some(result) = self.game_result() {
+            return Task::batch([
+                suggestion_task,
+                Task::perform(async {}, move |_| Message::EndGame(result)),
+            ]);
+        }
+        // After the human move, trigger the bot move asynchronously.
+        Task::batch([
+            suggestion_task,
+            Task::perform(async {}, |_| Message::BotMove),
+        ])
+    }
+
+    // Adds the piece `m` takes, if any, to the captured pieces of its side
+    fn record_capture(&mut self, m: Move) {
+        // A Chess960 King castles onto its own Rook, which is no capture
+        if let Some(piece) = self
+            .board
+            .piece_at(m.to.0, m.to.1)
+            .filter(|piece| piece.color != self.board.side_to_move)
+        {
+            match piece.color {
+                Color::White => self.captured_white.push(piece.kind),
+                Color::Black => self.captured_black.push(piece.kind),
+            }
+        }
+    }
+
+    // Takes back the human's last move, and the bot's reply if it has made one
+    fn take_back(&mut self) {
+        let taken = if self.current_turn == self.human_color {
+            2
+        } else {
+            1
+        };
+        if self.move_history.len() < taken {
+            return;
+        }
+        self.bot_moves_played = self.bot_moves_played.saturating_sub(taken as u32 - 1);
+        self.rewind(self.move_history.len() - taken);
+        self.last_move = Some("Move taken back".to_string());
+    }
+
+    // Goes back to the game's start position, keeping the settings and sides, and plays on
+    // from there; the bot moves first when it is its turn. A finished game starts again too,
+    // so a set-up position can be tried as often as needed.
+    fn reset_to_start(&mut self) -> Task<Message> {
+        self.rewind(0);
+        self.bot_moves_played = 0;
+        self.search_seed = rand::random();
+        self.resigned = false;
+        self.new_best = false;
+        self.promotion_pending = None;
+        self.move_error = None;
+        self.external_engine_error = None;
+        self.last_move = Some("Back to the start position".to_string());
+        self.state = AppState::Playing;
+        if self.current_turn == self.human_color {
+            Task::none()
+        } else {
+            Task::done(Message::BotMove)
+        }
+    }
+
+    // Keeps only the first `moves` moves of the game, replaying them from the start to
+    // rebuild the position and the captured pieces
+    fn rewind(&mut self, moves: usize) {
+        self.cancel_bot_search();
+        self.move_history.truncate(moves);
+        self.move_times.truncate(moves);
+        self.captured_white.clear();
+        self.captured_black.clear();
+        self.start_from(self.start_position.clone());
+        for m in self.move_history.clone() {
+            self.record_capture(m);
+            self.board.apply_move(m);
+            self.position_history.push(self.board.zobrist_key());
+        }
+        self.current_turn = self.board.side_to_move;
+        self.last_search = None;
+        self.last_human_move = None;
+        self.engine_suggestion = None;
+        self.suggestion_loss = None;
+        self.hint = None;
+        self.selected = None;
+        self.selected_moves = None;
+        self.pre_move = None;
+        self.bot_cancelled = false;
+        self.move_start_time = Instant::now();
+    }
+
+    // Starts the game from a FEN position instead of the initial one, if it could arise in a
+    // game; the bot moves first when the side to move is its own
+    fn set_up_position(&mut self, fen: &str) -> Result<(), String> {
+        let board = Board::from_fen(fen)?;
+        board.validate()?;
+        self.start_from(board);
+        Ok(())
+    }
+
+    // Starts the game from `board`, a position that could arise in a game
+    fn start_from(&mut self, board: Board) {
+        self.position_history = vec![board.zobrist_key()];
+        self.current_turn = board.side_to_move;
+        self.start_position = board.clone();
+        self.board = board;
+    }
+
+    // Opens the first game of a PGN text in the study, or says why it cannot be read
+    fn study_pgn(&mut self, pgn: Result<String, String>) {
+        let game = pgn.and_then(|text| match Game::all_from_pgn(&text).into_iter().next() {
+            Some(game) => game.map_err(|error| error.to_string()),
+            None => Err("no game found".to_string()),
+        });
+        match game {
+            Ok(game) => {
+                self.cancel_review_search();
+                self.pgn_error = None;
+                self.selected = None;
+                self.selected_moves = None;
+                self.state = AppState::Studying {
+                    annotation_index: game.moves.len().saturating_sub(1),
+                    game,
+                };
+            }
+            Err(error) => self.pgn_error = Some(format!("Could not read the PGN: {}", error)),
+        }
+    }
+
+    // The message a key press stands for in the current state, if any; see `shortcuts_help`
+    fn shortcut(&self, key: keyboard::Key, modifiers: keyboard::Modifiers) -> Option<Message> {
+        use keyboard::key::Named;
+        let playing = matches!(self.state, AppState::Playing);
+        let human_to_move =
+            playing && self.current_turn == self.human_color && self.bot_search.is_none();
+        match key.as_ref() {
+            keyboard::Key::Character(c) => match (
+                c.to_lowercase().as_str(),
+                modifiers.command(),
+                modifiers.shift(),
+            ) {
+                ("z", true, false) if playing => Some(Message::Undo),
+                ("s", true, false) => Some(Message::SaveGame),
+                ("o", true, false) if !playing => Some(Message::LoadGame),
+                ("v", true, false) if !playing => Some(Message::PasteFromClipboard),
+                ("c", true, true) => Some(Message::CopyPgn),
+                ("c", true, false) if playing || matches!(self.state, AppState::GameOver(_)) => {
+                    Some(Message::CopyFen)
+                }
+                (_, true, _) => None,
+                ("f", ..) if !matches!(self.state, AppState::SelectingDifficulty) => {
+                    Some(Message::FlipBoard)
+                }
+                ("h", ..) if human_to_move => Some(Message::RequestHint),
+                ("r", ..) if !matches!(self.state, AppState::SelectingDifficulty) => {
+                    Some(Message::RequestRestart)
+                }
+                ("b", ..) if playing => Some(Message::ToggleHalfBlind),
+                ("?", ..) => Some(Message::ToggleShortcuts),
+                _ => None,
+            },
+            keyboard::Key::Named(Named::Escape) => Some(Message::Escape),
+            keyboard::Key::Named(Named::ArrowLeft) => self.step_message(-1),
+            keyboard::Key::Named(Named::ArrowRight) => self.step_message(1),
+            _ => None,
+        }
+    }
+
+    // Moves one step back or forward through a reviewed or studied game
+    fn step_message(&self, step: isize) -> Option<Message> {
+        match &self.state {
+            AppState::GameReview { index, .. } => {
+                Some(Message::ReviewGoTo(index.saturating_add_signed(step)))
+            }
+            AppState::Studying {
+                annotation_index, ..
+            } => Some(Message::StudyGoTo(
+                annotation_index.saturating_add_signed(step),
+            )),
+            _ => None,
+        }
+    }
+
+    // The legal moves of the side to move, generated again only once the position has changed
+    fn legal_moves(&mut self) -> &LegalMoves {
+        if !self.legal_moves.is_for(&self.board, self.current_turn) {
+            self.legal_moves = LegalMoves::new(&self.board, self.current_turn);
+        }
+        &self.legal_moves
+    }
+
+    // How the game has ended in the current position, if it has
+    fn game_result(&mut self) -> Option<GameResult> {
+        self.legal_moves();
+        if self.legal_moves.status() == GameStatus::Checkmate {
+            Some(GameResult::Winner(opposite_color(self.current_turn)))
+        } else if self.legal_moves.is_draw(&self.board) {
+            Some(GameResult::Draw)
+        } else {
+            None
+        }
+    }
+
+    // Whether the human, on their turn, may claim a draw in the current position
+    fn can_claim_draw(&self) -> bool {
+        matches!(self.state, AppState::Playing)
+            && self.current_turn == self.human_color
+            && self.board.can_claim_draw(&self.position_history)
+    }
+
+    // Stops the bot's search, if one is running, and drops whatever it reports from now on
+    fn cancel_bot_search(&mut self) {
+        if let Some(search) = self.bot_search.take() {
+            search.stop.store(true, Ordering::Relaxed);
+            search.task.abort();
+        }
+        self.bot_progress = None;
+    }
+
+    // Stops the game review's searches, if they are still running
+    fn cancel_review_search(&mut self) {
+        if let Some(search) = self.review_search.take() {
+            search.stop.store(true, Ordering::Relaxed);
+            search.task.abort();
+        }
     }
 }
+
 impl Default for ChessApp {
     fn default() -> Self {
+        let board = Board::new();
         ChessApp {
-            board: Board::new(),
+            position_history: vec![board.zobrist_key()],
+            start_position: board.clone(),
+            legal_moves: LegalMoves::new(&board, Color::White),
+            board,
             selected: None,
             selected_moves: None,
+            move_error: None,
             current_turn: Color::White,
+            human_color: Color::White,
+            previous_human_color: Color::White,
             difficulty: 3, // Adjust for desired bot strength.
+            preset: Preset::Casual,
             slider_value: 3.0,
+            random_opening_moves: 0,
+            bot_moves_played: 0,
+            random_margin: 0,
+            skill_level: None,
             state: AppState::SelectingDifficulty, // Start with difficulty selection
             captured_white: Vec::new(),           // Captured white pieces
             captured_black: Vec::new(),           // Captured black pieces
             last_move: None,                      // The last move made
+            last_search: None,
+            show_engine_suggestion: false,
+            last_human_move: None,
+            engine_suggestion: None,
+            suggestion_loss: None,
+            search_seed: rand::random(),
+            flipped: false,
+            move_history: Vec::new(),
+            move_times: Vec::new(),
+            move_start_time: Instant::now(),
+            bot_search: None,
+            bot_progress: None,
+            bot_cancelled: false,
+            review_search: None,
+            pgn_error: None,
+            blindfold_mode: false,
+            half_blind: false,
+            premoves_enabled: true,
+            book: true,
+            book_file: load_book(),
+            // One core is left for the board to stay responsive while the bot thinks
+            search_threads: (cpu_count() - 1).max(1),
+            hash_mb: DEFAULT_HASH_MB,
+            contempt: DEFAULT_CONTEMPT,
+            external_engine: false,
+            external_engine_path: String::new(),
+            external_move_time: DEFAULT_EXTERNAL_MOVE_TIME,
+            uci_engine: Arc::new(Mutex::new(None)),
+            external_engine_error: None,
+            pre_move: None,
+            hint: None,
+            shortcuts_visible: false,
+            promotion_pending: None,
+            record: load_record(),
+            new_best: false,
+            resigned: false,
+            confirm_restart: false,
+            notice: None,
         }
     }
 }
@@ -145,135 +934,580 @@ impl Default for ChessApp {
 /// and returns a Command that can trigger asynchronous actions.
 fn update(app: &mut ChessApp, message: Message) -> Task<Message> {
     match message {
+        Message::PresetSelected(preset) => {
+            app.preset = preset;
+        }
+        Message::HumanColorSelected(color) => {
+            app.human_color = color;
+        }
         Message::SliderChanged(value) => {
             app.slider_value = value;
         }
+        Message::RandomOpeningChanged(value) => {
+            app.random_opening_moves = value.round() as u32;
+        }
+        Message::ToggleEngineSuggestion(enabled) => {
+            app.show_engine_suggestion = enabled;
+        }
+        Message::ToggleBlindfold(enabled) => {
+            app.blindfold_mode = enabled;
+        }
+        Message::TogglePreMoves(enabled) => {
+            app.premoves_enabled = enabled;
+        }
+        Message::ToggleBook(enabled) => {
+            app.book = enabled;
+        }
+        Message::ThreadsChanged(value) => {
+            app.search_threads = value.round() as usize;
+        }
+        Message::HashSizeChanged(value) => {
+            app.hash_mb = 1 << value.round() as u32;
+        }
+        Message::ContemptChanged(value) => {
+            app.contempt = value;
+        }
+        Message::ToggleExternalEngine(enabled) => {
+            app.external_engine = enabled;
+        }
+        Message::ExternalEnginePathChanged(path) => {
+            app.external_engine_path = path;
+        }
+        Message::ExternalMoveTimeChanged(seconds) => {
+            app.external_move_time = Duration::from_secs_f32(seconds);
+        }
+        Message::ExternalEngineFailed(error) => {
+            if app.bot_search.is_some() {
+                app.external_engine_error = Some(error);
+            }
+        }
+        Message::ToggleHalfBlind => {
+            if app.blindfold_mode {
+                app.half_blind = !app.half_blind;
+            }
+        }
+        Message::EngineSuggestion(played, suggestion, loss) => {
+            // Ignore suggestions that arrive after the human has moved on
+            if app.last_human_move == Some(played) {
+                app.engine_suggestion = Some(suggestion);
+                app.suggestion_loss = Some(loss);
+            }
+        }
         Message::Restart => {
+            app.cancel_bot_search();
+            app.cancel_review_search();
+            // The record outlives the game, even if it could not be saved
+            let record = std::mem::take(&mut app.record);
             *app = ChessApp::default();
+            app.record = record;
+        }
+        Message::RematchwithSwap => {
+            // The settings, difficulty included, carry over to the rematch
+            if matches!(app.state, AppState::GameOver(_)) {
+                app.human_color = opposite_color(app.previous_human_color);
+                app.flipped = app.human_color == Color::Black;
+                app.start_position = Board::new();
+                return app.reset_to_start();
+            }
+        }
+        Message::ResetToStartPosition => {
+            if matches!(app.state, AppState::Playing | AppState::GameOver(_)) {
+                return app.reset_to_start();
+            }
+        }
+        Message::FlipBoard => {
+            app.flipped = !app.flipped;
         }
         Message::DifficultySelected => {
-            app.difficulty = app.slider_value.round() as u32; // Save slider value as difficulty
+            match app.preset.settings() {
+                Some(settings) => {
+                    app.difficulty = settings.depth;
+                    app.random_opening_moves = settings.random_opening_moves;
+                    app.random_margin = settings.random_margin;
+                    app.skill_level = settings.skill_level;
+                }
+                None => {
+                    app.difficulty = app.slider_value.round() as u32; // Save slider value as difficulty
+                    app.random_margin = 0;
+                    app.skill_level = None;
+                }
+            }
             app.state = AppState::Playing;
+            // The first mover's clock starts with the game
+            app.move_start_time = Instant::now();
+            // The human's side is at the bottom of the board
+            app.flipped = app.human_color == Color::Black;
+            // The bot moves first when it plays White, or when a pasted position has it to move
+            if app.current_turn != app.human_color {
+                return Task::done(Message::BotMove);
+            }
         }
         Message::SquareClicked(row, col) => {
-            // Allow human moves only when it's White's turn.
+            // Allow human moves only on the human's turn.
             println!("turn: {:?}", app.current_turn);
-            if app.current_turn == Color::White && app.state == AppState::Playing {
+            if app.current_turn == app.human_color && matches!(app.state, AppState::Playing) {
+                app.move_error = None;
                 if let Some((sel_row, sel_col)) = app.selected {
                     println!("selected: {} {}", sel_row, sel_col);
                     // Attempt to move from the selected square to the clicked square.
-                    if app.board.is_valid_move((sel_row, sel_col), (row, col)) {
-                        app.last_move = Some(format!(
-                            "White moved {} from ({}, {}) to ({}, {})",
-                            app.board.squares[sel_row][sel_col].unwrap().kind.get_name(),
-                            sel_row,
-                            sel_col,
-                            row,
-                            col
-                        ));
-                        if let Some(piece) = app.board.squares[row][col] {
-                            if piece.color == Color::Black {
-                                app.captured_black.push(piece.kind); // Add to captured white
-                                app.last_move = Some(format!(
-                                    "White moved {} from ({}, {}) to ({}, {}) and captured {}.",
-                                    app.board.squares[sel_row][sel_col].unwrap().kind.get_name(),
-                                    sel_row,
-                                    sel_col,
-                                    row,
-                                    col,
-                                    piece.kind.get_name()
-                                ));
-                            }
-                        }
-                        app.board.apply_move(((sel_row, sel_col), (row, col)));
-                        app.selected = None;
-                        app.current_turn = opposite_color(app.current_turn);
-                        if app.board.is_checkmate(app.current_turn)
-                            || app.board.find_king(app.current_turn) == Some((row, col))
-                        {
-                            let winner = GameResult::Winner(opposite_color(app.current_turn));
-                            return Task::perform(async { () }, move |_| Message::EndGame(winner));
-                        } else if app.board.is_draw(app.current_turn) {
-                            return Task::perform(async { () }, |_| {
-                                Message::EndGame(GameResult::Draw)
-                            });
-                        }
-                        // After the human move, trigger the bot move asynchronously.
-                        return Task::perform(async { () }, |_| Message::BotMove);
+                    let (from, to) = ((sel_row, sel_col), (row, col));
+                    if app.legal_moves().contains(Move::new(from, to)) {
+                        return app.play_human_move(Move::new(from, to));
+                    } else if app.legal_moves().contains(Move::promoting(
+                        from,
+                        to,
+                        PieceType::Queen,
+                    )) {
+                        // The promotion card asks for the piece before the move is played
+                        app.promotion_pending = Some((from, to));
                     } else {
-                        // Clear selection on an invalid move.
+                        // Clear selection on an invalid move, saying why it was refused; a
+                        // second click on the selected piece only drops it
                         app.selected = None;
                         app.selected_moves = None;
+                        if from != to {
+                            app.move_error = app.board.validate_move(from, to).err();
+                        }
                         println!("invalid move");
                     }
                 } else {
                     println!("selectting");
-                    // No square is currently selected; select the square if it contains a White piece.
-                    if let Some(piece) = app.board.squares[row][col] {
-                        if piece.color == Color::White {
+                    // No square is currently selected; select the square if it holds one of the human's pieces.
+                    if let Some(piece) = app.board.piece_at(row, col) {
+                        if piece.color == app.human_color {
+                            // A new human move begins, so the previous suggestion is stale
+                            app.engine_suggestion = None;
+                            app.suggestion_loss = None;
                             app.selected = Some((row, col));
-                            app.selected_moves = Some(
-                                app.board
-                                    .generate_moves_for_piece(row, col)
-                                    .into_iter()
-                                    .filter(|(from, to)| app.board.is_valid_move(*from, *to))
-                                    .map(|(_, (d_row, d_col))| (d_row, d_col))
-                                    .collect(),
-                            );
+                            app.selected_moves = Some(app.legal_moves().from(row, col));
                         }
                     }
                 }
+            } else if app.premoves_enabled && matches!(app.state, AppState::Playing) {
+                // While the bot thinks, one of the human's pieces and then its target square queue a
+                // pre-move; a click with one queued drops it again
+                if app.pre_move.take().is_some() {
+                    app.selected = None;
+                } else if let Some((sel_row, sel_col)) = app.selected.take() {
+                    if (sel_row, sel_col) != (row, col) {
+                        app.pre_move = Some((sel_row, sel_col, row, col));
+                    }
+                } else if app
+                    .board
+                    .piece_at(row, col)
+                    .is_some_and(|piece| piece.color == app.human_color)
+                {
+                    // Its targets depend on the bot's reply, so none are highlighted
+                    app.selected = Some((row, col));
+                    app.selected_moves = None;
+                }
             }
         }
         Message::BotMove => {
-            // Bot moves as Black.
-            if app.current_turn == Color::Black {
-                if app.board.is_in_check(opposite_color(app.current_turn)) {
-                    let winner = GameResult::Winner(app.current_turn);
-                    return Task::perform(async { () }, move |_| Message::EndGame(winner));
-                }
-                if let Some(mv) =
-                    improved_best_move_for_color(&app.board, Color::Black, app.difficulty)
-                {
-                    app.last_move = Some(format!(
-                        "Black moved {} from ({}, {}) to ({}, {})",
-                        app.board.squares[mv.0 .0][mv.0 .1].unwrap().kind.get_name(),
-                        mv.0 .0,
-                        mv.0 .1,
-                        mv.1 .0,
-                        mv.1 .1
-                    ));
-                    // Check for capture
-                    if let Some(piece) = app.board.squares[mv.1 .0][mv.1 .1] {
-                        if piece.color == Color::White {
-                            app.captured_white.push(piece.kind); // Add to captured black pieces
-                            app.last_move = Some(format!(
-                                "Black moved {} from ({}, {}) to ({}, {}) and captured {}.",
-                                app.board.squares[mv.0 .0][mv.0 .1].unwrap().kind.get_name(),
-                                mv.0 .0,
-                                mv.0 .1,
-                                mv.1 .0,
-                                mv.1 .1,
-                                piece.kind.get_name()
-                            ));
+            // The bot plays the side the human does not
+            let bot_color = opposite_color(app.human_color);
+            if app.current_turn == bot_color && app.bot_search.is_none() {
+                // Play randomly during the training opening, then switch to the engine
+                let depth = if app.bot_moves_played < app.random_opening_moves {
+                    0
+                } else {
+                    app.difficulty
+                };
+                let stop = Arc::new(AtomicBool::new(false));
+                let limits = SearchLimits {
+                    stop: Some(Arc::clone(&stop)),
+                    random_margin: app.random_margin,
+                    skill_level: app.skill_level,
+                    // Only the bot holds draws in contempt; hints and reviews stay objective
+                    contempt: app.contempt,
+                    // The game's seed picks among the book moves, so each game opens its own way
+                    book: app.book,
+                    book_file: app.book.then(|| app.book_file.clone()).flatten(),
+                    ..app.search_limits(depth)
+                };
+                let board = app.board.clone();
+                let history = app.position_history.clone();
+                // The external engine, when set, plays past the random training opening
+                let external = (app.external_engine && depth > 0).then(|| ExternalMove {
+                    engine: Arc::clone(&app.uci_engine),
+                    path: PathBuf::from(app.external_engine_path.trim()),
+                    move_time: app.external_move_time,
+                    start_position: app.start_position.clone(),
+                    moves: app.move_history.clone(),
+                });
+                app.external_engine_error = None;
+                let external_stop = Arc::clone(&stop);
+                // The search runs on the executor's thread pool and streams each completed
+                // iteration back, so the board stays responsive while the bot thinks
+                let search = iced::stream::channel(16, move |mut output| async move {
+                    if let Some(external) = external {
+                        match external.play(&external_stop) {
+                            Ok(result) => {
+                                let _ = output.send(Message::BotSearchDone(Some(result))).await;
+                                return;
+                            }
+                            Err(error) => {
+                                let _ = output
+                                    .send(Message::ExternalEngineFailed(error.to_string()))
+                                    .await;
+                            }
                         }
                     }
+                    let result =
+                        search_with_info(&board, bot_color, &limits, &history, &mut |result| {
+                            let _ = output.try_send(Message::BotThinking(result.clone()));
+                        });
+                    let _ = output.send(Message::BotSearchDone(result)).await;
+                });
+                let (task, handle) = Task::run(search, |message| message).abortable();
+                app.bot_search = Some(BackgroundSearch { stop, task: handle });
+                app.bot_progress = None;
+                app.bot_cancelled = false;
+                return task;
+            }
+        }
+        Message::BotThinking(result) => {
+            if app.bot_search.is_some() {
+                app.bot_progress = Some(result);
+            }
+        }
+        Message::CancelBotSearch => {
+            // The board keeps its state, with the bot still to move
+            app.cancel_bot_search();
+            app.bot_cancelled = true;
+        }
+        Message::BotSearchDone(result) => {
+            // A cancelled search has nothing left to say
+            if app.bot_search.take().is_none() {
+                return Task::none();
+            }
+            app.bot_progress = None;
+            app.bot_moves_played += 1;
+            // A search stopped before its first iteration has no move, and the bot plays any
+            // legal one; with none, the game is over
+            let found = result.map(|result| {
+                let mv = result.best_move;
+                app.last_search = Some((app.board.clone(), result));
+                mv
+            });
+            match reply_after_search(&app.board, app.current_turn, found) {
+                Reply::Play(mv) => {
+                    app.last_move = Some(format!(
+                        "{:?} played {}",
+                        app.current_turn,
+                        app.board.move_to_san(mv)
+                    ));
+                    app.record_capture(mv);
                     app.board.apply_move(mv);
+                    app.position_history.push(app.board.zobrist_key());
+                    app.record_move(mv);
                     app.current_turn = opposite_color(app.current_turn);
-                    if app.board.is_checkmate(app.current_turn) {
-                        let winner = GameResult::Winner(opposite_color(app.current_turn));
-                        return Task::perform(async { () }, move |_| Message::EndGame(winner));
-                    } else if app.board.is_draw(app.current_turn) {
-                        return Task::perform(async { () }, |_| Message::EndGame(GameResult::Draw));
+                    if let Some(result) = app.game_result() {
+                        return Task::perform(async {}, move |_| Message::EndGame(result));
                     }
-                } else {
-                    let winner = GameResult::Winner(opposite_color(app.current_turn));
-                    return Task::perform(async { () }, move |_| Message::EndGame(winner));
+                    // A queued pre-move is played straight away if it is legal after the bot's
+                    // move, and dropped otherwise; a half-made one is dropped too
+                    app.selected = None;
+                    if let Some((from_row, from_col, to_row, to_col)) = app.pre_move.take() {
+                        // A queued promotion makes a queen, there being no time to ask
+                        let m = app.board.with_queen_promotion(Move::new(
+                            (from_row, from_col),
+                            (to_row, to_col),
+                        ));
+                        if app.legal_moves().contains(m) {
+                            app.engine_suggestion = None;
+                            app.suggestion_loss = None;
+                            return app.play_human_move(m);
+                        }
+                    }
+                }
+                Reply::Checkmated => {
+                    let result = GameResult::Winner(opposite_color(app.current_turn));
+                    return Task::perform(async {}, move |_| Message::EndGame(result));
+                }
+                Reply::Stalemated => {
+                    return Task::perform(async {}, move |_| Message::EndGame(GameResult::Draw));
                 }
             }
         }
+        Message::ClaimDraw => {
+            if app.can_claim_draw() {
+                app.cancel_bot_search();
+                return Task::done(Message::EndGame(GameResult::Draw));
+            }
+        }
+        Message::Resign => {
+            if matches!(app.state, AppState::Playing) {
+                app.cancel_bot_search();
+                app.resigned = true;
+                return Task::done(Message::EndGame(GameResult::Winner(opposite_color(
+                    app.human_color,
+                ))));
+            }
+        }
         Message::EndGame(result) => {
+            // Each game counts once towards the record
+            if !matches!(app.state, AppState::Playing) {
+                return Task::none();
+            }
+            app.pre_move = None;
+            app.previous_human_color = app.human_color;
+            let outcome = match result {
+                GameResult::Winner(color) if color == app.human_color => Outcome::Win,
+                GameResult::Winner(_) => Outcome::Loss,
+                GameResult::Draw => Outcome::Draw,
+            };
             app.state = AppState::GameOver(result);
+            // The record and rating are against the bot at its difficulty, which says nothing
+            // of a game against an external engine
+            if app.external_engine {
+                return Task::none();
+            }
+            let moves = app.move_history.len().div_ceil(2) as u32;
+            app.new_best = app
+                .record
+                .record_game(outcome, app.difficulty, moves, app.resigned);
+            return Task::done(Message::GameRecordUpdated);
+        }
+        Message::GameRecordUpdated => {
+            // A browser has no file to save to, so there the record lasts as long as the page
+            #[cfg(not(target_arch = "wasm32"))]
+            if let Err(error) = std::fs::write(RECORD_FILE, app.record.to_json()) {
+                app.notice = Some(format!("Could not save your record: {}", error));
+            }
+        }
+        Message::ReviewGame => {
+            let game = match &app.state {
+                AppState::GameOver(_) => app.finished_game(),
+                AppState::Studying { game, .. } => game.clone(),
+                _ => return Task::none(),
+            };
+            // Replay the game from the start to get the position after every move
+            let moves: Vec<Move> = game.moves.iter().map(|game_move| game_move.mv).collect();
+            let mut board = game.start_position();
+            let mut boards = vec![board.clone()];
+            for &m in &moves {
+                board.apply_move(m);
+                boards.push(board.clone());
+            }
+
+            // Each position is searched in turn in the background, and its result sent back
+            // as soon as it is known, so the review can be browsed while it fills in
+            let stop = Arc::new(AtomicBool::new(false));
+            let limits = SearchLimits {
+                stop: Some(Arc::clone(&stop)),
+                ..app.search_limits(REVIEW_DEPTH)
+            };
+            let positions = boards.clone();
+            let history: Vec<u64> = boards.iter().map(Board::zobrist_key).collect();
+            let review = iced::stream::channel(16, move |mut output| async move {
+                for (index, board) in positions.iter().enumerate() {
+                    let (score, best) = review_position(board, &limits, &history[..=index]);
+                    if output
+                        .send(Message::ReviewEvaluated(score, best))
+                        .await
+                        .is_err()
+                    {
+                        break;
+                    }
+                }
+            });
+            let (task, handle) = Task::run(review, |message| message).abortable();
+            app.review_search = Some(BackgroundSearch { stop, task: handle });
+            app.selected = None;
+            app.selected_moves = None;
+            app.state = AppState::GameReview {
+                metadata: game.metadata,
+                index: boards.len() - 1,
+                boards,
+                moves,
+                evals: Vec::new(),
+                best_alternatives: Vec::new(),
+            };
+            return task;
+        }
+        Message::ReviewEvaluated(score, best) => {
+            if let AppState::GameReview {
+                boards,
+                evals,
+                best_alternatives,
+                ..
+            } = &mut app.state
+            {
+                evals.push(score);
+                best_alternatives.push(best);
+                if evals.len() == boards.len() {
+                    app.review_search = None;
+                }
+            }
+        }
+        Message::ReviewGoTo(new_index) => {
+            if let AppState::GameReview { index, boards, .. } = &mut app.state {
+                *index = new_index.min(boards.len() - 1);
+            }
+        }
+        Message::StudyGame => {
+            app.cancel_review_search();
+            app.selected = None;
+            app.selected_moves = None;
+            app.state = AppState::Studying {
+                game: Game::from_moves(&app.move_history),
+                annotation_index: app.move_history.len().saturating_sub(1),
+            };
+        }
+        Message::StudyGoTo(index) => {
+            if let AppState::Studying {
+                game,
+                annotation_index,
+            } = &mut app.state
+            {
+                *annotation_index = index.min(game.moves.len().saturating_sub(1));
+            }
+        }
+        Message::AddAnnotation(index, annotation) => {
+            if let AppState::Studying { game, .. } = &mut app.state {
+                if let Some(game_move) = game.moves.get_mut(index) {
+                    game_move.annotation = (!annotation.is_empty()).then_some(annotation);
+                }
+            }
+        }
+        Message::SetNag(index, nag) => {
+            if let AppState::Studying { game, .. } = &mut app.state {
+                if let Some(game_move) = game.moves.get_mut(index) {
+                    game_move.set_nag(if game_move.nag() == Some(nag) {
+                        None
+                    } else {
+                        Some(nag)
+                    });
+                }
+            }
+        }
+        Message::CopyPgn => match &app.state {
+            AppState::Studying { game, .. } => return iced::clipboard::write(game.to_pgn()),
+            AppState::GameOver(_) => return iced::clipboard::write(app.finished_game().to_pgn()),
+            _ => {}
+        },
+        Message::CopyFen => {
+            return iced::clipboard::write(app.board.to_fen());
+        }
+        Message::PasteFromClipboard => {
+            return iced::clipboard::read().map(Message::ClipboardPasted);
+        }
+        Message::ClipboardPasted(text) => {
+            let text = text.unwrap_or_default();
+            let text = text.trim();
+            // A FEN is one line whose first field has the eight ranks
+            let is_fen = !text.contains('\n')
+                && text
+                    .split_whitespace()
+                    .next()
+                    .is_some_and(|placement| placement.matches('/').count() == 7);
+            if text.is_empty() {
+                app.pgn_error = Some("The clipboard holds no FEN or PGN".to_string());
+            } else if !is_fen {
+                // Of several games, the first is studied
+                app.study_pgn(Ok(text.to_string()));
+            } else if !matches!(app.state, AppState::SelectingDifficulty) {
+                app.pgn_error = Some("A position can only be set up before the game".to_string());
+            } else {
+                app.pgn_error = match app.set_up_position(text) {
+                    Ok(()) => None,
+                    Err(error) => Some(format!("Could not set up the position: {}", error)),
+                };
+                app.notice = app
+                    .pgn_error
+                    .is_none()
+                    .then(|| "Set up the position from the clipboard".to_string());
+            }
+        }
+        Message::RandomPosition => {
+            // The standard position is one of the 960 and may come up too
+            let board = Board::new_chess960(rand::random::<u16>() % 960);
+            app.notice = board
+                .chess960_id
+                .map(|id| format!("Set up Chess960 position {}", id));
+            app.pgn_error = None;
+            app.start_from(board);
+        }
+        Message::KeyPressed(key, modifiers) => {
+            if let Some(message) = app.shortcut(key, modifiers) {
+                return update(app, message);
+            }
+        }
+        Message::Undo => {
+            if matches!(app.state, AppState::Playing) {
+                app.take_back();
+            }
+        }
+        Message::SaveGame => {
+            let pgn = match &app.state {
+                AppState::Studying { game, .. } => game.to_pgn(),
+                AppState::Playing | AppState::GameOver(_) => app.finished_game().to_pgn(),
+                _ => return Task::none(),
+            };
+            app.notice = Some(match std::fs::write(SAVE_FILE, pgn) {
+                Ok(()) => format!("Saved the game to {}", SAVE_FILE),
+                Err(error) => format!("Could not save the game: {}", error),
+            });
+        }
+        Message::LoadGame => {
+            if !matches!(app.state, AppState::Playing) {
+                app.study_pgn(
+                    std::fs::read_to_string(SAVE_FILE).map_err(|error| error.to_string()),
+                );
+                app.notice = Some(
+                    app.pgn_error
+                        .take()
+                        .unwrap_or_else(|| format!("Loaded the game from {}", SAVE_FILE)),
+                );
+            }
+        }
+        Message::RequestHint => {
+            if matches!(app.state, AppState::Playing)
+                && app.current_turn == app.human_color
+                && app.bot_search.is_none()
+            {
+                let board = app.board.clone();
+                let history = app.position_history.clone();
+                let limits = app.search_limits(app.difficulty.max(1));
+                let color = app.human_color;
+                let key = board.zobrist_key();
+                return Task::future(async move {
+                    improved_best_move_for_color(&board, color, &limits, &history)
+                })
+                .and_then(move |best| Task::done(Message::HintFound(key, best.best_move)));
+            }
+        }
+        Message::HintFound(key, hint) => {
+            // A hint for a position the game has left is no use
+            if matches!(app.state, AppState::Playing) && app.board.zobrist_key() == key {
+                app.hint = Some(hint);
+            }
+        }
+        Message::RequestRestart => {
+            app.confirm_restart = true;
+        }
+        Message::Promote(kind) => {
+            if let Some((from, to)) = app.promotion_pending.take() {
+                return app.play_human_move(Move::promoting(from, to, kind));
+            }
+        }
+        Message::Escape => {
+            if app.promotion_pending.take().is_some() {
+                app.selected = None;
+                app.selected_moves = None;
+            } else if app.confirm_restart {
+                app.confirm_restart = false;
+            } else if app.shortcuts_visible {
+                app.shortcuts_visible = false;
+            } else {
+                app.selected = None;
+                app.selected_moves = None;
+                app.pre_move = None;
+            }
+        }
+        Message::ToggleShortcuts => {
+            app.shortcuts_visible = !app.shortcuts_visible;
         }
     }
     Task::none()
@@ -281,19 +1515,209 @@ fn update(app: &mut ChessApp, message: Message) -> Task<Message> {
 
 /// View function for the application.
 /// It receives an immutable reference to our state and returns an Element.
-fn view(app: &ChessApp) -> Element<Message> {
+fn view(app: &ChessApp) -> Element<'_, Message> {
+    let screen = Column::new()
+        .push(screen_view(app))
+        .push_maybe(app.notice.as_deref().map(Text::new));
+    // The promotion choice, the restart question or the shortcut list sits on top of the screen
+    let overlay = if app.promotion_pending.is_some() {
+        let pieces = PROMOTION_PIECES
+            .iter()
+            .fold(Row::new().spacing(10), |row, kind| {
+                row.push(Button::new(Text::new(kind.get_name())).on_press(Message::Promote(*kind)))
+            });
+        Some(
+            Column::new()
+                .spacing(10)
+                .push(Text::new("Promote to").size(24))
+                .push(
+                    pieces.push(
+                        Button::new(Text::new("Cancel"))
+                            .style(button::secondary)
+                            .on_press(Message::Escape),
+                    ),
+                ),
+        )
+    } else if app.confirm_restart {
+        Some(
+            Column::new()
+                .spacing(10)
+                .push(Text::new("Restart the game?").size(24))
+                .push(
+                    Row::new()
+                        .spacing(10)
+                        .push(Button::new(Text::new("Restart")).on_press(Message::Restart))
+                        .push(
+                            Button::new(Text::new("Cancel"))
+                                .style(button::secondary)
+                                .on_press(Message::Escape),
+                        ),
+                ),
+        )
+    } else if app.shortcuts_visible {
+        Some(shortcuts_help())
+    } else {
+        None
+    };
+    match overlay {
+        Some(content) => Stack::new()
+            .push(screen)
+            .push(
+                Container::new(card(content))
+                    .center_x(Length::Fill)
+                    .center_y(Length::Fill),
+            )
+            .into(),
+        None => screen.into(),
+    }
+}
+
+// The screen for the current state, under any overlay
+fn screen_view(app: &ChessApp) -> Element<'_, Message> {
     match &app.state {
         AppState::SelectingDifficulty => {
+            // A preset describes itself; Advanced shows the raw settings instead
+            let settings_view: Column<'_, Message> = match app.preset.settings() {
+                Some(_) => Column::new().push(Text::new(app.preset.description())),
+                None => Column::new()
+                    .spacing(10)
+                    .push(
+                        Text::new(difficulty_level_name(app.slider_value.round() as u32)).size(24),
+                    )
+                    .push(
+                        slider(
+                            0.0..=MAX_DIFFICULTY as f32,
+                            app.slider_value,
+                            Message::SliderChanged,
+                        )
+                        .step(1.0), // Step makes it snap to whole numbers
+                    )
+                    .push(Text::new(match app.slider_value.round() as u32 {
+                        0 => "Random moves".to_string(),
+                        depth => format!("Depth {} (~{} ELO)", depth, estimate_elo(depth)),
+                    }))
+                    .push(
+                        slider(
+                            0.0..=10.0,
+                            app.random_opening_moves as f32,
+                            Message::RandomOpeningChanged,
+                        )
+                        .step(1.0),
+                    )
+                    .push(Text::new(format!(
+                        "Random moves for first {} bot moves",
+                        app.random_opening_moves
+                    ))),
+            };
             Column::new()
+                .push(record_view(&app.record))
                 .push(Text::new("Select Difficulty"))
+                .push(pick_list(
+                    Preset::ALL,
+                    Some(app.preset),
+                    Message::PresetSelected,
+                ))
+                .push(settings_view)
+                .push([Color::White, Color::Black].into_iter().fold(
+                    Row::new().spacing(20).push(Text::new("Play as")),
+                    |row, color| {
+                        row.push(radio(
+                            format!("{:?}", color),
+                            color,
+                            Some(app.human_color),
+                            Message::HumanColorSelected,
+                        ))
+                    },
+                ))
+                .push(
+                    checkbox(
+                        "Show engine suggestion after my moves",
+                        app.show_engine_suggestion,
+                    )
+                    .on_toggle(Message::ToggleEngineSuggestion),
+                )
                 .push(
-                    slider(1.0..=7.0, app.slider_value, Message::SliderChanged).step(1.0), // Step makes it snap to whole numbers
+                    checkbox(
+                        "Blindfold (press B to mark occupied squares)",
+                        app.blindfold_mode,
+                    )
+                    .on_toggle(Message::ToggleBlindfold),
+                )
+                .push(
+                    checkbox(
+                        "Allow pre-moves (queue a move while the bot thinks)",
+                        app.premoves_enabled,
+                    )
+                    .on_toggle(Message::TogglePreMoves),
+                )
+                .push(
+                    checkbox("Opening book (the bot varies its openings)", app.book)
+                        .on_toggle(Message::ToggleBook),
+                )
+                .push(
+                    slider(
+                        1.0..=cpu_count() as f32,
+                        app.search_threads as f32,
+                        Message::ThreadsChanged,
+                    )
+                    .step(1.0),
                 )
                 .push(Text::new(format!(
-                    "Difficulty: {}",
-                    app.slider_value.round() as u32
+                    "Search threads: {} of {} cores{}",
+                    app.search_threads,
+                    cpu_count(),
+                    if app.search_threads == 1 {
+                        " (single-threaded)"
+                    } else {
+                        ""
+                    }
                 )))
-                .push(Button::new(Text::new("Start Game")).on_press(Message::DifficultySelected))
+                .push(
+                    slider(
+                        0.0..=MAX_HASH_MB.ilog2() as f32,
+                        app.hash_mb.ilog2() as f32,
+                        Message::HashSizeChanged,
+                    )
+                    .step(1.0),
+                )
+                .push(Text::new(format!("Hash table: {} MB", app.hash_mb)))
+                .push(
+                    slider(
+                        -MAX_CONTEMPT..=MAX_CONTEMPT,
+                        app.contempt,
+                        Message::ContemptChanged,
+                    )
+                    .step(5),
+                )
+                .push(Text::new(format!(
+                    "Bot contempt: {} ({})",
+                    app.contempt,
+                    match app.contempt {
+                        0 => "takes a draw at its true worth",
+                        contempt if contempt > 0 => "plays on rather than draw",
+                        _ => "settles for a draw",
+                    }
+                )))
+                // Browsers cannot start other programs
+                .push_maybe((!cfg!(target_arch = "wasm32")).then(|| external_engine_settings(app)))
+                .push(
+                    Row::new()
+                        .spacing(10)
+                        .push(
+                            Button::new(Text::new("Start Game"))
+                                .on_press(Message::DifficultySelected),
+                        )
+                        .push(
+                            Button::new(Text::new("Paste FEN or PGN"))
+                                .on_press(Message::PasteFromClipboard),
+                        )
+                        .push(
+                            Button::new(Text::new("Random Start"))
+                                .on_press(Message::RandomPosition),
+                        ),
+                )
+                .push_maybe(app.pgn_error.as_deref().map(Text::new))
+                .push(Text::new("Press ? for keyboard shortcuts").size(14))
                 .padding(20)
                 .spacing(10)
                 .into()
@@ -311,15 +1735,7 @@ fn view(app: &ChessApp) -> Element<Message> {
                         app.captured_white
                             .iter()
                             .fold(Row::new().spacing(10), |row, piece| {
-                                let asset = match piece {
-                                    PieceType::Pawn => "assets/white_pawn.jpeg",
-                                    PieceType::King => "assets/white_king.jpeg",
-                                    PieceType::Queen => "assets/white_queen.jpeg",
-                                    PieceType::Rook => "assets/white_rook.png",
-                                    PieceType::Knight => "assets/white_knight.jpeg",
-                                    PieceType::Bishop => "assets/white_bishop.jpeg",
-                                };
-                                let handle = image::Handle::from_path(asset);
+                                let handle = piece_image(Color::White, *piece);
                                 let image: iced::widget::Image<iced::widget::image::Handle> =
                                     Image::new(handle)
                                         .width(Length::Fixed(40.0))
@@ -338,15 +1754,7 @@ fn view(app: &ChessApp) -> Element<Message> {
                         app.captured_black
                             .iter()
                             .fold(Row::new().spacing(10), |row, piece| {
-                                let asset = match piece {
-                                    PieceType::Pawn => "assets/black_pawn.png",
-                                    PieceType::King => "assets/black_king.png",
-                                    PieceType::Queen => "assets/black_queen.jpeg",
-                                    PieceType::Rook => "assets/black_rook.png",
-                                    PieceType::Knight => "assets/black_knight.jpeg",
-                                    PieceType::Bishop => "assets/black_bishop.png",
-                                };
-                                let handle = image::Handle::from_path(asset);
+                                let handle = piece_image(Color::Black, *piece);
                                 let image: iced::widget::Image<iced::widget::image::Handle> =
                                     Image::new(handle)
                                         .width(Length::Fixed(40.0))
@@ -358,23 +1766,143 @@ fn view(app: &ChessApp) -> Element<Message> {
                     ),
                 );
 
+            let (white_pieces, black_pieces) = compute_piece_count(&app.board);
+            let material_view =
+                Row::new()
+                    .spacing(20)
+                    .push(material_text(&app.board))
+                    .push(Text::new(format!(
+                        "Pieces: White {}, Black {}",
+                        white_pieces, black_pieces
+                    )));
+
+            // Whose turn it is, with the number of the move being played, after the number of
+            // the Chess960 position the game began from
+            let status_view = Text::new(format!(
+                "{}Move {}: {:?} to move{}",
+                app.start_position
+                    .chess960_id
+                    .map_or(String::new(), |id| format!("Chess960 #{} · ", id)),
+                app.board.full_move_number,
+                app.current_turn,
+                if app.board.is_in_check(app.current_turn) {
+                    " (check)"
+                } else {
+                    ""
+                }
+            ));
+
             // Display the last move
             let last_move_view = Column::new().push(Text::new("Last Move")).push(Text::new(
                 app.last_move
                     .clone()
                     .unwrap_or_else(|| "No move yet".to_string()),
             ));
+            // Compare the human's last move with the engine's choice
+            let suggestion_view = match (app.engine_suggestion, app.suggestion_loss) {
+                (Some(_), Some(0)) => Some(Text::new("Engine suggestion: Best!")),
+                (Some(suggestion), Some(loss)) if loss >= 5000 => Some(Text::new(format!(
+                    "Engine suggestion: {} (your move misses a decisive line)",
+                    move_to_uci(suggestion)
+                ))),
+                (Some(suggestion), Some(loss)) => Some(Text::new(format!(
+                    "Engine suggestion: {} (your move: -{:.2})",
+                    move_to_uci(suggestion),
+                    loss as f32 / 100.0
+                ))),
+                _ => None,
+            };
+
+            // Display how sure the engine is of its last move and the line it expects after it
+            let engine_line_view = Column::new()
+                .push(Text::new("Engine Line"))
+                .extend(match &app.last_search {
+                    Some((board, result)) => engine_summary(board, result),
+                    None => vec![Text::new("No search yet").into()],
+                });
+            let move_history_view = app.move_history_view();
+            // While the bot thinks, show how deep it got and offer to stop it; once stopped,
+            // offer to let it think again
+            let thinking_view = if app.bot_search.is_some() {
+                Some(
+                    Row::new()
+                        .spacing(10)
+                        .push(Text::new(match &app.bot_progress {
+                            Some(progress) => format!(
+                                "Thinking… depth {} (best so far: {})",
+                                progress.depth,
+                                move_to_uci(progress.best_move)
+                            ),
+                            None => "Thinking…".to_string(),
+                        }))
+                        .push(Button::new(Text::new("Cancel")).on_press(Message::CancelBotSearch)),
+                )
+            } else if app.bot_cancelled {
+                Some(
+                    Row::new()
+                        .spacing(10)
+                        .push(Text::new("The bot's search was cancelled"))
+                        .push(Button::new(Text::new("Think Again")).on_press(Message::BotMove)),
+                )
+            } else {
+                None
+            };
             let controls = Row::new()
                 .push(Button::new(Text::new("Restart")).on_press(Message::Restart))
+                .push(
+                    Button::new(Text::new("Reset to Start Position"))
+                        .on_press(Message::ResetToStartPosition),
+                )
+                .push(Button::new(Text::new("Flip Board")).on_press(Message::FlipBoard))
+                .push(
+                    Button::new(Text::new("Hint")).on_press_maybe(
+                        (app.current_turn == app.human_color && app.bot_search.is_none())
+                            .then_some(Message::RequestHint),
+                    ),
+                )
+                // Only offered once the position allows a claim
+                .push(
+                    Button::new(Text::new("Claim Draw"))
+                        .on_press_maybe(app.can_claim_draw().then_some(Message::ClaimDraw)),
+                )
+                .push(
+                    Button::new(Text::new("Resign"))
+                        .style(button::danger)
+                        .on_press(Message::Resign),
+                )
                 .padding(10)
                 .spacing(10); // Add spacing around the button
 
             // Combine everything
             Column::new()
+                .push(material_view)
                 .push(board_view)
+                .push(status_view)
+                .push_maybe(app.move_error.map(|error| {
+                    Text::new(format!("Illegal move: {}", error))
+                        .color(IcedColor::from_rgb8(200, 40, 40))
+                }))
+                .push_maybe(app.blindfold_mode.then(|| {
+                    Text::new(if app.half_blind {
+                        "Blindfold: occupied squares marked (B to hide them)"
+                    } else {
+                        "Blindfold: pieces hidden (B to mark occupied squares)"
+                    })
+                }))
+                .push_maybe(thinking_view)
+                .push_maybe(app.external_engine_error.as_ref().map(|error| {
+                    Text::new(format!(
+                        "External engine failed ({}); the built-in engine played instead",
+                        error
+                    ))
+                    .color(IcedColor::from_rgb8(200, 40, 40))
+                }))
                 .push(captured_white_view)
                 .push(captured_black_view)
                 .push(last_move_view)
+                .push_maybe(suggestion_view)
+                .push(engine_line_view)
+                .push(move_history_view)
                 .push(controls)
                 .into()
         }
@@ -383,18 +1911,771 @@ fn view(app: &ChessApp) -> Element<Message> {
                 GameResult::Winner(color) => format!("{:?} Wins!", color),
                 GameResult::Draw => "It's a Draw!".to_string(),
             };
-            let board_view = app.board_view();
+            // The final position stays on screen under a card with the result
+            let result_card = card(
+                Column::new()
+                    .spacing(10)
+                    .push(Text::new("Game Over").size(28))
+                    .push(Text::new(result_text))
+                    .push(Text::new(if app.resigned {
+                        "by resignation"
+                    } else {
+                        game_over_reason(&app.board, &app.position_history)
+                    }))
+                    .push_maybe(app.new_best.then(|| {
+                        Text::new(format!(
+                            "New best! Your first win at difficulty {}",
+                            difficulty_label(app.difficulty)
+                        ))
+                        .color(IcedColor::from_rgb8(0, 150, 0))
+                    }))
+                    .push_maybe(endgame_text(&app.board).map(Text::new))
+                    .push(
+                        Row::new()
+                            .spacing(10)
+                            .push(
+                                Button::new(Text::new("Play Again")).on_press(Message::Restart), // Restart game
+                            )
+                            .push(
+                                Button::new(Text::new("Rematch (Swap Sides)"))
+                                    .on_press(Message::RematchwithSwap),
+                            )
+                            .push(
+                                Button::new(Text::new("Retry Position"))
+                                    .on_press(Message::ResetToStartPosition),
+                            )
+                            .push(Button::new(Text::new("Export PGN")).on_press(Message::CopyPgn))
+                            .push(Button::new(Text::new("Copy FEN")).on_press(Message::CopyFen)),
+                    )
+                    .push(
+                        Row::new()
+                            .spacing(10)
+                            .push(
+                                Button::new(Text::new("Review Game")).on_press(Message::ReviewGame),
+                            )
+                            .push(
+                                Button::new(Text::new("Study Game")).on_press(Message::StudyGame),
+                            ),
+                    ),
+            );
+            let last_move = app.move_history.last().copied();
             Column::new()
-                .push(Text::new("Game Over"))
-                .push(Text::new(result_text))
                 .push(
-                    Button::new(Text::new("Play Again")).on_press(Message::Restart), // Restart game
+                    Stack::new()
+                        .push(app.render_board(&app.board, last_move, None))
+                        .push(
+                            Container::new(result_card)
+                                .center_x(Length::Fill)
+                                .center_y(Length::Fill),
+                        ),
+                )
+                .push(app.move_history_view())
+                .into()
+        }
+        AppState::GameReview {
+            metadata,
+            index,
+            boards,
+            moves,
+            evals,
+            best_alternatives,
+        } => {
+            let index = *index;
+            let result_text = match metadata.result.as_str() {
+                "1-0" => "White won",
+                "0-1" => "Black won",
+                "1/2-1/2" => "Drawn",
+                _ => "unfinished",
+            };
+
+            // Board 0 is the start position; board i follows moves[i - 1]
+            let played = index.checked_sub(1).map(|i| moves[i]);
+            let alternative = index
+                .checked_sub(1)
+                .and_then(|i| best_alternatives.get(i).copied().flatten())
+                .filter(|&best| Some(best) != played);
+            let board_view = Row::new()
+                .spacing(10)
+                .push(eval_bar(evals.get(index).copied(), app.flipped))
+                .push(app.render_board(&boards[index], played, alternative));
+
+            let move_text = match played {
+                Some(m) => format!(
+                    "Move {}: {} {}",
+                    index.div_ceil(2),
+                    if index % 2 == 1 { "White" } else { "Black" },
+                    move_to_uci(m)
+                ),
+                None => "Start position".to_string(),
+            };
+            // A move's loss is how far the score fell from the best line of the position before
+            // it, from the mover's point of view
+            let verdict = match (played, index.checked_sub(1)) {
+                (Some(m), Some(previous)) => match (evals.get(previous), evals.get(index)) {
+                    (Some(&before), Some(&after)) => {
+                        let loss = if previous % 2 == 0 {
+                            before - after
+                        } else {
+                            after - before
+                        };
+                        Some(if best_alternatives[previous] == Some(m) {
+                            "Best Move!".to_string()
+                        } else if loss >= 5000 {
+                            format!(
+                                "Blunder: misses a decisive line (the engine played {})",
+                                alternative.map_or("-".to_string(), move_to_uci)
+                            )
+                        } else if loss >= BLUNDER_THRESHOLD {
+                            format!("Blunder (-{}cp)", loss)
+                        } else {
+                            format!("-{}cp", loss.max(0))
+                        })
+                    }
+                    _ => Some("Evaluating…".to_string()),
+                },
+                _ => None,
+            };
+            let eval_text = match evals.get(index) {
+                Some(&score) => format_score(score),
+                None => "Evaluating…".to_string(),
+            };
+
+            let last = boards.len() - 1;
+            let navigation = Row::new()
+                .spacing(10)
+                .push(Button::new(Text::new("|<")).on_press(Message::ReviewGoTo(0)))
+                .push(
+                    Button::new(Text::new("<"))
+                        .on_press(Message::ReviewGoTo(index.saturating_sub(1))),
+                )
+                .push(Text::new(format!("{} / {}", index, last)))
+                .push(Button::new(Text::new(">")).on_press(Message::ReviewGoTo(index + 1)))
+                .push(Button::new(Text::new(">|")).on_press(Message::ReviewGoTo(last)));
+
+            Column::new()
+                .push(Text::new(format!("Game Review ({})", result_text)))
+                .push_maybe(game_heading(metadata).map(Text::new))
+                .push_maybe(
+                    (!metadata.event.is_empty() || !metadata.date.is_empty()).then(|| {
+                        Text::new(
+                            [metadata.event.as_str(), metadata.date.as_str()]
+                                .into_iter()
+                                .filter(|part| !part.is_empty())
+                                .collect::<Vec<_>>()
+                                .join(", "),
+                        )
+                    }),
                 )
                 .push(board_view)
+                .push(Text::new(move_text))
+                .push_maybe(verdict.map(Text::new))
+                .push(Text::new(format!("Evaluation: {}", eval_text)))
+                .push_maybe(endgame_text(&boards[index]).map(Text::new))
+                .push(Text::new(format!(
+                    "Reviewed {} of {} positions",
+                    evals.len(),
+                    boards.len()
+                )))
+                .push(navigation)
+                .push(
+                    Row::new()
+                        .spacing(10)
+                        .push(Button::new(Text::new("Play Again")).on_press(Message::Restart))
+                        .push(Button::new(Text::new("Study Game")).on_press(Message::StudyGame))
+                        .push(Button::new(Text::new("Flip Board")).on_press(Message::FlipBoard)),
+                )
                 .padding(20)
                 .spacing(10)
                 .into()
         }
+        AppState::Studying {
+            game,
+            annotation_index,
+        } => {
+            let index = *annotation_index;
+            let current = game.moves.get(index);
+            let board_view = match current {
+                Some(game_move) => {
+                    app.render_board(&game.board_after(index + 1), Some(game_move.mv), None)
+                }
+                None => app.render_board(&game.start_position(), None, None),
+            };
+
+            // The selected move's comment and glyph, edited in place
+            let editor = current.map(|game_move| {
+                let nags = Nag::ALL.iter().fold(Row::new().spacing(5), |row, &nag| {
+                    let label = Text::new(nag.symbol());
+                    row.push(if game_move.nag() == Some(nag) {
+                        Button::new(label).on_press(Message::SetNag(index, nag))
+                    } else {
+                        Button::new(label)
+                            .style(button::secondary)
+                            .on_press(Message::SetNag(index, nag))
+                    })
+                });
+                Column::new()
+                    .spacing(10)
+                    .push(Text::new(format!(
+                        "{}{} {}",
+                        index / 2 + 1,
+                        if index % 2 == 0 { "." } else { "..." },
+                        move_to_uci(game_move.mv)
+                    )))
+                    .push(nags)
+                    .push(
+                        text_input(
+                            "Comment on this move",
+                            game_move.annotation.as_deref().unwrap_or(""),
+                        )
+                        .on_input(move |text| Message::AddAnnotation(index, text)),
+                    )
+            });
+
+            // Every move with its glyph, and its comment below it; a move is picked by
+            // clicking it
+            let moves_view = game.moves.iter().enumerate().fold(
+                Column::new().spacing(5).push(Text::new("Move History")),
+                |column, (i, game_move)| {
+                    let label = Button::new(Text::new(format!(
+                        "{}{} {}{}",
+                        i / 2 + 1,
+                        if i % 2 == 0 { "." } else { "..." },
+                        move_to_uci(game_move.mv),
+                        game_move.nag().map_or("", |nag| nag.symbol())
+                    )))
+                    .on_press(Message::StudyGoTo(i));
+                    column
+                        .push(if i == index {
+                            label
+                        } else {
+                            label.style(button::text)
+                        })
+                        .push_maybe(
+                            game_move
+                                .annotation
+                                .as_deref()
+                                .map(|annotation| Text::new(annotation).size(14)),
+                        )
+                },
+            );
+
+            let last = game.moves.len().saturating_sub(1);
+            let navigation = Row::new()
+                .spacing(10)
+                .push(Button::new(Text::new("|<")).on_press(Message::StudyGoTo(0)))
+                .push(
+                    Button::new(Text::new("<"))
+                        .on_press(Message::StudyGoTo(index.saturating_sub(1))),
+                )
+                .push(Button::new(Text::new(">")).on_press(Message::StudyGoTo(index + 1)))
+                .push(Button::new(Text::new(">|")).on_press(Message::StudyGoTo(last)));
+
+            Column::new()
+                .push(Text::new("Study"))
+                .push_maybe(game_heading(&game.metadata).map(Text::new))
+                .push_maybe(
+                    (!game.metadata.event.is_empty()).then(|| Text::new(&game.metadata.event)),
+                )
+                .push(board_view)
+                .push(navigation)
+                .push_maybe(editor)
+                .push(moves_view)
+                .push_maybe(app.pgn_error.as_deref().map(Text::new))
+                .push(
+                    Row::new()
+                        .spacing(10)
+                        .push(Button::new(Text::new("Copy PGN")).on_press(Message::CopyPgn))
+                        .push(
+                            Button::new(Text::new("Paste PGN"))
+                                .on_press(Message::PasteFromClipboard),
+                        )
+                        .push(Button::new(Text::new("Review")).on_press(Message::ReviewGame))
+                        .push(Button::new(Text::new("Flip Board")).on_press(Message::FlipBoard))
+                        .push(Button::new(Text::new("Play Again")).on_press(Message::Restart)),
+                )
+                .padding(20)
+                .spacing(10)
+                .into()
+        }
+    }
+}
+
+// A white, rounded card for content shown over the board
+fn card<'a>(content: Column<'a, Message>) -> Container<'a, Message> {
+    Container::new(content).padding(20).style(|_theme: &Theme| {
+        container::Style::default()
+            .background(IcedColor::from_rgba8(255, 255, 255, 0.85))
+            .border(Border {
+                radius: Radius::from(8.0),
+                ..Border::default()
+            })
+    })
+}
+
+// The keyboard shortcuts, shown over the screen by `?`
+fn shortcuts_help<'a>() -> Column<'a, Message> {
+    [
+        ("Ctrl+Z", "Take back your last move"),
+        ("Ctrl+S", "Save the game to saved_game.pgn"),
+        ("Ctrl+O", "Load and study the game in saved_game.pgn"),
+        ("Ctrl+C", "Copy the position as FEN"),
+        ("Ctrl+Shift+C", "Copy the game as PGN"),
+        (
+            "Ctrl+V",
+            "Before a game, play from a copied FEN or study a copied PGN",
+        ),
+        ("F", "Flip the board"),
+        ("H", "Show the engine's move for you"),
+        ("R", "Restart, after asking"),
+        ("B", "In blindfold play, mark occupied squares"),
+        ("Left / Right", "Step through a reviewed or studied game"),
+        ("Escape", "Close this, or drop the selected piece"),
+        ("?", "Show or hide these shortcuts"),
+    ]
+    .into_iter()
+    .fold(
+        Column::new()
+            .spacing(5)
+            .push(Text::new("Keyboard Shortcuts").size(24)),
+        |column, (keys, action)| {
+            column.push(
+                Row::new()
+                    .spacing(10)
+                    .push(Text::new(keys).width(Length::Fixed(120.0)))
+                    .push(Text::new(action)),
+            )
+        },
+    )
+}
+
+// Searches a position of the game review: its score from White's point of view and the
+// engine's move, None once the game is over
+fn review_position(board: &Board, limits: &SearchLimits, history: &[u64]) -> (i32, Option<Move>) {
+    let color = board.side_to_move;
+    let legal = LegalMoves::new(board, color);
+    if legal.status() == GameStatus::Checkmate {
+        let score = match color {
+            Color::White => -MATE_VALUE,
+            Color::Black => MATE_VALUE,
+        };
+        return (score, None);
+    }
+    if legal.is_draw(board) {
+        return (0, None);
+    }
+    match improved_best_move_for_color(board, color, limits, history) {
+        Some(result) => (result.score, Some(result.best_move)),
+        None => (0, None),
+    }
+}
+
+// "White vs Black" from a game's tags, when either player is named
+fn game_heading(metadata: &GameMetadata) -> Option<String> {
+    let name = |player: &str| {
+        if player.is_empty() {
+            "?".to_string()
+        } else {
+            player.to_string()
+        }
+    };
+    (!metadata.white.is_empty() || !metadata.black.is_empty())
+        .then(|| format!("{} vs {}", name(&metadata.white), name(&metadata.black)))
+}
+
+// The player's record on the main menu, once they have played a game
+fn record_view<'a>(record: &PlayerRecord) -> Column<'a, Message> {
+    if record.games_played == 0 {
+        return Column::new();
+    }
+    Column::new()
+        .spacing(5)
+        .push(Text::new(format!(
+            "W: {} / L: {} / D: {}   Rating: {}",
+            record.wins, record.losses, record.draws, record.rating
+        )))
+        .push_maybe(
+            record
+                .best_difficulty_beaten
+                .map(|best| Text::new(format!("Best: Difficulty {}", difficulty_label(best)))),
+        )
+        .push(Text::new(format!(
+            "Longest win streak: {}   Resigned: {}   Average game: {:.0} moves",
+            record.longest_win_streak,
+            record.games_resigned,
+            record.average_game_length()
+        )))
+}
+
+// Reads the player's record, starting a new one if there is none or it cannot be read, as
+// always in a browser
+fn load_record() -> PlayerRecord {
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(record) = std::fs::read_to_string(RECORD_FILE)
+        .ok()
+        .and_then(|json| PlayerRecord::from_json(&json).ok())
+    {
+        return record;
+    }
+    PlayerRecord::default()
+}
+
+// Reads the Polyglot book in BOOK_FILE, if there is one; never in a browser
+fn load_book() -> Option<Arc<Book>> {
+    #[cfg(not(target_arch = "wasm32"))]
+    if std::path::Path::new(BOOK_FILE).exists() {
+        match Book::open(std::path::Path::new(BOOK_FILE)) {
+            Ok(book) => return Some(Arc::new(book)),
+            Err(error) => eprintln!("Could not load {}: {}", BOOK_FILE, error),
+        }
+    }
+    None
+}
+
+// The difficulty screen's choice of an external UCI engine to play for the bot, with its
+// path and time per move once chosen
+fn external_engine_settings(app: &ChessApp) -> Column<'_, Message> {
+    let settings = Column::new().spacing(10).push(
+        checkbox(
+            "Play against an external UCI engine (such as Stockfish)",
+            app.external_engine,
+        )
+        .on_toggle(Message::ToggleExternalEngine),
+    );
+    if !app.external_engine {
+        return settings;
+    }
+    settings
+        .push(
+            text_input("Path to the engine's executable", &app.external_engine_path)
+                .on_input(Message::ExternalEnginePathChanged),
+        )
+        .push(
+            slider(
+                0.1..=MAX_EXTERNAL_MOVE_SECONDS,
+                app.external_move_time.as_secs_f32(),
+                Message::ExternalMoveTimeChanged,
+            )
+            .step(0.1),
+        )
+        .push(Text::new(format!(
+            "External engine time: {:.1} s per move",
+            app.external_move_time.as_secs_f32()
+        )))
+}
+
+// Why the game on `board` has ended, for the game over card; `history` holds the keys of
+// every position of the game, to tell a claimed repetition apart
+fn game_over_reason(board: &Board, history: &[u64]) -> &'static str {
+    match board.game_status(board.side_to_move) {
+        GameStatus::Checkmate => return "by checkmate",
+        GameStatus::Stalemate => return "by stalemate",
+        GameStatus::Ongoing | GameStatus::Check => {}
+    }
+    if board.half_move_clock >= 100 {
+        "by the fifty-move rule"
+    } else if board.is_threefold_repetition(history) {
+        "by threefold repetition"
+    } else {
+        "by insufficient material"
+    }
+}
+
+// Names the endgame once fewer than 8 pieces remain, with what theory says of it
+fn endgame_text(board: &Board) -> Option<String> {
+    let pieces = (board.occupancy(Color::White) | board.occupancy(Color::Black)).count_ones();
+    let endgame = classify_endgame(board);
+    if pieces >= 8 || endgame == EndgameType::Unknown {
+        return None;
+    }
+    Some(if endgame.is_theoretical_draw() {
+        format!("{}: Theoretical Draw", endgame.name())
+    } else if let Some(moves) = endgame.moves_to_mate() {
+        format!(
+            "{}: Forced Mate (in at most {} moves)",
+            endgame.name(),
+            moves
+        )
+    } else {
+        endgame.name().to_string()
+    })
+}
+
+// Moves of the engine's line written out as the expected continuation
+const CONTINUATION_MOVES: usize = 3;
+
+// The engine's confidence in its last search from `board`: depth, score and nodes, the
+// start of its expected line in algebraic notation, any sacrifice it plans, and the full
+// line with the search's statistics
+fn engine_summary<'a>(board: &Board, result: &SearchResult) -> Vec<Element<'a, Message>> {
+    let mut lines = vec![
+        Text::new(format!(
+            "Engine: d{}, {}, {} nodes",
+            result.depth,
+            format_score(result.score),
+            format_count(result.nodes)
+        ))
+        .into(),
+        Text::new(format!(
+            "Expected continuation: {}",
+            board.line_to_san(&result.pv[..result.pv.len().min(CONTINUATION_MOVES)])
+        ))
+        .into(),
+    ];
+    if let Some(index) = board.first_sacrifice(&result.pv) {
+        // The line starts with the bot's own move; a move number goes up with each White move
+        let move_number = board.full_move_number as usize
+            + match board.side_to_move {
+                Color::White => index / 2,
+                Color::Black => index.div_ceil(2),
+            };
+        lines.push(
+            Text::new(format!("Engine plans a sacrifice on move {}", move_number))
+                .color(IcedColor::from_rgb8(200, 100, 0))
+                .into(),
+        );
+    }
+    lines.push(
+        Text::new(format!(
+            "{} ({:.2}s, {} nps, hash {:.1}% full)",
+            result
+                .pv
+                .iter()
+                .map(|m| move_to_uci(*m))
+                .collect::<Vec<_>>()
+                .join(" "),
+            result.time.as_secs_f32(),
+            result.nps(),
+            result.hash_full as f32 / 10.0
+        ))
+        .into(),
+    );
+    lines
+}
+
+// A count shortened to thousands or millions, e.g. "1.2M"
+fn format_count(count: u64) -> String {
+    match count {
+        0..=999 => count.to_string(),
+        1_000..=999_999 => format!("{:.1}k", count as f32 / 1_000.0),
+        _ => format!("{:.1}M", count as f32 / 1_000_000.0),
+    }
+}
+
+// Formats a score in pawns, or as a forced mate for the side it favours
+fn format_score(score: i32) -> String {
+    if score.abs() >= MATE_VALUE - 1000 {
+        format!("{} mates", if score > 0 { "White" } else { "Black" })
+    } else {
+        format!("{:+.2}", score as f32 / 100.0)
+    }
+}
+
+// The material balance in pawns, "White +1.5", in green for the side ahead; a difference
+// of under 10 centipawns reads "Equal", in grey
+fn material_text<'a>(board: &Board) -> Text<'a> {
+    let balance = compute_material_balance(board);
+    if balance.abs() < 10 {
+        return Text::new("Equal").color(IcedColor::from_rgb8(128, 128, 128));
+    }
+    let leader = if balance > 0 { "White" } else { "Black" };
+    Text::new(format!("{} +{:.1}", leader, balance.abs() as f32 / 100.0))
+        .color(IcedColor::from_rgb8(0, 150, 0))
+}
+
+// A vertical bar split between White and Black in proportion to the score, White's share
+// at the bottom unless the board is flipped. An unknown score shows an even split.
+fn eval_bar<'a>(score: Option<i32>, flipped: bool) -> Element<'a, Message> {
+    // Scores beyond ten pawns fill the bar up to a sliver
+    let white_share = (score.unwrap_or(0).clamp(-1000, 1000) + 1050) as u16;
+    let part = |color: IcedColor, portion: u16| {
+        Container::new(Text::new(""))
+            .width(Length::Fill)
+            .height(Length::FillPortion(portion))
+            .style(move |_theme: &Theme| container::Style::default().background(color))
+    };
+    let white = part(IcedColor::WHITE, white_share);
+    let black = part(IcedColor::BLACK, 2100 - white_share);
+    let bar = if flipped {
+        Column::new().push(white).push(black)
+    } else {
+        Column::new().push(black).push(white)
+    };
+    Container::new(bar)
+        .width(Length::Fixed(COORDINATE_SIZE))
+        .height(Length::Fill)
+        .into()
+}
+
+// Desktop builds load the piece images from the assets directory. The browser has no file
+// system, so the wasm build embeds them in the binary instead.
+#[cfg(not(feature = "wasm"))]
+macro_rules! asset {
+    ($file:literal) => {
+        image::Handle::from_path(concat!("assets/", $file))
+    };
+}
+
+#[cfg(feature = "wasm")]
+macro_rules! asset {
+    ($file:literal) => {
+        image::Handle::from_bytes(include_bytes!(concat!("../assets/", $file)).as_slice())
+    };
+}
+
+fn piece_image(color: Color, kind: PieceType) -> image::Handle {
+    match (color, kind) {
+        (Color::White, PieceType::Pawn) => asset!("white_pawn.jpeg"),
+        (Color::Black, PieceType::Pawn) => asset!("black_pawn.png"),
+        (Color::White, PieceType::King) => asset!("white_king.jpeg"),
+        (Color::Black, PieceType::King) => asset!("black_king.png"),
+        (Color::White, PieceType::Queen) => asset!("white_queen.jpeg"),
+        (Color::Black, PieceType::Queen) => asset!("black_queen.jpeg"),
+        (Color::White, PieceType::Rook) => asset!("white_rook.png"),
+        (Color::Black, PieceType::Rook) => asset!("black_rook.png"),
+        (Color::White, PieceType::Knight) => asset!("white_knight.jpeg"),
+        (Color::Black, PieceType::Knight) => asset!("black_knight.jpeg"),
+        (Color::White, PieceType::Bishop) => asset!("white_bishop.jpeg"),
+        (Color::Black, PieceType::Bishop) => asset!("black_bishop.png"),
+    }
+}
+
+// Named bundles of bot settings, so players need not know what a search depth is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Preset {
+    Beginner,
+    Casual,
+    Club,
+    Expert,
+    Advanced, // The raw depth and random-opening sliders
+}
+
+// The bot settings a preset stands for
+struct PresetSettings {
+    depth: u32,
+    random_opening_moves: u32,
+    random_margin: i32,       // See `SearchLimits::random_margin`
+    skill_level: Option<u32>, // See `SearchLimits::skill_level`
+}
+
+impl Preset {
+    const ALL: [Preset; 5] = [
+        Preset::Beginner,
+        Preset::Casual,
+        Preset::Club,
+        Preset::Expert,
+        Preset::Advanced,
+    ];
+
+    // None for Advanced, whose settings come from the sliders
+    fn settings(self) -> Option<PresetSettings> {
+        let (depth, random_opening_moves, random_margin, skill_level) = match self {
+            Preset::Beginner => (1, 3, 0, Some(0)),
+            Preset::Casual => (3, 1, 15, Some(12)),
+            Preset::Club => (4, 0, 0, None),
+            Preset::Expert => (6, 0, 0, None),
+            Preset::Advanced => return None,
+        };
+        Some(PresetSettings {
+            depth,
+            random_opening_moves,
+            random_margin,
+            skill_level,
+        })
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            Preset::Beginner => "Looks one move ahead, develops its pieces and often misses the best move.",
+            Preset::Casual => "Sees simple tactics but now and then settles for a slightly worse move.",
+            Preset::Club => "Plays its best move, calculating two moves for each side.",
+            Preset::Expert => "Plays its best move, calculating three moves for each side; takes a while to think.",
+            Preset::Advanced => "Set the search depth and random opening moves yourself.",
+        }
+    }
+}
+
+impl std::fmt::Display for Preset {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+// The number of cores the bot may search with, 1 where it cannot tell (or in the browser)
+fn cpu_count() -> usize {
+    std::thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+/// Returns the display name of a difficulty level; level 0 plays random moves.
+fn difficulty_label(difficulty: u32) -> String {
+    if difficulty == 0 {
+        "Random".to_string()
+    } else {
+        difficulty.to_string()
+    }
+}
+
+/// Canvas program drawing a translucent arrow for a move over the board.
+struct MoveArrow {
+    mv: Move,
+    color: IcedColor,
+    flipped: bool, // Must match the orientation of the board underneath
+}
+
+impl<Message> canvas::Program<Message> for MoveArrow {
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &(),
+        renderer: &Renderer,
+        _theme: &Theme,
+        bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<Geometry> {
+        let mut frame = Frame::new(renderer, bounds.size());
+        let square_width = bounds.width / 8.0;
+        let square_height = bounds.height / 8.0;
+        // Row 7 is drawn at the top of the board, unless it is flipped
+        let center = |(row, col): (usize, usize)| {
+            let (x, y) = if self.flipped {
+                (7 - col, row)
+            } else {
+                (col, 7 - row)
+            };
+            Point::new(
+                (x as f32 + 0.5) * square_width,
+                (y as f32 + 0.5) * square_height,
+            )
+        };
+        let (from, to) = (center(self.mv.from), center(self.mv.to));
+
+        let (dx, dy) = (to.x - from.x, to.y - from.y);
+        let length = (dx * dx + dy * dy).sqrt().max(1.0);
+        let (ux, uy) = (dx / length, dy / length);
+        let head_length = square_width.min(square_height) * 0.4;
+        let head_base = Point::new(to.x - ux * head_length, to.y - uy * head_length);
+
+        frame.stroke(
+            &Path::line(from, head_base),
+            Stroke::default()
+                .with_color(self.color)
+                .with_width(square_width.min(square_height) * 0.15),
+        );
+        let head = Path::new(|builder| {
+            builder.move_to(to);
+            builder.line_to(Point::new(
+                head_base.x - uy * head_length * 0.6,
+                head_base.y + ux * head_length * 0.6,
+            ));
+            builder.line_to(Point::new(
+                head_base.x + uy * head_length * 0.6,
+                head_base.y - ux * head_length * 0.6,
+            ));
+            builder.close();
+        });
+        frame.fill(&head, self.color);
+
+        vec![frame.into_geometry()]
     }
 }
 
@@ -427,7 +2708,88 @@ fn hex_to_color(hex: &str) -> IcedColor {
     IcedColor::from_rgb8(r, g, b)
 }
 
+// Key presses no widget has taken, for the keyboard shortcuts (see `ChessApp::shortcut`)
+fn subscription(_app: &ChessApp) -> Subscription<Message> {
+    keyboard::on_key_press(|key, modifiers| Some(Message::KeyPressed(key, modifiers)))
+}
+
+// Has the bot evaluate with the network in NNUE_FILE, keeping the hand-crafted evaluation
+// when there is none
+#[cfg(feature = "nnue")]
+fn load_network() {
+    let path = std::path::Path::new(NNUE_FILE);
+    if !path.exists() {
+        return;
+    }
+    match chess::engine::nnue::NnueEvaluator::new(path) {
+        Ok(network) => {
+            chess::engine::nnue::set_network(network);
+        }
+        Err(error) => eprintln!("Could not load {}: {}", NNUE_FILE, error),
+    }
+}
+
 fn main() -> iced::Result {
+    #[cfg(feature = "nnue")]
+    load_network();
+
+    // `--cli` plays in the terminal instead of opening the window
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("--cli") {
+        let options = CliOptions::from_args(&args[1..]).unwrap_or_else(|error| {
+            eprintln!("{}\n{}", error, cli::USAGE);
+            std::process::exit(2);
+        });
+        if let Err(error) = cli::run(std::io::stdin().lock(), &mut std::io::stdout(), &options) {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    // `--epd FILE` runs a test suite and prints how many positions the engine solved
+    #[cfg(not(target_arch = "wasm32"))]
+    if args.first().map(String::as_str) == Some("--epd") {
+        let (path, options) = match args.get(1) {
+            Some(path) => (path, &args[2..]),
+            None => {
+                eprintln!("{}", chess::epd::USAGE);
+                std::process::exit(2);
+            }
+        };
+        let (limits, move_time) = chess::epd::options_from_args(options).unwrap_or_else(|error| {
+            eprintln!("{}\n{}", error, chess::epd::USAGE);
+            std::process::exit(2);
+        });
+        match chess::epd::run_epd_suite(std::path::Path::new(path), &limits, move_time) {
+            Ok(report) => println!("{}", report),
+            Err(error) => {
+                eprintln!("{}: {}", path, error);
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
+    }
+    // `--uci` speaks the UCI protocol on stdin and stdout, for chess GUIs
+    #[cfg(not(target_arch = "wasm32"))]
+    if args.first().map(String::as_str) == Some("--uci") {
+        if let Err(error) = chess::uci::run(std::io::stdin().lock(), &mut std::io::stdout()) {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    // `--xboard` speaks the XBoard protocol on stdin and stdout, for chess GUIs
+    #[cfg(not(target_arch = "wasm32"))]
+    if args.first().map(String::as_str) == Some("--xboard") {
+        if let Err(error) = chess::xboard::run(std::io::stdin().lock(), &mut std::io::stdout()) {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
     // Use the iced::application helper to create and run the application.
-    iced::application("Rust Chess", update, view).run()
+    iced::application("Rust Chess", update, view)
+        .subscription(subscription)
+        .run()
 }
@@ -0,0 +1,179 @@
+// record.rs
+// The player's record against the bot across games: results, streaks, the hardest level
+// beaten and an Elo-style rating, kept in a small JSON file between runs.
+use crate::engine::estimate_elo;
+use std::fmt::Write;
+
+// How a game ended, from the player's side
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Outcome {
+    Win,
+    Loss,
+    Draw,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct PlayerRecord {
+    pub games_played: u32,
+    pub wins: u32,
+    pub losses: u32,
+    pub draws: u32,
+    pub games_resigned: u32, // Losses by resignation, counted among `losses` too
+    pub longest_win_streak: u32,
+    pub current_streak: i32, // Wins in a row if positive, losses in a row if negative
+    pub best_difficulty_beaten: Option<u32>,
+    pub total_moves: u32, // Full moves over all games, for the average game length
+    pub rating: i32,
+}
+
+// The rating a new player starts from
+pub const START_RATING: i32 = 1200;
+// Rating points a game moves between evenly matched players: a win against an equal
+// opponent gains half of it, a loss costs half
+const RATING_K: f64 = 60.0;
+
+impl Default for PlayerRecord {
+    fn default() -> Self {
+        PlayerRecord {
+            games_played: 0,
+            wins: 0,
+            losses: 0,
+            draws: 0,
+            games_resigned: 0,
+            longest_win_streak: 0,
+            current_streak: 0,
+            best_difficulty_beaten: None,
+            total_moves: 0,
+            rating: START_RATING,
+        }
+    }
+}
+
+impl PlayerRecord {
+    // Adds a finished game of `moves` full moves against the bot at `difficulty`. Returns
+    // whether it was a win at a higher difficulty than any beaten before.
+    pub fn record_game(
+        &mut self,
+        outcome: Outcome,
+        difficulty: u32,
+        moves: u32,
+        resigned: bool,
+    ) -> bool {
+        self.games_played += 1;
+        self.total_moves += moves;
+        let mut new_best = false;
+        match outcome {
+            Outcome::Win => {
+                self.wins += 1;
+                self.current_streak = self.current_streak.max(0) + 1;
+                self.longest_win_streak = self.longest_win_streak.max(self.current_streak as u32);
+                new_best = self
+                    .best_difficulty_beaten
+                    .is_none_or(|best| difficulty > best);
+                if new_best {
+                    self.best_difficulty_beaten = Some(difficulty);
+                }
+            }
+            Outcome::Loss => {
+                self.losses += 1;
+                if resigned {
+                    self.games_resigned += 1;
+                }
+                self.current_streak = self.current_streak.min(0) - 1;
+            }
+            Outcome::Draw => {
+                self.draws += 1;
+                self.current_streak = 0;
+            }
+        }
+        self.rating += rating_change(self.rating, estimate_elo(difficulty) as i32, outcome);
+        new_best
+    }
+
+    // Full moves per game, 0 before the first game
+    pub fn average_game_length(&self) -> f32 {
+        if self.games_played == 0 {
+            0.0
+        } else {
+            self.total_moves as f32 / self.games_played as f32
+        }
+    }
+
+    // The record as a flat JSON object of numbers; no best difficulty is written as -1
+    pub fn to_json(&self) -> String {
+        let mut json = String::from("{\n");
+        let fields = self.fields();
+        for (index, (name, value)) in fields.iter().enumerate() {
+            let comma = if index + 1 < fields.len() { "," } else { "" };
+            let _ = writeln!(json, "  \"{}\": {}{}", name, value, comma);
+        }
+        json.push('}');
+        json
+    }
+
+    // Reads a record written by `to_json`. Unknown fields are skipped and missing ones keep
+    // their defaults, so records from older versions still load.
+    pub fn from_json(json: &str) -> Result<PlayerRecord, String> {
+        let body = json
+            .trim()
+            .strip_prefix('{')
+            .and_then(|rest| rest.strip_suffix('}'))
+            .ok_or("expected a JSON object")?;
+        let mut record = PlayerRecord::default();
+        for pair in body.split(',').filter(|pair| !pair.trim().is_empty()) {
+            let (name, value) = pair
+                .split_once(':')
+                .ok_or_else(|| format!("expected \"name\": value, found '{}'", pair.trim()))?;
+            let name = name.trim().trim_matches('"');
+            let value: i64 = value
+                .trim()
+                .parse()
+                .map_err(|_| format!("invalid value for {}: '{}'", name, value.trim()))?;
+            let count = || u32::try_from(value).map_err(|_| format!("invalid {}", name));
+            match name {
+                "games_played" => record.games_played = count()?,
+                "wins" => record.wins = count()?,
+                "losses" => record.losses = count()?,
+                "draws" => record.draws = count()?,
+                "games_resigned" => record.games_resigned = count()?,
+                "longest_win_streak" => record.longest_win_streak = count()?,
+                "current_streak" => record.current_streak = value as i32,
+                "best_difficulty_beaten" => record.best_difficulty_beaten = count().ok(),
+                "total_moves" => record.total_moves = count()?,
+                "rating" => record.rating = value as i32,
+                _ => {}
+            }
+        }
+        Ok(record)
+    }
+
+    fn fields(&self) -> Vec<(&'static str, i64)> {
+        vec![
+            ("games_played", self.games_played as i64),
+            ("wins", self.wins as i64),
+            ("losses", self.losses as i64),
+            ("draws", self.draws as i64),
+            ("games_resigned", self.games_resigned as i64),
+            ("longest_win_streak", self.longest_win_streak as i64),
+            ("current_streak", self.current_streak as i64),
+            (
+                "best_difficulty_beaten",
+                self.best_difficulty_beaten.map_or(-1, |best| best as i64),
+            ),
+            ("total_moves", self.total_moves as i64),
+            ("rating", self.rating as i64),
+        ]
+    }
+}
+
+// The Elo change for a player rated `rating` after a game against an opponent rated
+// `opponent`: the result less the expected score, scaled by RATING_K
+fn rating_change(rating: i32, opponent: i32, outcome: Outcome) -> i32 {
+    let expected = 1.0 / (1.0 + 10f64.powf((opponent - rating) as f64 / 400.0));
+    let score = match outcome {
+        Outcome::Win => 1.0,
+        Outcome::Loss => 0.0,
+        Outcome::Draw => 0.5,
+    };
+    (RATING_K * (score - expected)).round() as i32
+}
@@ -0,0 +1,354 @@
+// uci.rs
+// The engine behind the Universal Chess Interface, for `chess --uci`, so Arena, Cute Chess and
+// other GUIs speaking it can play against it or analyse with it. The GUI sends the position
+// and a `go` before every move; the search runs on a thread of its own, reporting each
+// iteration in an `info` line, while commands are still read so that `stop` can end it.
+use crate::clock::{less_overhead, share_of_clock, TIMED_SEARCH_DEPTH};
+use crate::engine::book::Book;
+use crate::engine::{
+    move_to_uci, search_with_info, Board, Color, SearchLimits, SearchResult, DEFAULT_HASH_MB,
+    MATE_VALUE, MAX_DIFFICULTY,
+};
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, ScopedJoinHandle};
+use std::time::Duration;
+
+// The reach of the `Hash`, `Threads` and `Contempt` options
+const MAX_HASH_MB: usize = 1024;
+const MAX_THREADS: usize = 256;
+const MAX_CONTEMPT: i32 = 100;
+
+struct Session {
+    board: Board,
+    history: Vec<u64>,    // Every position of the game, for repetitions
+    limits: SearchLimits, // The options set so far, for every search
+}
+
+impl Session {
+    fn new() -> Self {
+        let board = Board::new();
+        Session {
+            history: vec![board.zobrist_key()],
+            board,
+            limits: SearchLimits {
+                hash_mb: DEFAULT_HASH_MB,
+                // One thread unless the GUI asks for more, so matches run engines side by side
+                threads: 1,
+                ..SearchLimits::default()
+            },
+        }
+    }
+
+    // Sets up `position startpos|fen FEN [moves M...]`, leaving the position as it was if
+    // any of it is invalid
+    fn set_position(&mut self, args: &str) -> Result<(), String> {
+        let (position, moves) = match args.split_once("moves") {
+            Some((position, moves)) => (position.trim(), moves),
+            None => (args, ""),
+        };
+        let mut board = if position == "startpos" {
+            Board::new()
+        } else if let Some(fen) = position.strip_prefix("fen") {
+            let board = Board::from_fen(fen.trim())?;
+            board.validate()?;
+            board
+        } else {
+            return Err(format!("invalid position '{}'", position));
+        };
+        let mut history = vec![board.zobrist_key()];
+        for text in moves.split_whitespace() {
+            let m = board
+                .parse_uci_move(text)
+                .map_err(|error| error.to_string())?;
+            board.apply_move(m);
+            history.push(board.zobrist_key());
+        }
+        self.board = board;
+        self.history = history;
+        Ok(())
+    }
+
+    // Reads `setoption name NAME value VALUE`; names are matched ignoring case
+    fn set_option(&mut self, args: &str) -> Result<(), String> {
+        let (name, value) = args
+            .strip_prefix("name")
+            .and_then(|rest| rest.split_once("value"))
+            .map(|(name, value)| (name.trim(), value.trim()))
+            .ok_or_else(|| format!("invalid option '{}'", args))?;
+        let invalid = || format!("invalid value '{}' for {}", value, name);
+        match name.to_ascii_lowercase().as_str() {
+            "hash" => {
+                let megabytes: usize = value.parse().map_err(|_| invalid())?;
+                self.limits.hash_mb = megabytes.clamp(1, MAX_HASH_MB);
+            }
+            "threads" => {
+                let threads: usize = value.parse().map_err(|_| invalid())?;
+                self.limits.threads = threads.clamp(1, MAX_THREADS);
+            }
+            "contempt" => {
+                let contempt: i32 = value.parse().map_err(|_| invalid())?;
+                self.limits.contempt = contempt.clamp(-MAX_CONTEMPT, MAX_CONTEMPT);
+            }
+            "ownbook" => {
+                self.limits.book = value.parse().map_err(|_| invalid())?;
+            }
+            // A Polyglot book to play from, or none for "<empty>"
+            "bookfile" => {
+                self.limits.book_file = match value {
+                    "" | "<empty>" => None,
+                    path => Some(Arc::new(Book::open(Path::new(path)).map_err(|error| {
+                        format!("could not read book '{}': {}", path, error)
+                    })?)),
+                };
+            }
+            _ => return Err(format!("unknown option '{}'", name)),
+        }
+        Ok(())
+    }
+}
+
+// What `go` asks for; a search without a depth, clock or `infinite` goes to MAX_DIFFICULTY
+#[derive(Debug, Default)]
+struct GoOptions {
+    depth: Option<u32>,
+    move_time: Option<Duration>,
+    clocks: [Option<Duration>; 2], // White's and Black's time left
+    increments: [Duration; 2],
+    moves_to_go: Option<u32>,
+    infinite: bool, // Search until `stop`, answering only then
+}
+
+impl GoOptions {
+    // Reads the arguments of `go`, skipping those this engine has no use for (`ponder`,
+    // `nodes`, `mate`, `searchmoves` and their values)
+    fn parse(args: &str) -> GoOptions {
+        let mut options = GoOptions::default();
+        let mut args = args.split_whitespace();
+        while let Some(arg) = args.next() {
+            // Times are in milliseconds, and clocks run below zero once a flag falls
+            let mut number = || args.next().and_then(|value| value.parse::<i64>().ok());
+            let time = |ms: Option<i64>| ms.map(|ms| Duration::from_millis(ms.max(0) as u64));
+            match arg {
+                "depth" => options.depth = number().map(|depth| depth.max(1) as u32),
+                "movetime" => options.move_time = time(number()),
+                "wtime" => options.clocks[0] = time(number()),
+                "btime" => options.clocks[1] = time(number()),
+                "winc" => options.increments[0] = time(number()).unwrap_or_default(),
+                "binc" => options.increments[1] = time(number()).unwrap_or_default(),
+                "movestogo" => options.moves_to_go = number().map(|moves| moves.max(1) as u32),
+                "infinite" => options.infinite = true,
+                _ => {}
+            }
+        }
+        options
+    }
+
+    // The time to spend on this move for `color`: `movetime` if given, or else an even share
+    // of the clock over the moves left in the control plus most of the increment
+    fn budget(&self, color: Color) -> Option<Duration> {
+        if self.infinite {
+            return None;
+        }
+        let side = match color {
+            Color::White => 0,
+            Color::Black => 1,
+        };
+        match (self.move_time, self.clocks[side]) {
+            (Some(move_time), _) => Some(less_overhead(move_time)),
+            (None, Some(clock)) => Some(share_of_clock(
+                clock,
+                self.increments[side],
+                self.moves_to_go,
+            )),
+            (None, None) => None,
+        }
+    }
+}
+
+// A search running on its own thread
+struct Search<'scope> {
+    stop: Arc<AtomicBool>,
+    infinite: bool,
+    thread: ScopedJoinHandle<'scope, Result<(), io::Error>>,
+}
+
+impl Search<'_> {
+    // Waits for the search to answer, first stopping it if `stop` or if it would otherwise
+    // never end
+    fn finish(self, stop: bool) -> Result<(), io::Error> {
+        if stop || self.infinite {
+            self.stop.store(true, Ordering::Relaxed);
+        }
+        self.thread
+            .join()
+            .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+    }
+}
+
+// Runs the protocol until `quit` or the end of the input, where a running search is left to
+// answer unless it is infinite
+pub fn run(input: impl BufRead, output: &mut (impl Write + Send)) -> Result<(), io::Error> {
+    let output = Mutex::new(output);
+    let mut session = Session::new();
+    thread::scope(|scope| {
+        let mut search: Option<Search> = None;
+        for line in input.lines() {
+            let line = line?;
+            let (command, args) = line.trim().split_once(' ').unwrap_or((line.trim(), ""));
+            let args = args.trim();
+            // `stop` and `quit` end a running search; anything else that changes the engine's
+            // state waits for it to answer, as a script sending commands ahead of time expects
+            if !matches!(command, "isready" | "debug" | "ponderhit" | "") {
+                if let Some(search) = search.take() {
+                    search.finish(matches!(command, "stop" | "quit"))?;
+                }
+            }
+            match command {
+                "uci" => send(
+                    &output,
+                    &format!(
+                        "id name chess-rust\nid author the chess-rust authors\n\
+                         option name Hash type spin default {} min 1 max {}\n\
+                         option name Threads type spin default 1 min 1 max {}\n\
+                         option name Contempt type spin default 0 min {} max {}\n\
+                         option name OwnBook type check default false\n\
+                         option name BookFile type string default <empty>\nuciok",
+                        DEFAULT_HASH_MB, MAX_HASH_MB, MAX_THREADS, -MAX_CONTEMPT, MAX_CONTEMPT
+                    ),
+                )?,
+                "isready" => send(&output, "readyok")?,
+                // Each search starts with a table of its own, so only the position is reset
+                "ucinewgame" => {
+                    let limits = session.limits.clone();
+                    session = Session::new();
+                    session.limits = limits;
+                }
+                "position" => {
+                    if let Err(error) = session.set_position(args) {
+                        send(&output, &format!("info string {}", error))?;
+                    }
+                }
+                "setoption" => {
+                    if let Err(error) = session.set_option(args) {
+                        send(&output, &format!("info string {}", error))?;
+                    }
+                }
+                "go" => {
+                    let options = GoOptions::parse(args);
+                    let stop = Arc::new(AtomicBool::new(false));
+                    let budget = options.budget(session.board.side_to_move);
+                    if let Some(budget) = budget {
+                        let timer = Arc::clone(&stop);
+                        thread::spawn(move || {
+                            thread::sleep(budget);
+                            timer.store(true, Ordering::Relaxed);
+                        });
+                    }
+                    let limits = SearchLimits {
+                        depth: options
+                            .depth
+                            .unwrap_or(if budget.is_some() || options.infinite {
+                                TIMED_SEARCH_DEPTH
+                            } else {
+                                MAX_DIFFICULTY
+                            }),
+                        stop: Some(Arc::clone(&stop)),
+                        ..session.limits.clone()
+                    };
+                    let (board, history) = (session.board.clone(), session.history.clone());
+                    let (infinite, output) = (options.infinite, &output);
+                    let thread =
+                        scope.spawn(move || think(&board, &history, &limits, infinite, output));
+                    search = Some(Search {
+                        stop,
+                        infinite,
+                        thread,
+                    });
+                }
+                // The search already running was stopped above
+                "stop" => {}
+                "quit" => return Ok(()),
+                // Settings and commands this engine has no use for: it neither ponders nor
+                // needs registering
+                "" | "debug" | "ponderhit" | "register" => {}
+                _ => send(
+                    &output,
+                    &format!("info string unknown command '{}'", command),
+                )?,
+            }
+        }
+        search.map_or(Ok(()), |search| search.finish(false))
+    })
+}
+
+// Searches `board`, reporting every iteration, and answers with `bestmove`. An infinite
+// search holds its answer back until it is stopped.
+fn think(
+    board: &Board,
+    history: &[u64],
+    limits: &SearchLimits,
+    infinite: bool,
+    output: &Mutex<&mut (impl Write + Send)>,
+) -> Result<(), io::Error> {
+    let color = board.side_to_move;
+    let mut reported = Ok(());
+    let result = search_with_info(board, color, limits, history, &mut |result| {
+        if reported.is_ok() {
+            reported = send(output, &info_line(result, color));
+        }
+    });
+    reported?;
+    if let Some(stop) = infinite.then_some(limits.stop.as_ref()).flatten() {
+        while !stop.load(Ordering::Relaxed) {
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+    // A search stopped before its first iteration has no move, so any legal one is played;
+    // with none at all the protocol expects a null move
+    let m = result
+        .map(|result| result.best_move)
+        .or_else(|| board.generate_all_legal_moves(color).first().copied());
+    send(
+        output,
+        &format!("bestmove {}", m.map_or("0000".to_string(), move_to_uci)),
+    )
+}
+
+// `info depth 6 score cp 34 nodes 120544 nps 802000 time 150 hashfull 12 pv e2e4 e7e5`, with
+// the score for the side to move, as "mate 3" or "mate -2" when a mate is in sight
+fn info_line(result: &SearchResult, color: Color) -> String {
+    let score = match color {
+        Color::White => result.score,
+        Color::Black => -result.score,
+    };
+    let score = if score.abs() >= MATE_VALUE - 1000 {
+        let moves = (MATE_VALUE - score.abs() + 1) / 2;
+        format!("mate {}", if score > 0 { moves } else { -moves })
+    } else {
+        format!("cp {}", score)
+    };
+    let pv: Vec<String> = result.pv.iter().map(|&m| move_to_uci(m)).collect();
+    format!(
+        "info depth {} score {} nodes {} nps {} time {} hashfull {} pv {}",
+        result.depth,
+        score,
+        result.nodes,
+        result.nps(),
+        result.time.as_millis(),
+        result.hash_full,
+        pv.join(" ")
+    )
+}
+
+// Writes one or more lines at once, so the search thread and the command loop never
+// interleave, and flushes them straight to the GUI
+fn send(output: &Mutex<&mut (impl Write + Send)>, text: &str) -> Result<(), io::Error> {
+    let mut output = output
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    writeln!(output, "{}", text)?;
+    output.flush()
+}
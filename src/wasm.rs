@@ -0,0 +1,54 @@
+// wasm.rs
+// The engine's JavaScript API, exported with the `wasm` feature (see WASM.md). Positions go in
+// and out as FEN and moves as coordinate notation ("e2e4"), so the page keeps the game and
+// the engine holds nothing between calls.
+use crate::engine::{improved_best_move_for_color, move_to_uci, Board, SearchLimits};
+use wasm_bindgen::prelude::*;
+
+/// The FEN of the start position.
+#[wasm_bindgen]
+pub fn new_game() -> String {
+    Board::new().to_fen()
+}
+
+/// Plays a move in coordinate notation, such as "e2e4" or "e7e8n", in the position given as
+/// FEN, and returns the FEN of the position after it. Throws if either is invalid or the move
+/// is illegal. A promotion without a piece letter is to a queen.
+#[wasm_bindgen]
+pub fn apply_move_from_uci(fen: &str, uci: &str) -> Result<String, JsError> {
+    let mut board = parse_fen(fen)?;
+    let m = board
+        .parse_uci_move(uci)
+        .map_err(|error| JsError::new(&error.to_string()))?;
+    board.apply_move(m);
+    Ok(board.to_fen())
+}
+
+/// The legal moves of the side to move in the position given as FEN, in coordinate notation.
+#[wasm_bindgen]
+pub fn legal_moves(fen: &str) -> Result<Vec<String>, JsError> {
+    let board = parse_fen(fen)?;
+    Ok(board
+        .generate_all_legal_moves(board.side_to_move)
+        .into_iter()
+        .map(move_to_uci)
+        .collect())
+}
+
+/// The engine's move for the side to move in the position given as FEN, searched `depth`
+/// plies deep, in coordinate notation; undefined when the game is over.
+#[wasm_bindgen]
+pub fn best_move(fen: &str, depth: u32) -> Result<Option<String>, JsError> {
+    let board = parse_fen(fen)?;
+    let result = improved_best_move_for_color(
+        &board,
+        board.side_to_move,
+        &SearchLimits::depth(depth),
+        &[board.zobrist_key()],
+    );
+    Ok(result.map(|result| move_to_uci(result.best_move)))
+}
+
+fn parse_fen(fen: &str) -> Result<Board, JsError> {
+    Board::from_fen(fen).map_err(|error| JsError::new(&format!("invalid FEN: {}", error)))
+}
@@ -0,0 +1,213 @@
+// xboard.rs
+// The engine behind the XBoard (CECP) protocol, for `chess --xboard`, so WinBoard and other
+// GUIs speaking it can play against it. Commands are read a line at a time; moves go both
+// ways in coordinate notation ("e2e4", "e7e8q"). The engine thinks only when it is its turn,
+// without pondering, and cannot be interrupted mid-search.
+use crate::clock::{share_of_clock, TIMED_SEARCH_DEPTH};
+use crate::engine::{
+    improved_best_move_for_color, move_to_uci, Board, Color, GameStatus, SearchLimits,
+    MAX_DIFFICULTY,
+};
+use std::io::{self, BufRead, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+// Sent in reply to `protover`: moves arrive as "usermove e2e4" and leave in coordinates
+const FEATURES: &str = "feature done=0\nfeature myname=\"chess-rust\" usermove=1 san=0 setboard=0 \
+ping=1 colors=0 sigint=0 sigterm=0\nfeature done=1";
+
+struct Session {
+    board: Board,
+    history: Vec<u64>,           // Every position of the game, for repetitions
+    engine_color: Option<Color>, // None in force mode, where the engine only relays moves
+    depth: Option<u32>,          // Set by `sd`
+    moves_per_control: u32,      // 0 when the whole game is one control
+    increment: Duration,
+    clock: Option<Duration>, // The engine's time left, from `level` and then `time`
+}
+
+impl Session {
+    fn new() -> Self {
+        let board = Board::new();
+        Session {
+            history: vec![board.zobrist_key()],
+            board,
+            engine_color: Some(Color::Black),
+            depth: None,
+            moves_per_control: 0,
+            increment: Duration::ZERO,
+            clock: None,
+        }
+    }
+
+    fn play(&mut self, text: &str, output: &mut impl Write) -> Result<(), io::Error> {
+        match self.board.parse_uci_move(text) {
+            Ok(m) => {
+                self.board.apply_move(m);
+                self.history.push(self.board.zobrist_key());
+                self.report_result(output)?;
+                self.think(output)
+            }
+            Err(_) => writeln!(output, "Illegal move: {}", text),
+        }
+    }
+
+    // Moves for the engine if it is its turn and the game is not over
+    fn think(&mut self, output: &mut impl Write) -> Result<(), io::Error> {
+        if self.engine_color != Some(self.board.side_to_move) || self.result().is_some() {
+            return Ok(());
+        }
+        let color = self.board.side_to_move;
+        let budget = self.move_budget();
+        let limits = SearchLimits {
+            depth: self.depth.unwrap_or(if budget.is_some() {
+                TIMED_SEARCH_DEPTH
+            } else {
+                MAX_DIFFICULTY
+            }),
+            stop: budget.map(|budget| {
+                let stop = Arc::new(AtomicBool::new(false));
+                let timer = Arc::clone(&stop);
+                std::thread::spawn(move || {
+                    std::thread::sleep(budget);
+                    timer.store(true, Ordering::Relaxed);
+                });
+                stop
+            }),
+            ..SearchLimits::default()
+        };
+        // A search stopped before its first iteration has no move, so any legal one is played
+        let m = improved_best_move_for_color(&self.board, color, &limits, &self.history)
+            .map(|result| result.best_move)
+            .or_else(|| self.board.generate_all_legal_moves(color).first().copied());
+        if let Some(m) = m {
+            self.board.apply_move(m);
+            self.history.push(self.board.zobrist_key());
+            writeln!(output, "move {}", move_to_uci(m))?;
+            self.report_result(output)?;
+        }
+        Ok(())
+    }
+
+    // The time to spend on this move: an even share of the clock over the moves left in the
+    // control, plus most of the increment
+    fn move_budget(&self) -> Option<Duration> {
+        let clock = self.clock?;
+        let moves_to_go = match self.moves_per_control {
+            0 => None,
+            per_control => Some(per_control - (self.board.full_move_number - 1) % per_control),
+        };
+        Some(share_of_clock(clock, self.increment, moves_to_go))
+    }
+
+    // The result and its reason, as the protocol reports them, once the game is over
+    fn result(&self) -> Option<(&'static str, &'static str)> {
+        let mover = self.board.side_to_move;
+        match self.board.game_status(mover) {
+            GameStatus::Checkmate => {
+                return Some(match mover {
+                    Color::White => ("0-1", "Black mates"),
+                    Color::Black => ("1-0", "White mates"),
+                })
+            }
+            GameStatus::Stalemate => return Some(("1/2-1/2", "Stalemate")),
+            GameStatus::Ongoing | GameStatus::Check => {}
+        }
+        if self.board.is_insufficient_material() {
+            Some(("1/2-1/2", "Insufficient material"))
+        } else if self.board.half_move_clock >= 100 {
+            Some(("1/2-1/2", "Fifty move rule"))
+        } else if self.board.is_threefold_repetition(&self.history) {
+            Some(("1/2-1/2", "Draw by repetition"))
+        } else {
+            None
+        }
+    }
+
+    fn report_result(&self, output: &mut impl Write) -> Result<(), io::Error> {
+        match self.result() {
+            Some((result, reason)) => writeln!(output, "{} {{{}}}", result, reason),
+            None => Ok(()),
+        }
+    }
+}
+
+// Runs the protocol until `quit` or the end of the input
+pub fn run(input: impl BufRead, output: &mut impl Write) -> Result<(), io::Error> {
+    let mut session = Session::new();
+    for line in input.lines() {
+        let line = line?;
+        let (command, args) = line.trim().split_once(' ').unwrap_or((line.trim(), ""));
+        let args = args.trim();
+        match command {
+            "xboard" => writeln!(output)?,
+            "protover" => writeln!(output, "{}", FEATURES)?,
+            "new" => session = Session::new(),
+            "force" => session.engine_color = None,
+            "go" => {
+                session.engine_color = Some(session.board.side_to_move);
+                session.think(output)?;
+            }
+            "usermove" | "move" => session.play(args, output)?,
+            "level" => match parse_level(args) {
+                Some((moves, base, increment)) => {
+                    session.moves_per_control = moves;
+                    session.clock = Some(base);
+                    session.increment = increment;
+                }
+                None => writeln!(output, "Error (bad level): {}", args)?,
+            },
+            // Clocks are given in centiseconds, and run below zero once a flag falls
+            "time" => match args.parse::<i64>() {
+                Ok(centiseconds) => {
+                    session.clock = Some(Duration::from_millis(centiseconds.max(0) as u64 * 10))
+                }
+                Err(_) => writeln!(output, "Error (bad time): {}", args)?,
+            },
+            // The opponent's clock does not change how long the engine thinks
+            "otim" => {}
+            "sd" => match args.parse() {
+                Ok(depth) => session.depth = Some(depth),
+                Err(_) => writeln!(output, "Error (bad depth): {}", args)?,
+            },
+            "ping" => writeln!(output, "pong {}", args)?,
+            // The game is over, whatever the board says; the engine waits for `new`
+            "result" => session.engine_color = None,
+            "quit" => return Ok(()),
+            // Replies to our features, and settings this engine has no use for
+            "" | "accepted" | "rejected" | "random" | "post" | "nopost" | "hard" | "easy"
+            | "computer" | "name" | "rating" | "ics" => {}
+            // Before `usermove=1` is accepted, a move may come bare
+            _ if args.is_empty() && session.board.parse_uci_move(command).is_ok() => {
+                session.play(command, output)?
+            }
+            _ => writeln!(output, "Error (unknown command): {}", command)?,
+        }
+        output.flush()?;
+    }
+    Ok(())
+}
+
+// Reads `level MPS BASE INC`: moves per control (0 for the whole game), the base time in
+// minutes or minutes:seconds, and the increment in seconds
+fn parse_level(args: &str) -> Option<(u32, Duration, Duration)> {
+    let mut fields = args.split_whitespace();
+    let moves = fields.next()?.parse().ok()?;
+    let base = fields.next()?;
+    let base = match base.split_once(':') {
+        Some((minutes, seconds)) => {
+            minutes.parse::<u64>().ok()? * 60 + seconds.parse::<u64>().ok()?
+        }
+        None => base.parse::<u64>().ok()? * 60,
+    };
+    let increment: f64 = fields.next()?.parse().ok()?;
+    if fields.next().is_some() || !(0.0..1e6).contains(&increment) {
+        return None;
+    }
+    Some((
+        moves,
+        Duration::from_secs(base),
+        Duration::from_secs_f64(increment),
+    ))
+}
@@ -0,0 +1,70 @@
+// The built-in opening book: every move it offers is legal, and a search that does not use it
+// is the same search as before it existed
+use chess::engine::book::Book;
+use chess::engine::{improved_best_move_for_color, Board, SearchLimits, SearchResult};
+
+// Walks every position the book reaches from the initial one, returning how many of them it
+// has moves for
+fn walk(book: &Book, board: &Board, line: &mut Vec<String>) -> usize {
+    let legal = board.generate_all_legal_moves(board.side_to_move);
+    let mut positions = usize::from(!book.moves(board).is_empty());
+    for (m, weight) in book.moves(board) {
+        let san = board.move_to_san(m);
+        assert!(
+            legal.contains(&m),
+            "{} is illegal after {}",
+            san,
+            line.join(" ")
+        );
+        assert!(weight > 0);
+        let mut next = board.clone();
+        next.apply_move(m);
+        line.push(san);
+        positions += walk(book, &next, line);
+        line.pop();
+    }
+    positions
+}
+
+#[test]
+fn book_moves_are_legal() {
+    let positions = walk(Book::builtin(), &Board::new(), &mut Vec::new());
+    // Seventeen lines of eight to ten moves, sharing their first few
+    assert!(positions > 50, "only {} positions", positions);
+}
+
+fn search(board: &Board, book: bool) -> SearchResult {
+    let limits = SearchLimits {
+        book,
+        threads: 1,
+        ..SearchLimits::depth(3)
+    };
+    improved_best_move_for_color(board, board.side_to_move, &limits, &[board.zobrist_key()])
+        .unwrap()
+}
+
+#[test]
+fn search_without_the_book() {
+    // In the book, the book's move comes unsearched, and without it the position is searched
+    let start = Board::new();
+    let from_book = search(&start, true);
+    assert_eq!(from_book.nodes, 0);
+    assert!(Book::builtin()
+        .moves(&start)
+        .iter()
+        .any(|&(m, _)| m == from_book.best_move));
+    let searched = search(&start, false);
+    assert!(searched.nodes > 0);
+    assert_eq!(searched.depth, 3);
+
+    // Out of the book, using it or not makes no difference at all
+    let board =
+        Board::from_fen("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4")
+            .unwrap();
+    assert!(Book::builtin().moves(&board).is_empty());
+    let (with, without) = (search(&board, true), search(&board, false));
+    assert_eq!(with.best_move, without.best_move);
+    assert_eq!(with.score, without.score);
+    assert_eq!(with.pv, without.pv);
+    assert_eq!(with.nodes, without.nodes);
+}
@@ -0,0 +1,114 @@
+// What the bot does once its search is over, `reply_after_search`: the move found, any legal
+// move when the search was stopped before finding one, and the end of the game without one
+use chess::engine::{improved_best_move_for_color, reply_after_search, Board, Reply, SearchLimits};
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+
+fn board(fen: &str) -> Board {
+    Board::from_fen(fen).unwrap()
+}
+
+#[test]
+fn checkmated() {
+    // Fool's mate
+    let board = board("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3");
+    assert_eq!(
+        reply_after_search(&board, board.side_to_move, None),
+        Reply::Checkmated
+    );
+}
+
+#[test]
+fn stalemated() {
+    let board = board("k7/2Q5/1K6/8/8/8/8/8 b - - 0 1");
+    assert_eq!(
+        reply_after_search(&board, board.side_to_move, None),
+        Reply::Stalemated
+    );
+}
+
+#[test]
+fn plays_the_move_found() {
+    let board = Board::new();
+    let found = board.parse_uci_move("g1f3").unwrap();
+    assert_eq!(
+        reply_after_search(&board, board.side_to_move, Some(found)),
+        Reply::Play(found)
+    );
+}
+
+// A search stopped before its first iteration comes back empty, yet the bot still moves
+#[test]
+fn stopped_search() {
+    for fen in [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        // In check, with one way out
+        "4k3/8/8/8/8/8/5PP1/r5K1 w - - 0 1",
+    ] {
+        let board = board(fen);
+        let color = board.side_to_move;
+        let limits = SearchLimits {
+            stop: Some(Arc::new(AtomicBool::new(true))),
+            ..SearchLimits::depth(4)
+        };
+        let found = improved_best_move_for_color(&board, color, &limits, &[board.zobrist_key()])
+            .map(|result| result.best_move);
+        assert_eq!(found, None, "{}", fen);
+        let Reply::Play(m) = reply_after_search(&board, color, found) else {
+            panic!("no move in {}", fen);
+        };
+        assert!(board.generate_all_legal_moves(color).contains(&m));
+    }
+}
+
+// The engine's search has nothing to say for a bot without a legal move, and the reply then
+// tells a mated bot, which loses, from a stalemated one, which draws
+#[test]
+fn engine_agrees_on_mate_and_stalemate() {
+    for (fen, reply) in [
+        (
+            "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3",
+            Reply::Checkmated,
+        ),
+        ("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1", Reply::Stalemated),
+        // Pawns blocked and the king boxed in: stalemate with material still on the board
+        ("8/8/8/8/p7/Pp6/2k5/K7 w - - 0 1", Reply::Stalemated),
+    ] {
+        let board = board(fen);
+        let color = board.side_to_move;
+        let found = improved_best_move_for_color(
+            &board,
+            color,
+            &SearchLimits::depth(3),
+            &[board.zobrist_key()],
+        );
+        assert!(found.is_none(), "a move in {}", fen);
+        assert_eq!(reply_after_search(&board, color, None), reply, "{}", fen);
+    }
+}
+
+// The bot playing the mating move leaves the human without a reply, mated
+#[test]
+fn bot_move_ends_the_game() {
+    for fen in [
+        "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1",
+        "r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4",
+    ] {
+        let mut board = board(fen);
+        let color = board.side_to_move;
+        let limits = SearchLimits::depth(3);
+        let result =
+            improved_best_move_for_color(&board, color, &limits, &[board.zobrist_key()]).unwrap();
+        let Reply::Play(m) = reply_after_search(&board, color, Some(result.best_move)) else {
+            panic!("no move in {}", fen);
+        };
+        board.apply_move(m);
+        let opponent = board.side_to_move;
+        assert_eq!(
+            reply_after_search(&board, opponent, None),
+            Reply::Checkmated,
+            "{}",
+            fen
+        );
+    }
+}
@@ -0,0 +1,29 @@
+// Castling is only legal when no square the King stands on, crosses or lands on is attacked,
+// on either wing and for either side
+use chess::engine::{algebraic_to_square, Board};
+
+fn can_play(fen: &str, from: &str, to: &str) -> bool {
+    let board = Board::from_fen(fen).unwrap();
+    board.is_valid_move(
+        algebraic_to_square(from).unwrap(),
+        algebraic_to_square(to).unwrap(),
+    )
+}
+
+#[test]
+fn queenside_through_or_into_check() {
+    // d1 is attacked by the Rook on d8, which the King crosses
+    assert!(!can_play("3rk3/8/8/8/8/8/8/R3K2R w KQ - 0 1", "e1", "c1"));
+    // c1, where the King lands, by the Rook on c8
+    assert!(!can_play("2r1k3/8/8/8/8/8/8/R3K2R w KQ - 0 1", "e1", "c1"));
+    assert!(!can_play("r3k2r/8/8/8/8/8/8/3RK3 b kq - 0 1", "e8", "c8"));
+    // Only the Rook crosses b1, so an attack there does not matter
+    assert!(can_play("1r2k3/8/8/8/8/8/8/R3K2R w KQ - 0 1", "e1", "c1"));
+}
+
+#[test]
+fn kingside_through_or_into_check() {
+    assert!(!can_play("4kr2/8/8/8/8/8/8/R3K2R w KQ - 0 1", "e1", "g1"));
+    assert!(!can_play("4k1r1/8/8/8/8/8/8/R3K2R w KQ - 0 1", "e1", "g1"));
+    assert!(can_play("4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1", "e1", "g1"));
+}
@@ -0,0 +1,169 @@
+// The 960 starting positions of `Board::new_chess960`, and Chess960 castling rights written to
+// FEN and read back, as X-FEN and as Shredder-FEN
+use chess::engine::{algebraic_to_square, move_to_uci, Board, Color, Move, PieceType};
+use std::collections::HashSet;
+
+fn back_rank(board: &Board, row: usize) -> Vec<Option<PieceType>> {
+    (0..8)
+        .map(|col| board.piece_at(row, col).map(|piece| piece.kind))
+        .collect()
+}
+
+fn cols_of(rank: &[Option<PieceType>], kind: PieceType) -> Vec<usize> {
+    (0..8).filter(|&col| rank[col] == Some(kind)).collect()
+}
+
+fn sorted_moves(board: &Board) -> Vec<String> {
+    let mut moves: Vec<String> = board
+        .generate_all_legal_moves(board.side_to_move)
+        .into_iter()
+        .map(move_to_uci)
+        .collect();
+    moves.sort();
+    moves
+}
+
+#[test]
+fn starting_positions() {
+    let mut seen = HashSet::new();
+    for id in 0..960 {
+        let board = Board::new_chess960(id);
+        assert_eq!(board.validate(), Ok(()), "{}", id);
+        let rank = back_rank(&board, 0);
+        assert_eq!(rank, back_rank(&board, 7), "{}", id);
+        assert!(rank.iter().all(Option::is_some));
+        let bishops = cols_of(&rank, PieceType::Bishop);
+        assert_eq!(bishops.len(), 2);
+        assert_ne!(
+            bishops[0] % 2,
+            bishops[1] % 2,
+            "{}: bishops on one colour",
+            id
+        );
+        let rooks = cols_of(&rank, PieceType::Rook);
+        let king = cols_of(&rank, PieceType::King);
+        assert!(rooks[0] < king[0] && king[0] < rooks[1], "{}", id);
+        assert_eq!(cols_of(&rank, PieceType::Queen).len(), 1);
+        assert_eq!(cols_of(&rank, PieceType::Knight).len(), 2);
+
+        // Read back from its FEN, with both sides free to castle either way
+        let fen = board.to_fen();
+        let first_rank = fen.split(['/', ' ']).nth(7).unwrap().to_string();
+        assert!(seen.insert(first_rank), "{} repeats a position", id);
+        assert_eq!(fen.split(' ').nth(2), Some("KQkq"), "{}", id);
+        let read = Board::from_fen(&fen).unwrap();
+        assert_eq!(read.to_fen(), fen);
+        assert_eq!(read.zobrist_key(), board.zobrist_key());
+        // Castling is written as the king taking its rook unless they stand where they usually do
+        assert_eq!(read.is_chess960(), king != [4] || rooks != [0, 7], "{}", id);
+    }
+    assert_eq!(Board::new_chess960(518).to_fen(), Board::new().to_fen());
+    assert_eq!(
+        Board::new_chess960(518 + 960).to_fen(),
+        Board::new().to_fen()
+    );
+}
+
+// Whether the king and rook can castle on an otherwise empty back rank: each of them has a clear
+// way to its square, passing nothing but the other
+fn path_clear(rooks: &[usize], king: usize, rook: usize, kingside: bool) -> bool {
+    let (king_to, rook_to) = if kingside { (6, 5) } else { (2, 3) };
+    let span = |from: usize, to: usize| from.min(to)..=from.max(to);
+    rooks.iter().all(|&other| {
+        other == rook
+            || !span(king, king_to).contains(&other) && !span(rook, rook_to).contains(&other)
+    })
+}
+
+// With the back rank cleared but for the king and rooks, every position castles each way its
+// rooks allow, and the rights survive a round trip through FEN
+#[test]
+fn castling_round_trips() {
+    for id in 0..960 {
+        let mut board = Board::new_chess960(id);
+        for row in [0, 7] {
+            for col in 0..8 {
+                let kind = board.piece_at(row, col).unwrap().kind;
+                if kind != PieceType::King && kind != PieceType::Rook {
+                    board.set_piece(row, col, None);
+                }
+            }
+        }
+        let rank = back_rank(&board, 0);
+        let rooks = cols_of(&rank, PieceType::Rook);
+        let king = cols_of(&rank, PieceType::King)[0];
+        let fen = board.to_fen();
+        for side in ["w", "b"] {
+            let fen = fen.replacen(" w ", &format!(" {} ", side), 1);
+            let read = Board::from_fen(&fen).unwrap();
+            assert_eq!(read.to_fen(), fen);
+            let row = match read.side_to_move {
+                Color::White => 0,
+                Color::Black => 7,
+            };
+            let moves = read.generate_all_legal_moves(read.side_to_move);
+            for (rook, kingside) in [(rooks[0], false), (rooks[1], true)] {
+                let col = match (read.is_chess960(), kingside) {
+                    (true, _) => rook,
+                    (false, true) => 6,
+                    (false, false) => 2,
+                };
+                assert_eq!(
+                    moves.contains(&Move::new((row, king), (row, col))),
+                    path_clear(&rooks, king, rook, kingside),
+                    "{} castling to {} in {}",
+                    id,
+                    col,
+                    fen
+                );
+            }
+
+            // The same rights given by the rooks' files, as Shredder-FEN writes them
+            let files = |upper: bool| -> String {
+                rooks
+                    .iter()
+                    .rev()
+                    .map(|&col| (b'a' + col as u8) as char)
+                    .map(|file| {
+                        if upper {
+                            file.to_ascii_uppercase()
+                        } else {
+                            file
+                        }
+                    })
+                    .collect()
+            };
+            let shredder = fen.replacen("KQkq", &format!("{}{}", files(true), files(false)), 1);
+            let read_shredder = Board::from_fen(&shredder).unwrap();
+            assert_eq!(read_shredder.to_fen(), fen, "{}", shredder);
+            assert_eq!(sorted_moves(&read_shredder), sorted_moves(&read));
+        }
+    }
+}
+
+// A rook's file is written only when another rook stands further out on that side, where KQkq
+// would name the wrong one
+#[test]
+fn inner_rook_keeps_its_file() {
+    let fen = "1r1k2r1/pppppppp/8/8/8/8/PPPPPPPP/RRK3R1 w B - 0 1";
+    let board = Board::from_fen(fen).unwrap();
+    assert_eq!(board.validate(), Ok(()));
+    assert_eq!(board.to_fen(), fen);
+    let c1 = algebraic_to_square("c1").unwrap();
+    let castle = Move::new(c1, algebraic_to_square("b1").unwrap());
+    assert!(board
+        .generate_all_legal_moves(Color::White)
+        .contains(&castle));
+    let mut castled = board.clone();
+    castled.apply_move(castle);
+    assert!(castled
+        .to_fen()
+        .starts_with("1r1k2r1/pppppppp/8/8/8/8/PPPPPPPP/R1KR2R1 b - -"));
+
+    // Read as the outermost rook, the a1 rook would castle across b1, which is not empty
+    let outer = Board::from_fen(&fen.replacen(" B ", " Q ", 1)).unwrap();
+    assert_eq!(outer.to_fen().split(' ').nth(2), Some("Q"));
+    let legal = outer.generate_all_legal_moves(Color::White);
+    assert!(!legal.contains(&castle));
+    assert!(!legal.contains(&Move::new(c1, algebraic_to_square("a1").unwrap())));
+}
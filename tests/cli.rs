@@ -0,0 +1,61 @@
+// The `--cli` game driven through the binary, with the player's moves piped to its stdin
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+fn play(args: &[&str], input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_chess"))
+        .arg("--cli")
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn plays(stdout: &str, color: &str) -> Vec<String> {
+    let prefix = format!("{} plays ", color);
+    stdout
+        .lines()
+        .filter_map(|line| line.split_once(&prefix).map(|(_, san)| san.to_string()))
+        .collect()
+}
+
+#[test]
+fn plays_and_replies() {
+    let output = play(&["--depth", "2"], "e2e4\ne1e3\nNz9\nquit\n");
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("You play White."));
+    assert_eq!(plays(&stdout, "White"), ["e4"]);
+    assert_eq!(plays(&stdout, "Black").len(), 1, "{}", stdout);
+    // Both bad moves are refused and asked for again, and quit ends the game there
+    assert!(stdout.contains("illegal move 'e1e3'"), "{}", stdout);
+    assert!(stdout.contains("unrecognised move 'Nz9'"), "{}", stdout);
+    assert_eq!(stdout.matches("White to move: ").count(), 4);
+}
+
+// Playing Black, the engine moves first; the input running out ends the game as quit does
+#[test]
+fn engine_moves_first() {
+    let output = play(&["--color", "black", "--depth", "1"], "");
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(plays(&stdout, "White").len(), 1, "{}", stdout);
+    assert!(plays(&stdout, "Black").is_empty());
+}
+
+#[test]
+fn bad_options() {
+    let output = play(&["--depth", "deep"], "");
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("invalid depth 'deep'") && stderr.contains("usage: chess --cli"));
+}
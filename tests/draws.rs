@@ -0,0 +1,86 @@
+// The search scores positions the fifty-move rule or a threefold repetition would draw as
+// draws: the side ahead steers clear of them and the side behind heads for them. Scores are
+// White's.
+use chess::engine::{improved_best_move_for_color, move_to_uci, Board, SearchLimits};
+
+const DEPTH: u32 = 4;
+
+// The move the search picks in `board`, given the keys of the game so far, and its score
+fn best(board: &Board, history: &[u64]) -> (String, i32) {
+    let result = improved_best_move_for_color(
+        board,
+        board.side_to_move,
+        &SearchLimits::depth(DEPTH),
+        history,
+    )
+    .unwrap();
+    (move_to_uci(result.best_move), result.score)
+}
+
+fn fresh(fen: &str) -> (String, i32) {
+    let board = Board::from_fen(fen).unwrap();
+    best(&board, &[board.zobrist_key()])
+}
+
+// With the clock at 99, any move but a pawn move or a capture ends the game drawn, so a rook up
+// White pushes its pawn, which it has no reason to do with the clock at 0
+#[test]
+fn fifty_move_rule() {
+    let (m, score) = fresh("4k3/8/8/8/8/8/P7/R3K3 w - - 99 80");
+    assert!(m.starts_with("a2"), "{}", m);
+    assert!(score >= 500, "{}", score);
+    let (m, _) = fresh("4k3/8/8/8/8/8/P7/R3K3 w - - 0 80");
+    assert!(!m.starts_with("a2"), "{}", m);
+
+    // Without a pawn to push or anything to take, every move draws
+    let (_, score) = fresh("4k3/8/8/8/8/8/8/R3K3 w - - 99 80");
+    assert_eq!(score, 0);
+}
+
+// Plays `moves` from `fen`, returning the board and the keys of every position on the way
+fn play(fen: &str, moves: &[&str]) -> (Board, Vec<u64>) {
+    let mut board = Board::from_fen(fen).unwrap();
+    let mut history = vec![board.zobrist_key()];
+    for text in moves {
+        let m = board.parse_uci_move(text).unwrap();
+        board.apply_move(m);
+        history.push(board.zobrist_key());
+    }
+    (board, history)
+}
+
+const ROOK_UP: &str = "8/8/4k3/8/8/8/1R6/4K3 w - - 0 1";
+const CYCLE: [&str; 4] = ["b2e2", "e6d6", "e2b2", "d6e6"];
+
+// Re2+ is White's move here, until the checks have gone round twice and it would repeat the
+// position after it a third time
+#[test]
+fn threefold_repetition_avoided() {
+    assert_eq!(fresh(ROOK_UP).0, "b2e2");
+    let (board, history) = play(ROOK_UP, &[CYCLE, CYCLE].concat());
+    assert_eq!(board.to_fen().split(' ').next(), ROOK_UP.split(' ').next());
+    // The history starts after Re2+, so the position is on the board for the second time only
+    let history = &history[1..];
+    assert!(!board.is_threefold_repetition(history));
+    let mut repeated = board.clone();
+    repeated.apply_move(board.parse_uci_move("b2e2").unwrap());
+    let mut with_repeat = history.to_vec();
+    with_repeat.push(repeated.zobrist_key());
+    assert!(repeated.is_threefold_repetition(&with_repeat));
+
+    let (m, score) = best(&board, history);
+    assert_ne!(m, "b2e2");
+    assert!(score >= 500, "{}", score);
+    // Without the game's history the same position gives the check again
+    assert_eq!(best(&board, &[board.zobrist_key()]).0, "b2e2");
+}
+
+// A rook down, Black repeats the position a third time rather than step aside
+#[test]
+fn threefold_repetition_sought() {
+    let (board, history) = play(ROOK_UP, &[&CYCLE[..], &CYCLE[..3]].concat());
+    assert_eq!(best(&board, &history), ("d6e6".to_string(), 0));
+    let (m, score) = best(&board, &[board.zobrist_key()]);
+    assert_ne!(m, "d6e6");
+    assert!(score >= 500, "{}", score);
+}
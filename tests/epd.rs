@@ -0,0 +1,67 @@
+// The EPD test suite runner, and EPD records read and written
+use chess::engine::SearchLimits;
+use chess::epd::{run_epd_suite, Epd, DEFAULT_DEPTH};
+use std::path::Path;
+
+const MINI_SUITE: &str = "tests/data/mini_suite.epd";
+
+// At the default depth the engine solves 17 of the 20 positions; fewer is a regression in the
+// search
+#[test]
+fn mini_suite() {
+    let report = run_epd_suite(
+        Path::new(MINI_SUITE),
+        &SearchLimits::depth(DEFAULT_DEPTH),
+        None,
+    )
+    .unwrap();
+    assert_eq!(report.positions.len(), 20);
+    assert!(report.passed() >= 17, "{}", report);
+}
+
+// Writing a record and reading it back gives the same position and operations
+fn assert_round_trip(line: &str) {
+    let epd = Epd::parse_line(line).unwrap();
+    let written = epd.to_string();
+    let read = Epd::parse_line(&written).unwrap();
+    assert_eq!(read.board.to_fen(), epd.board.to_fen(), "{}", written);
+    assert_eq!(read.best_moves, epd.best_moves, "{}", written);
+    assert_eq!(read.avoid_moves, epd.avoid_moves, "{}", written);
+    assert_eq!(read.centipawn_eval, epd.centipawn_eval, "{}", written);
+    assert_eq!(read.id, epd.id, "{}", written);
+}
+
+#[test]
+fn mini_suite_round_trips() {
+    let text = std::fs::read_to_string(MINI_SUITE).unwrap();
+    let lines: Vec<&str> = text
+        .lines()
+        .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+        .collect();
+    assert_eq!(lines.len(), 20);
+    for line in lines {
+        assert_round_trip(line);
+    }
+}
+
+#[test]
+fn operations_round_trip() {
+    for line in [
+        // Avoid moves and a score alongside the best moves
+        "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - bm Bb5 Bc4; am Qe2; \
+         ce 35; id \"opening\";",
+        "4k3/8/8/8/8/8/8/4K2R w K - am Rh8+; ce -12;",
+        // A semicolon inside the quoted id does not end the operation
+        "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id \"WAC.001; mate\";",
+        // Moves written in coordinates are read, and written back in SAN
+        "4k3/P7/8/8/8/8/8/4K3 w - - bm a7a8q; id \"promotion\";",
+    ] {
+        assert_round_trip(line);
+    }
+    let epd = Epd::parse_line(
+        "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id \"WAC.001; mate\";",
+    )
+    .unwrap();
+    assert_eq!(epd.id.as_deref(), Some("WAC.001; mate"));
+    assert_eq!(epd.best_moves.len(), 1);
+}
@@ -0,0 +1,48 @@
+// Which material `Board::is_insufficient_material` calls a dead draw, and which it leaves on
+use chess::engine::Board;
+
+fn insufficient(fen: &str) -> bool {
+    Board::from_fen(fen).unwrap().is_insufficient_material()
+}
+
+#[test]
+fn bare_kings() {
+    assert!(insufficient("4k3/8/8/8/8/8/8/4K3 w - - 0 1"));
+}
+
+#[test]
+fn lone_knight() {
+    assert!(insufficient("4k3/8/8/8/8/8/8/1N2K3 w - - 0 1"));
+    assert!(insufficient("1n2k3/8/8/8/8/8/8/4K3 b - - 0 1"));
+}
+
+#[test]
+fn lone_bishop() {
+    assert!(insufficient("4k3/8/8/8/8/8/8/2B1K3 w - - 0 1"));
+    assert!(insufficient("2b1k3/8/8/8/8/8/8/4K3 w - - 0 1"));
+}
+
+// c1 and f8 are dark squares, c8 and f1 light ones
+#[test]
+fn bishops_on_one_colour() {
+    assert!(insufficient("4kb2/8/8/8/8/8/8/2B1K3 w - - 0 1"));
+    assert!(insufficient("4k3/8/8/8/8/8/8/B1B1K3 w - - 0 1"));
+    assert!(!insufficient("2b1k3/8/8/8/8/8/8/2B1K3 w - - 0 1"));
+    assert!(!insufficient("4k3/8/8/8/8/8/8/2B1KB2 w - - 0 1"));
+}
+
+#[test]
+fn two_knights() {
+    assert!(insufficient("4k3/8/8/8/8/8/8/1N2K1N1 w - - 0 1"));
+    // A third knight, or one on each side, can still mate
+    assert!(!insufficient("4k3/8/8/8/8/8/8/1NN1K1N1 w - - 0 1"));
+    assert!(!insufficient("1n2k3/8/8/8/8/8/8/1N2K3 w - - 0 1"));
+}
+
+#[test]
+fn mating_material() {
+    assert!(!insufficient("4k3/8/8/8/8/8/8/1NB1K3 w - - 0 1"));
+    assert!(!insufficient("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1"));
+    assert!(!insufficient("4k3/8/8/8/8/8/8/R3K3 w - - 0 1"));
+    assert!(!insufficient("4k3/8/8/8/8/8/8/3QK3 w - - 0 1"));
+}
@@ -0,0 +1,44 @@
+// Mates the search finds, and how deep it has to look for them
+use chess::engine::{
+    improved_best_move_for_color, move_to_uci, Board, SearchLimits, SearchResult, MATE_VALUE,
+};
+
+fn search(fen: &str, limits: &SearchLimits) -> SearchResult {
+    let board = Board::from_fen(fen).unwrap();
+    improved_best_move_for_color(&board, board.side_to_move, limits, &[board.zobrist_key()])
+        .unwrap()
+}
+
+// Nh6+ Kh8 Qg8+ Rxg8 Nf7#, every White move a check. The mated position is five plies away,
+// beyond a depth 4 search unless the checks extend it.
+#[test]
+fn extensions_find_mate_in_three() {
+    let fen = "r5k1/5Npp/8/8/8/1Q6/6PP/6K1 w - - 0 1";
+    let extended = search(fen, &SearchLimits::depth(4));
+    assert_eq!(move_to_uci(extended.best_move), "f7h6");
+    assert_eq!(extended.score, MATE_VALUE - 5);
+
+    let unextended = |depth| SearchLimits {
+        max_extensions: Some(0),
+        ..SearchLimits::depth(depth)
+    };
+    assert!(search(fen, &unextended(4)).score < MATE_VALUE - 5);
+    // Without extensions it takes the full six plies
+    let result = search(fen, &unextended(6));
+    assert_eq!(move_to_uci(result.best_move), "f7h6");
+    assert_eq!(result.score, MATE_VALUE - 5);
+}
+
+// Qd8+ Bxd8 Re8#. Mate-distance pruning cuts lines that cannot mate sooner than one already
+// found, so each depth reports the same mate, three plies away, and the move that starts it.
+#[test]
+fn mate_in_two_at_every_depth() {
+    let fen = "r1b2k1r/ppp1bppp/8/1B1Q4/5q2/2P5/PPP2PPP/R3R1K1 w - - 1 1";
+    for depth in 3..=6 {
+        let result = search(fen, &SearchLimits::depth(depth));
+        assert_eq!(move_to_uci(result.best_move), "d5d8", "depth {}", depth);
+        assert_eq!(result.score, MATE_VALUE - 3, "depth {}", depth);
+        let pv: Vec<String> = result.pv.iter().map(|&m| move_to_uci(m)).collect();
+        assert_eq!(pv, ["d5d8", "e7d8", "e1e8"], "depth {}", depth);
+    }
+}
@@ -0,0 +1,73 @@
+// The half-move clock and the full-move number, kept by `apply_move` and read and written in FEN
+use chess::engine::Board;
+
+fn play(fen: &str, moves: &[&str]) -> Board {
+    let mut board = Board::from_fen(fen).unwrap();
+    for text in moves {
+        let m = board.parse_uci_move(text).unwrap();
+        board.apply_move(m);
+    }
+    board
+}
+
+// Every pawn move and every capture restarts the clock, en passant being both
+#[test]
+fn clock_restarts_on_pawn_moves_and_captures() {
+    let board = play("4k3/8/8/3pP3/8/8/8/4K3 w - d6 37 60", &["e5d6"]);
+    assert_eq!(board.half_move_clock, 0);
+    // The pawn taken en passant is gone from d5
+    assert!(board.piece_at(4, 3).is_none());
+    assert_eq!(board.to_fen(), "4k3/8/3P4/8/8/8/8/4K3 b - - 0 60");
+    // And for Black, from a double step just played
+    let board = play("4k3/8/8/8/3p4/8/4P3/4K3 w - - 12 40", &["e2e4", "d4e3"]);
+    assert_eq!(board.half_move_clock, 0);
+    assert!(board.piece_at(3, 4).is_none());
+
+    // Other moves count up, and pushes and captures start over
+    let board = play(
+        "4k3/8/8/8/8/8/4P3/R3K3 w - - 5 30",
+        &["a1a2", "e8d8", "a2a3"],
+    );
+    assert_eq!(board.half_move_clock, 8);
+    let board = play(
+        "4k3/8/8/8/8/8/4P3/R3K3 w - - 5 30",
+        &["a1a2", "e8d8", "e2e3"],
+    );
+    assert_eq!(board.half_move_clock, 0);
+    let board = play("3rk3/8/8/8/8/8/8/R2RK3 w - - 5 30", &["d1d8", "e8d8"]);
+    assert_eq!(board.half_move_clock, 0);
+}
+
+// The full-move number goes up after each Black move, whoever moved first, and FEN keeps both
+// counters
+#[test]
+fn full_move_number() {
+    let mut board = Board::new();
+    assert_eq!((board.full_move_number, board.half_moves_played()), (1, 0));
+    for (played, text) in (1..).zip(["e2e4", "e7e5", "g1f3", "b8c6", "f1b5"]) {
+        let m = board.parse_uci_move(text).unwrap();
+        board.apply_move(m);
+        assert_eq!(board.half_moves_played(), played);
+        assert_eq!(board.full_move_number, played / 2 + 1);
+    }
+    assert_eq!(
+        board.to_fen(),
+        "r1bqkbnr/pppp1ppp/2n5/1B2p3/4P3/5N2/PPPP1PPP/RNBQK2R b KQkq - 3 3"
+    );
+
+    // From a position with Black to move
+    let board = play("4k3/8/8/8/8/8/8/4K2R b K - 9 41", &["e8d7", "h1h7"]);
+    assert_eq!(board.full_move_number, 42);
+    assert_eq!(board.half_moves_played(), 83);
+
+    for fen in [
+        "4k3/8/8/8/8/8/8/4K2R b K - 9 41",
+        "r1bqkbnr/pppp1ppp/2n5/1B2p3/4P3/5N2/PPPP1PPP/RNBQK2R b KQkq - 3 3",
+        "8/8/4k3/8/8/4K3/8/8 w - - 99 250",
+    ] {
+        assert_eq!(Board::from_fen(fen).unwrap().to_fen(), fen);
+    }
+    // The counters may be left off, as in EPD, for a fresh clock at move one
+    let board = Board::from_fen("8/8/4k3/8/8/4K3/8/8 w - -").unwrap();
+    assert_eq!((board.half_move_clock, board.full_move_number), (0, 1));
+}
@@ -0,0 +1,108 @@
+// The experimental network evaluation on the tiny network in tests/data: 768 inputs, 8 hidden
+// and one output, written by `NnueEvaluator::random(&[8], 344).save(...)`
+#![cfg(feature = "nnue")]
+use chess::engine::nnue::{self, NnueEvaluator};
+use chess::engine::{evaluate_position, move_to_uci, Board, GameStatus, PositionConstraints};
+use rand::rngs::StdRng;
+use rand::seq::IndexedRandom;
+use rand::SeedableRng;
+use std::path::Path;
+use std::sync::Once;
+
+// The network every test evaluates with, set before any of them makes a board so that every
+// board keeps an accumulator
+fn network() -> &'static NnueEvaluator {
+    static SET: Once = Once::new();
+    SET.call_once(|| {
+        let network = NnueEvaluator::new(Path::new("tests/data/tiny.nnue")).unwrap();
+        assert!(nnue::set_network(network));
+    });
+    nnue::network().unwrap()
+}
+
+fn assert_accumulator_fresh(network: &NnueEvaluator, board: &Board, context: &str) {
+    let fresh = network.accumulate(board);
+    assert_eq!(board.accumulator().len(), fresh.len(), "{}", context);
+    for (kept, fresh) in board.accumulator().iter().zip(&fresh) {
+        assert!(
+            (kept - fresh).abs() < 1e-3,
+            "{} against {} {}",
+            kept,
+            fresh,
+            context
+        );
+    }
+    let (kept, fresh) = (
+        network.evaluate(board),
+        network.evaluate_from_scratch(board),
+    );
+    assert!(
+        (kept - fresh).abs() <= 1,
+        "{} against {} {}",
+        kept,
+        fresh,
+        context
+    );
+}
+
+#[test]
+fn reads_the_saved_network() {
+    let network = network();
+    let generated = NnueEvaluator::random(&[8], 344);
+    let board = Board::new();
+    assert_eq!(
+        network.evaluate_from_scratch(&board),
+        generated.evaluate_from_scratch(&board)
+    );
+    assert_eq!(evaluate_position(&board), network.evaluate(&board));
+}
+
+#[test]
+fn tells_positions_apart() {
+    let network = network();
+    let scores: Vec<i32> = [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "4k3/8/8/8/8/8/8/3QK3 w - - 0 1",
+        "3qk3/8/8/8/8/8/8/4K3 w - - 0 1",
+        "r3k2r/pppq1ppp/2np1n2/2b1p3/2B1P3/2NP1N2/PPPQ1PPP/R3K2R w KQkq - 4 8",
+    ]
+    .iter()
+    .map(|fen| network.evaluate(&Board::from_fen(fen).unwrap()))
+    .collect();
+    // Random weights play no chess, but they do tell positions apart
+    assert!(
+        scores.iter().any(|&score| score != scores[0]),
+        "{:?}",
+        scores
+    );
+}
+
+// The accumulator `apply_move` updates piece by piece stays equal to one summed afresh
+#[test]
+fn accumulator_follows_the_moves() {
+    let network = network();
+    let mut rng = StdRng::seed_from_u64(344);
+    let starts = std::iter::once(Board::new())
+        .chain((0..10).map(|_| Board::random_position(&mut rng, &PositionConstraints::default())));
+    for start in starts.collect::<Vec<_>>() {
+        let mut board = start.clone();
+        let mut played = Vec::new();
+        for _ in 0..100 {
+            let context = format!("from {} after {:?}", start.to_fen(), played);
+            assert_accumulator_fresh(network, &board, &context);
+            let color = board.side_to_move;
+            if matches!(
+                board.game_status(color),
+                GameStatus::Checkmate | GameStatus::Stalemate
+            ) {
+                break;
+            }
+            let &m = board
+                .generate_all_legal_moves(color)
+                .choose(&mut rng)
+                .unwrap();
+            board.apply_move(m);
+            played.push(move_to_uci(m));
+        }
+    }
+}
@@ -0,0 +1,74 @@
+// Perft counts from the Chess Programming Wiki ("Perft Results" and "Chess960 Perft Results"):
+// the number of leaf positions a given depth ahead, which any move generator bug changes
+use chess::engine::Board;
+
+fn assert_perft(fen: &str, expected: &[u64]) {
+    let board = Board::from_fen(fen).unwrap();
+    for (depth, &count) in (1..).zip(expected) {
+        assert_eq!(board.perft(depth), count, "perft({}) of {}", depth, fen);
+    }
+}
+
+#[test]
+fn start_position() {
+    assert_perft(
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        &[20, 400, 8_902, 197_281],
+    );
+}
+
+// Castling both ways for both sides, en passant, pins and promotions
+#[test]
+fn kiwipete() {
+    assert_perft(
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        &[48, 2_039, 97_862],
+    );
+}
+
+// Rook and pawn endgame with en passant captures that expose the king
+#[test]
+fn position_3() {
+    assert_perft(
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        &[14, 191, 2_812, 43_238, 674_624],
+    );
+}
+
+// Promotions to every piece, with captures, out of check
+#[test]
+fn position_4() {
+    assert_perft(
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        &[6, 264, 9_467, 422_333],
+    );
+}
+
+#[test]
+fn position_5() {
+    assert_perft(
+        "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        &[44, 1_486, 62_379],
+    );
+}
+
+#[test]
+fn position_6() {
+    assert_perft(
+        "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+        &[46, 2_079, 89_890],
+    );
+}
+
+// Chess960 castling rights are given by the rooks' files
+#[test]
+fn chess960_positions() {
+    assert_perft(
+        "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+        &[21, 528, 12_189, 326_672],
+    );
+    assert_perft(
+        "2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9",
+        &[21, 807, 18_002, 667_366],
+    );
+}
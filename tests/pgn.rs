@@ -0,0 +1,245 @@
+// Reading PGN games with `Game::from_pgn`
+use chess::engine::{algebraic_to_square, Color, GameStatus, Move, PieceType};
+use chess::game::{Game, Nag, PgnParseError};
+
+fn square_move(from: &str, to: &str) -> Move {
+    Move::new(
+        algebraic_to_square(from).unwrap(),
+        algebraic_to_square(to).unwrap(),
+    )
+}
+
+// Castling may be written with zeros, which are not a move number
+#[test]
+fn castling_with_zeros() {
+    let game = Game::from_pgn("1. e4 e5 2. Nf3 Nc6 3. Bc4 Bc5 4. 0-0 Nf6 *").unwrap();
+    assert_eq!(game.moves[6].mv, square_move("e1", "g1"));
+    assert_eq!(
+        game,
+        Game::from_pgn("1. e4 e5 2. Nf3 Nc6 3. Bc4 Bc5 4. O-O Nf6 *").unwrap()
+    );
+
+    let game =
+        Game::from_pgn("1. d4 d5 2. Nc3 Nc6 3. Bf4 Bf5 4. Qd2 Qd7 5. 0-0-0 0-0-0 *").unwrap();
+    assert_eq!(game.moves[8].mv, square_move("e1", "c1"));
+    assert_eq!(game.moves[9].mv, square_move("e8", "c8"));
+    // Run together with the move number, too
+    let game = Game::from_pgn("1.d4 d5 2.Nc3 Nc6 3.Bf4 Bf5 4.Qd2 Qd7 5.0-0-0 *").unwrap();
+    assert_eq!(game.moves[8].mv, square_move("e1", "c1"));
+}
+
+// Morphy against the Duke of Brunswick and Count Isouard, Paris 1858
+const OPERA_GAME: &str = r#"[Event "Paris"]
+[Site "Paris FRA"]
+[Date "1858.??.??"]
+[Round "?"]
+[White "Paul Morphy"]
+[Black "Duke Karl / Count Isouard"]
+[Result "1-0"]
+[ECO "C41"]
+
+1.e4 e5 2.Nf3 d6 3.d4 Bg4 4.dxe5 Bxf3 5.Qxf3 dxe5 6.Bc4 Nf6 7.Qb3 Qe7 8.Nc3 c6
+9.Bg5 b5 10.Nxb5 cxb5 11.Bxb5+ Nbd7 12.O-O-O Rd8 13.Rxd7 Rxd7 14.Rd1 Qe6
+15.Bxd7+ Nxd7 16.Qb8+ Nxb8 17.Rd8# 1-0
+"#;
+
+#[test]
+fn tag_pairs() {
+    let game = Game::from_pgn(OPERA_GAME).unwrap();
+    let metadata = &game.metadata;
+    assert_eq!(metadata.event, "Paris");
+    assert_eq!(metadata.site, "Paris FRA");
+    assert_eq!(metadata.date, "1858.??.??");
+    // "?" is the PGN unknown value
+    assert_eq!(metadata.round, "");
+    assert_eq!(metadata.white, "Paul Morphy");
+    assert_eq!(metadata.black, "Duke Karl / Count Isouard");
+    assert_eq!(metadata.result, "1-0");
+    assert_eq!(metadata.extra_tags["ECO"], "C41");
+    assert_eq!(game.moves.len(), 33);
+    assert_eq!(
+        game.board_after(33).game_status(Color::Black),
+        GameStatus::Checkmate
+    );
+
+    // Quotes and backslashes escaped in a value
+    let game = Game::from_pgn("[White \"O\\\"Kelly \\\\ Jr\"]\n\n1. e4 *").unwrap();
+    assert_eq!(game.metadata.white, "O\"Kelly \\ Jr");
+}
+
+#[test]
+fn comments() {
+    let game = Game::from_pgn(
+        "1. e4 {Best by test} e5 {A comment with (parentheses), $1 and\n   a ; semicolon}\n\
+         2. Nf3 {first} {second} *",
+    )
+    .unwrap();
+    assert_eq!(game.moves.len(), 3);
+    assert_eq!(game.moves[0].annotation.as_deref(), Some("Best by test"));
+    assert_eq!(
+        game.moves[1].annotation.as_deref(),
+        Some("A comment with (parentheses), $1 and a ; semicolon")
+    );
+    assert!(game.moves[1].variations.is_empty() && game.moves[1].nags.is_empty());
+    assert_eq!(game.moves[2].annotation.as_deref(), Some("first second"));
+
+    // Comments inside variations inside variations. A comment runs to the first closing brace,
+    // so an opening one inside it is only text
+    let game = Game::from_pgn(
+        "1. e4 e5 (1... c5 {Sicilian} 2. Nf3 (2. Nc3 {closed { really} Nc6) d6) 2. Nf3 *",
+    )
+    .unwrap();
+    let sicilian = &game.moves[1].variations[0];
+    assert_eq!(sicilian[0].annotation.as_deref(), Some("Sicilian"));
+    assert_eq!(
+        sicilian[1].variations[0][0].annotation.as_deref(),
+        Some("closed { really")
+    );
+    // A comment before the first move has no move to go with
+    let game = Game::from_pgn("{Annotated by someone} 1. e4 *").unwrap();
+    assert_eq!(game.moves[0].annotation, None);
+    // One never closed is an error, as a variation never closed is
+    assert_eq!(
+        Game::from_pgn("1. e4 {unfinished e5 *"),
+        Err(PgnParseError::UnrecognisedMove("{".to_string()))
+    );
+}
+
+#[test]
+fn recursive_variations() {
+    let game =
+        Game::from_pgn("1. e4 e5 (1... c5 2. Nf3 (2. Nc3 Nc6 (2... e6)) d6) (1... e6) 2. Nf3 *")
+            .unwrap();
+    assert_eq!(game.moves.len(), 3);
+    let variations = &game.moves[1].variations;
+    assert_eq!(variations.len(), 2);
+    let sicilian = &variations[0];
+    assert_eq!(sicilian.len(), 3);
+    assert_eq!(sicilian[0].mv, square_move("c7", "c5"));
+    assert_eq!(sicilian[2].mv, square_move("d7", "d6"));
+    // Each variation starts from the position before the move it replaces
+    let closed = &sicilian[1].variations[0];
+    assert_eq!(closed[0].mv, square_move("b1", "c3"));
+    assert_eq!(closed[1].variations[0][0].mv, square_move("e7", "e6"));
+    assert_eq!(variations[1][0].mv, square_move("e7", "e6"));
+    assert_eq!(game.moves[2].mv, square_move("g1", "f3"));
+}
+
+#[test]
+fn glyphs() {
+    let game = Game::from_pgn("1. e4 $1 e5 $2 $14 2. Nf3!? Nc6?? 3. Bb5 $146 *").unwrap();
+    assert_eq!(game.moves[0].nags, [1]);
+    assert_eq!(game.moves[0].nag(), Some(Nag::Good));
+    // A position assessment is kept after the move-quality glyph
+    assert_eq!(game.moves[1].nags, [2, 14]);
+    assert_eq!(game.moves[1].nag(), Some(Nag::Mistake));
+    assert_eq!(game.moves[2].nag(), Some(Nag::Interesting));
+    assert_eq!(game.moves[3].nag(), Some(Nag::Blunder));
+    assert_eq!(game.moves[4].nags, [146]);
+    assert_eq!(game.moves[4].nag(), None);
+}
+
+#[test]
+fn set_up_position() {
+    let fen = "8/P5k1/8/8/8/8/6K1/8 w - - 0 40";
+    let pgn = format!(
+        "[SetUp \"1\"]\n[FEN \"{}\"]\n\n40. a8=Q Kf6 41. Qf8+ Ke6 42. Kf3 *",
+        fen
+    );
+    let game = Game::from_pgn(&pgn).unwrap();
+    assert_eq!(game.start_position().to_fen(), fen);
+    assert_eq!(
+        game.moves[0].mv,
+        Move::promoting(
+            algebraic_to_square("a7").unwrap(),
+            algebraic_to_square("a8").unwrap(),
+            PieceType::Queen
+        )
+    );
+    assert_eq!(game.moves.len(), 5);
+    // Black to move first, numbered "1..."
+    let game = Game::from_pgn(
+        "[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/4P3/4K3 b - - 0 1\"]\n\n1... Kd7 2. e4 *",
+    )
+    .unwrap();
+    assert_eq!(game.moves[0].mv, square_move("e8", "d7"));
+    // And written back with both tags
+    let pgn = game.to_pgn();
+    assert!(pgn.contains("[SetUp \"1\"]") && pgn.contains("[FEN \"4k3/8/8/8/8/8/4P3/4K3 b"));
+    assert_eq!(Game::from_pgn(&pgn).unwrap(), game);
+}
+
+#[test]
+fn several_games() {
+    let pgn = format!("{}\n{}", OPERA_GAME, "[Event \"Next\"]\n\n1. d4 d5 *\n");
+    let games = Game::all_from_pgn(&pgn);
+    assert_eq!(games.len(), 2);
+    assert_eq!(games[1].as_ref().unwrap().moves.len(), 2);
+}
+
+#[test]
+fn malformed_input() {
+    let cases: &[(&str, PgnParseError)] = &[
+        (
+            "[Event \"Unclosed\"\n\n1. e4 *",
+            PgnParseError::MalformedTag("[Event \"Unclosed\"".to_string()),
+        ),
+        (
+            "[Event Unquoted]\n\n1. e4 *",
+            PgnParseError::MalformedTag("[Event Unquoted]".to_string()),
+        ),
+        ("1. e5 *", PgnParseError::IllegalMove("1. e5".to_string())),
+        (
+            "1. e4 e5 2. Ke3 *",
+            PgnParseError::IllegalMove("2. Ke3".to_string()),
+        ),
+        (
+            "1. e4 Zz9 *",
+            PgnParseError::UnrecognisedMove("1... Zz9".to_string()),
+        ),
+        (
+            "[FEN \"4k3/8/8/8/8/8/8/N1N1K3 w - - 0 1\"]\n\n1. Nb3 *",
+            PgnParseError::AmbiguousMove("1. Nb3".to_string()),
+        ),
+        (
+            "1. e4 e5 ) *",
+            PgnParseError::UnrecognisedMove(")".to_string()),
+        ),
+        (
+            "1. e4 e5 (1... c5 *",
+            PgnParseError::UnrecognisedMove("(".to_string()),
+        ),
+        (
+            "( 1. e4 ) *",
+            PgnParseError::UnrecognisedMove("(".to_string()),
+        ),
+    ];
+    for (pgn, error) in cases {
+        assert_eq!(Game::from_pgn(pgn).as_ref(), Err(error), "{:?}", pgn);
+    }
+    assert!(matches!(
+        Game::from_pgn("[FEN \"not a position\"]\n\n1. e4 *"),
+        Err(PgnParseError::InvalidFen(_))
+    ));
+}
+
+// Garbage made of PGN's own characters is refused or read, but never panics
+#[test]
+fn random_garbage() {
+    const ALPHABET: &[u8] = b"[]{}()\";$!?.-=+#/*0123456789abcdefghKQRBNOx \n";
+    let mut state = 0x2545_f491_4f6c_dd1d_u64;
+    for _ in 0..2000 {
+        let len = (state % 60) as usize;
+        let mut text = String::new();
+        for _ in 0..len {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            text.push(ALPHABET[(state % ALPHABET.len() as u64) as usize] as char);
+        }
+        let _ = Game::from_pgn(&text);
+        let _ = Game::all_from_pgn(&text);
+        // Appended to a real game, too
+        let _ = Game::from_pgn(&format!("1. e4 e5 2. Nf3 {}", text));
+    }
+}
@@ -0,0 +1,111 @@
+// Invariants of the board checked at every ply of seeded random playouts, from the start
+// position and from `Board::random_position`. A failure names the seed, the position the
+// playout started from and the moves played, which is enough to replay it.
+use chess::engine::{move_to_uci, Board, Color, GameStatus, Piece, PieceType, PositionConstraints};
+use rand::rngs::StdRng;
+use rand::seq::IndexedRandom;
+use rand::SeedableRng;
+
+const PLIES: usize = 80;
+
+fn check_invariants(board: &Board, context: &dyn Fn() -> String) {
+    // Exactly one king a side
+    for color in [Color::White, Color::Black] {
+        let kings = board
+            .bitboard(Piece {
+                color,
+                kind: PieceType::King,
+            })
+            .count_ones();
+        assert_eq!(kings, 1, "{:?} has {} kings\n{}", color, kings, context());
+    }
+
+    // The legal moves are exactly the (from, to) pairs `validate_move` accepts, and none of
+    // them takes a king
+    let color = board.side_to_move;
+    let legal = board.generate_all_legal_moves(color);
+    for m in &legal {
+        assert!(
+            board.piece_at(m.to.0, m.to.1).map(|piece| piece.kind) != Some(PieceType::King),
+            "{} takes a king\n{}",
+            move_to_uci(*m),
+            context()
+        );
+    }
+    for from in (0..64).map(|square| (square / 8, square % 8)) {
+        for to in (0..64).map(|square| (square / 8, square % 8)) {
+            let generated = legal.iter().any(|m| m.from == from && m.to == to);
+            let valid = board.validate_move(from, to);
+            assert_eq!(
+                generated,
+                valid.is_ok(),
+                "generated {} but validate_move says {:?} for {:?} to {:?}\n{}",
+                generated,
+                valid,
+                from,
+                to,
+                context()
+            );
+        }
+    }
+
+    // The key of the position `apply_move` reached is the key of the same position read
+    // afresh from its FEN, so no castling right or en passant square was left stale
+    let fen = board.to_fen();
+    let fresh = Board::from_fen(&fen).unwrap();
+    assert_eq!(
+        board.zobrist_key(),
+        fresh.zobrist_key(),
+        "key differs from a fresh {}\n{}",
+        fen,
+        context()
+    );
+}
+
+fn play_out(seed: u64, start: Board) {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut board = start.clone();
+    let mut played: Vec<String> = Vec::new();
+    for _ in 0..PLIES {
+        let context = || {
+            format!(
+                "seed {}, from {}, after {}",
+                seed,
+                start.to_fen(),
+                played.join(" ")
+            )
+        };
+        check_invariants(&board, &context);
+        let color = board.side_to_move;
+        if matches!(
+            board.game_status(color),
+            GameStatus::Checkmate | GameStatus::Stalemate
+        ) {
+            break;
+        }
+        let legal = board.generate_all_legal_moves(color);
+        let &m = legal.choose(&mut rng).unwrap();
+        board.apply_move(m);
+        played.push(move_to_uci(m));
+    }
+}
+
+#[test]
+fn from_the_start_position() {
+    for seed in 0..20 {
+        play_out(seed, Board::new());
+    }
+}
+
+#[test]
+fn from_random_positions() {
+    for seed in 0..40 {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let constraints = PositionConstraints {
+            pieces: 4 + seed as usize % 20,
+            ..PositionConstraints::default()
+        };
+        let start = Board::random_position(&mut rng, &constraints);
+        play_out(seed, start);
+    }
+}
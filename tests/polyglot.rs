@@ -0,0 +1,160 @@
+// Polyglot opening books: keys checked against the format specification's own examples, and
+// `tests/data/tiny_book.bin`, a book of a few hand-made entries:
+//   the start position    e2e4 (weight 3), d2d4 (1)
+//   after 1. e4           e7e5 (2), c7c5 (1), a7a6 (0)
+//   an Italian Game       e1h1, White castling short (5), e1e3, which is illegal (1)
+//   8/P6k/8/8/8/8/8/K7 w  a7a8 to a queen (2) and to a knight (1)
+use chess::engine::book::Book;
+use chess::engine::polyglot::polyglot_key;
+use chess::engine::{improved_best_move_for_color, move_to_uci, Board, SearchLimits};
+use chess::uci;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+const TINY_BOOK: &str = "tests/data/tiny_book.bin";
+const ITALIAN: &str = "r1bqk1nr/pppp1ppp/2n5/2b1p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4";
+const PROMOTION: &str = "8/P6k/8/8/8/8/8/K7 w - - 0 1";
+
+fn play(moves: &str) -> Board {
+    let mut board = Board::new();
+    for text in moves.split_whitespace() {
+        let m = board.parse_uci_move(text).unwrap();
+        board.apply_move(m);
+    }
+    board
+}
+
+fn book_moves(book: &Book, board: &Board) -> Vec<(String, u32)> {
+    book.moves(board)
+        .into_iter()
+        .map(|(m, weight)| (move_to_uci(m), weight))
+        .collect()
+}
+
+// The keys the specification gives after each of these lines. They take in moves of pawns,
+// kings and a rook, lost castling rights and the en passant files that count.
+#[test]
+fn reference_keys() {
+    for (moves, key) in [
+        ("", 0x463b96181691fc9c),
+        ("e2e4", 0x823c9b50fd114196),
+        ("e2e4 d7d5", 0x0756b94461c50fb0),
+        ("e2e4 d7d5 e4e5", 0x662fafb965db29d4),
+        ("e2e4 d7d5 e4e5 f7f5", 0x22a48b5a8e47ff78),
+        ("e2e4 d7d5 e4e5 f7f5 e1e2", 0x652a607ca3f242c1),
+        ("e2e4 d7d5 e4e5 f7f5 e1e2 e8f7", 0x00fdd303c946bdd9),
+        ("a2a4 b7b5 h2h4 b5b4 c2c4", 0x3c8123ea7b067637),
+        ("a2a4 b7b5 h2h4 b5b4 c2c4 b4c3 a1a3", 0x5c3f9b829b279560),
+    ] {
+        let board = play(moves);
+        assert_eq!(polyglot_key(&board), key, "after {:?}", moves);
+        // And from the position's FEN, with its en passant square whether it counts or not
+        let read = Board::from_fen(&board.to_fen()).unwrap();
+        assert_eq!(polyglot_key(&read), key, "{}", board.to_fen());
+    }
+}
+
+#[test]
+fn reads_the_tiny_book() {
+    let book = Book::open(Path::new(TINY_BOOK)).unwrap();
+    assert_eq!(
+        book_moves(&book, &Board::new()),
+        [("e2e4".to_string(), 3), ("d2d4".to_string(), 1)]
+    );
+    assert_eq!(
+        book_moves(&book, &play("e2e4")),
+        [
+            ("e7e5".to_string(), 2),
+            ("c7c5".to_string(), 1),
+            ("a7a6".to_string(), 0)
+        ]
+    );
+    // Castling is read as the engine writes it, and the illegal entry is left out
+    let italian = Board::from_fen(ITALIAN).unwrap();
+    assert_eq!(book_moves(&book, &italian), [("e1g1".to_string(), 5)]);
+    assert_eq!(
+        book_moves(&book, &Board::from_fen(PROMOTION).unwrap()),
+        [("a7a8q".to_string(), 2), ("a7a8n".to_string(), 1)]
+    );
+    // Out of the book
+    assert!(book.moves(&play("d2d4")).is_empty());
+    assert!(book
+        .probe(&play("d2d4"), &mut StdRng::seed_from_u64(0))
+        .is_none());
+}
+
+// Each move comes up about as often as its weight says, never with a weight of 0, and the same
+// seed picks the same move
+#[test]
+fn picks_by_weight() {
+    let book = Book::open(Path::new(TINY_BOOK)).unwrap();
+    let after_e4 = play("e2e4");
+    let mut counts: HashMap<String, u32> = HashMap::new();
+    for seed in 0..600 {
+        let m = book
+            .probe(&after_e4, &mut StdRng::seed_from_u64(seed))
+            .unwrap();
+        *counts.entry(move_to_uci(m)).or_default() += 1;
+        let again = book.probe(&after_e4, &mut StdRng::seed_from_u64(seed));
+        assert_eq!(again, Some(m));
+    }
+    assert_eq!(counts.get("a7a6"), None);
+    assert!((320..480).contains(&counts["e7e5"]), "{:?}", counts);
+    assert_eq!(counts["e7e5"] + counts["c7c5"], 600);
+}
+
+#[test]
+fn refuses_other_files() {
+    assert!(Book::from_polyglot(&[0; 15]).is_err());
+    assert!(Book::open(Path::new("tests/data/no_such_book.bin")).is_err());
+    // No entries is a book without positions, not an error
+    assert!(Book::from_polyglot(&[])
+        .unwrap()
+        .moves(&Board::new())
+        .is_empty());
+}
+
+// The bot plays from the book before searching, and searches once out of it
+#[test]
+fn search_plays_from_the_file() {
+    let limits = SearchLimits {
+        book_file: Some(Arc::new(Book::open(Path::new(TINY_BOOK)).unwrap())),
+        ..SearchLimits::depth(3)
+    };
+    let search = |board: &Board| {
+        improved_best_move_for_color(board, board.side_to_move, &limits, &[board.zobrist_key()])
+            .unwrap()
+    };
+    let result = search(&Board::new());
+    assert_eq!(result.nodes, 0);
+    assert!(["e2e4", "d2d4"].contains(&move_to_uci(result.best_move).as_str()));
+    let italian = Board::from_fen(ITALIAN).unwrap();
+    assert_eq!(move_to_uci(search(&italian).best_move), "e1g1");
+    assert!(search(&play("d2d4")).nodes > 0);
+}
+
+#[test]
+fn uci_book_file() {
+    let script = format!(
+        "setoption name BookFile value {}\nposition startpos moves e2e4\ngo depth 2\n\
+         setoption name BookFile value tests/data/no_such_book.bin\n\
+         setoption name BookFile value <empty>\nposition startpos moves e2e4 e7e5\ngo depth 1\n\
+         quit\n",
+        TINY_BOOK
+    );
+    let mut output = Vec::new();
+    uci::run(script.as_bytes(), &mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    let best: Vec<&str> = output
+        .lines()
+        .filter_map(|line| line.strip_prefix("bestmove "))
+        .collect();
+    assert_eq!(best.len(), 2, "{}", output);
+    assert!(["e7e5", "c7c5"].contains(&best[0]), "{}", output);
+    assert!(output
+        .lines()
+        .any(|line| line.starts_with("info string could not read book")));
+}
@@ -0,0 +1,78 @@
+// The player's record written to JSON and read back, as it is kept between runs
+use chess::record::{Outcome, PlayerRecord};
+
+// Wins, losses, draws and a resignation at several levels
+fn played_record() -> PlayerRecord {
+    let mut record = PlayerRecord::default();
+    for (outcome, difficulty, moves, resigned) in [
+        (Outcome::Win, 1, 24, false),
+        (Outcome::Win, 3, 31, false),
+        (Outcome::Loss, 5, 40, false),
+        (Outcome::Draw, 4, 62, false),
+        (Outcome::Loss, 6, 18, true),
+        (Outcome::Win, 2, 27, false),
+    ] {
+        record.record_game(outcome, difficulty, moves, resigned);
+    }
+    record
+}
+
+#[test]
+fn round_trip() {
+    let record = played_record();
+    assert_eq!(
+        (
+            record.games_played,
+            record.wins,
+            record.losses,
+            record.draws
+        ),
+        (6, 3, 2, 1)
+    );
+    assert_eq!(record.games_resigned, 1);
+    assert_eq!(record.longest_win_streak, 2);
+    assert_eq!(record.current_streak, 1);
+    assert_eq!(record.best_difficulty_beaten, Some(3));
+    assert_eq!(PlayerRecord::from_json(&record.to_json()), Ok(record));
+
+    // A new player, with no level beaten, and one on a losing streak
+    let record = PlayerRecord::default();
+    assert_eq!(PlayerRecord::from_json(&record.to_json()), Ok(record));
+    let mut record = PlayerRecord::default();
+    record.record_game(Outcome::Loss, 7, 35, false);
+    record.record_game(Outcome::Loss, 7, 20, true);
+    assert_eq!(record.current_streak, -2);
+    assert_eq!(PlayerRecord::from_json(&record.to_json()), Ok(record));
+}
+
+// A file cut short, as by a crash while it was written, is refused rather than read in part
+#[test]
+fn truncated_file() {
+    let json = played_record().to_json();
+    for end in 0..json.len() {
+        assert!(
+            PlayerRecord::from_json(&json[..end]).is_err(),
+            "read {:?}",
+            &json[..end]
+        );
+    }
+}
+
+#[test]
+fn malformed_file() {
+    for json in [
+        "",
+        "[]",
+        "{\"wins\" 3}",
+        "{\"wins\": three}",
+        "{\"wins\": -1}",
+        "{\"wins\": 99999999999}",
+        "not json at all",
+    ] {
+        assert!(PlayerRecord::from_json(json).is_err(), "read {:?}", json);
+    }
+    // Fields from another version are skipped, and missing ones keep their defaults
+    let record = PlayerRecord::from_json("{\"wins\": 2, \"favourite_opening\": 7}").unwrap();
+    assert_eq!(record.wins, 2);
+    assert_eq!(record.rating, PlayerRecord::default().rating);
+}
@@ -0,0 +1,146 @@
+// `Board::parse_san` over a table of inputs, each with the move it reads as, given in UCI
+// notation, or the error it gives
+use chess::engine::{Board, ChessError};
+
+const START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+// Both sides may castle either way
+const CASTLING: &str = "r3k2r/pppppppp/8/8/8/8/PPPPPPPP/R3K2R w KQkq - 0 1";
+const CASTLING_BLACK: &str = "r3k2r/pppppppp/8/8/8/8/PPPPPPPP/R3K2R b KQkq - 0 1";
+// Rooks on a1 and h1 can both reach d1, rooks on a1 and a5 both reach a3
+const ROOKS: &str = "4k3/8/8/R7/8/8/4K3/R6R w - - 0 1";
+// Queens on e4, h4 and h1 can all reach e1: each pair shares a file or a rank
+const QUEENS: &str = "4k3/8/8/8/4Q2Q/8/8/K6Q w - - 0 1";
+// Knights on b1 and f3 can both reach d2
+const KNIGHTS: &str = "4k3/8/8/8/8/5N2/8/1N2K3 w - - 0 1";
+const CAPTURES: &str = "rnbqkbnr/ppp1pppp/8/3p4/2P1P3/8/PP1P1PPP/RNBQKBNR w KQkq - 0 2";
+const EN_PASSANT: &str = "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 2";
+// The a-pawn promotes by advancing or by taking on b8
+const PROMOTION: &str = "1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1";
+// The knight is pinned to its king
+const PINNED: &str = "4r1k1/8/8/8/8/8/4N3/4K3 w - - 0 1";
+
+type Expected = Result<&'static str, fn(String) -> ChessError>;
+
+const INVALID: fn(String) -> ChessError = ChessError::InvalidNotation;
+const ILLEGAL: fn(String) -> ChessError = ChessError::IllegalMove;
+const AMBIGUOUS: fn(String) -> ChessError = ChessError::AmbiguousMove;
+
+const CASES: &[(&str, &str, Expected)] = &[
+    // Pawn pushes and piece moves
+    (START, "e4", Ok("e2e4")),
+    (START, "e3", Ok("e2e3")),
+    (START, "Nf3", Ok("g1f3")),
+    (START, "Nc3", Ok("b1c3")),
+    (START, "  d4 ", Ok("d2d4")),
+    // Long algebraic, with the square the piece leaves from
+    (START, "e2e4", Ok("e2e4")),
+    (START, "Ng1f3", Ok("g1f3")),
+    // Check, mate and annotation suffixes are ignored
+    (START, "e4+", Ok("e2e4")),
+    (START, "e4#", Ok("e2e4")),
+    (START, "Nf3!", Ok("g1f3")),
+    (START, "Nf3?", Ok("g1f3")),
+    (START, "Nf3!?", Ok("g1f3")),
+    (START, "Nf3?!", Ok("g1f3")),
+    (START, "Nf3!!", Ok("g1f3")),
+    (START, "Nf3??", Ok("g1f3")),
+    // Castling, with letters or zeros
+    (CASTLING, "O-O", Ok("e1g1")),
+    (CASTLING, "0-0", Ok("e1g1")),
+    (CASTLING, "O-O-O", Ok("e1c1")),
+    (CASTLING, "0-0-0", Ok("e1c1")),
+    (CASTLING, "O-O+", Ok("e1g1")),
+    (CASTLING, "O-O-O#", Ok("e1c1")),
+    (CASTLING_BLACK, "O-O", Ok("e8g8")),
+    (CASTLING_BLACK, "0-0-0", Ok("e8c8")),
+    (START, "O-O", Err(ILLEGAL)),
+    (START, "0-0-0", Err(ILLEGAL)),
+    (CASTLING, "o-o", Err(INVALID)),
+    // Disambiguation by file, rank or square
+    (ROOKS, "Rd1", Err(AMBIGUOUS)),
+    (ROOKS, "Rad1", Ok("a1d1")),
+    (ROOKS, "Rhd1", Ok("h1d1")),
+    (ROOKS, "Ra3", Err(AMBIGUOUS)),
+    (ROOKS, "R1a3", Ok("a1a3")),
+    (ROOKS, "R5a3", Ok("a5a3")),
+    (ROOKS, "Rb1", Err(AMBIGUOUS)),
+    (ROOKS, "Rab1", Ok("a1b1")),
+    (QUEENS, "Qe1", Err(AMBIGUOUS)),
+    (QUEENS, "Qhe1", Err(AMBIGUOUS)),
+    (QUEENS, "Q4e1", Err(AMBIGUOUS)),
+    (QUEENS, "Qh4e1", Ok("h4e1")),
+    (QUEENS, "Qee1", Ok("e4e1")),
+    (QUEENS, "Q1e1", Ok("h1e1")),
+    (KNIGHTS, "Nd2", Err(AMBIGUOUS)),
+    (KNIGHTS, "Nbd2", Ok("b1d2")),
+    (KNIGHTS, "Nfd2", Ok("f3d2")),
+    (KNIGHTS, "N1d2", Ok("b1d2")),
+    (KNIGHTS, "N3d2", Ok("f3d2")),
+    (KNIGHTS, "Nd4", Ok("f3d4")),
+    // Captures, by pawns named by their file
+    (CAPTURES, "exd5", Ok("e4d5")),
+    (CAPTURES, "cxd5", Ok("c4d5")),
+    (CAPTURES, "xd5", Err(AMBIGUOUS)),
+    (CAPTURES, "Qb3", Ok("d1b3")),
+    (EN_PASSANT, "exd6", Ok("e5d6")),
+    (EN_PASSANT, "e6", Ok("e5e6")),
+    // Promotion, with and without "="
+    (PROMOTION, "a8=Q", Ok("a7a8q")),
+    (PROMOTION, "a8Q", Ok("a7a8q")),
+    (PROMOTION, "a8=N", Ok("a7a8n")),
+    (PROMOTION, "a8N", Ok("a7a8n")),
+    (PROMOTION, "a8=R+", Ok("a7a8r")),
+    (PROMOTION, "axb8=Q+", Ok("a7b8q")),
+    (PROMOTION, "axb8B", Ok("a7b8b")),
+    // A promotion without its piece makes a queen
+    (PROMOTION, "a8", Ok("a7a8q")),
+    (PROMOTION, "a8=K", Err(INVALID)),
+    (PROMOTION, "a8=P", Err(INVALID)),
+    (PROMOTION, "a8=", Err(INVALID)),
+    (PROMOTION, "a8=QQ", Err(INVALID)),
+    // Well formed, but not legal here
+    (START, "e5", Err(ILLEGAL)),
+    (START, "Nf6", Err(ILLEGAL)),
+    (START, "Ke2", Err(ILLEGAL)),
+    (START, "Bb5", Err(ILLEGAL)),
+    (START, "exd5", Err(ILLEGAL)),
+    (START, "Nbd2", Err(ILLEGAL)),
+    (PINNED, "Nc3", Err(ILLEGAL)),
+    (PINNED, "Kd2", Ok("e1d2")),
+    // Not a move at all
+    (START, "", Err(INVALID)),
+    (START, "N", Err(INVALID)),
+    (START, "Nf", Err(INVALID)),
+    (START, "Zf3", Err(INVALID)),
+    (START, "e9", Err(INVALID)),
+    (START, "i4", Err(INVALID)),
+    (START, "e4e5e6", Err(INVALID)),
+    (START, "Nf3é", Err(INVALID)),
+];
+
+#[test]
+fn san_table() {
+    assert!(CASES.len() >= 50);
+    for &(fen, san, expected) in CASES {
+        let board = Board::from_fen(fen).unwrap();
+        let expected = match expected {
+            Ok(uci) => Ok(board.parse_uci_move(uci).unwrap()),
+            Err(error) => Err(error(san.to_string())),
+        };
+        assert_eq!(board.parse_san(san), expected, "{:?} in {}", san, fen);
+    }
+}
+
+// Every legal move reads back from the SAN `move_to_san` writes for it
+#[test]
+fn writes_what_it_reads() {
+    for fen in [
+        START, CASTLING, ROOKS, QUEENS, KNIGHTS, CAPTURES, EN_PASSANT, PROMOTION,
+    ] {
+        let board = Board::from_fen(fen).unwrap();
+        for m in board.generate_all_legal_moves(board.side_to_move) {
+            let san = board.move_to_san(m);
+            assert_eq!(board.parse_san(&san), Ok(m), "{:?} in {}", san, fen);
+        }
+    }
+}
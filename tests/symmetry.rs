@@ -0,0 +1,55 @@
+// The evaluation treats the two sides, and the two wings, alike: a position with the colours
+// swapped scores the exact negation, and one reflected left to right scores the same
+use chess::engine::{evaluate_position, Board, PositionConstraints};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+fn assert_symmetric(board: &Board) {
+    let score = evaluate_position(board);
+    let fen = board.to_fen();
+    assert_eq!(
+        score,
+        -evaluate_position(&board.color_flipped()),
+        "colours not alike in {}",
+        fen
+    );
+    assert_eq!(
+        score,
+        evaluate_position(&board.mirrored()),
+        "wings not alike in {}",
+        fen
+    );
+}
+
+#[test]
+fn hand_picked_positions() {
+    for fen in [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        // Castling rights on one side only, and for one colour only
+        "r3k2r/pppq1ppp/2np1n2/2b1p3/2B1P3/2NP1N2/PPPQ1PPP/R3K2R w Kq - 4 8",
+        "r3k3/8/8/8/8/8/8/4K2R b K - 0 1",
+        // En passant squares for either side
+        "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+        "rnbqkbnr/pppp1ppp/8/8/3Pp3/5N2/PPP1PPPP/RNBQKB1R b KQkq d3 0 3",
+        // Lopsided material, passed pawns and kings away from home
+        "8/1P4k1/8/3K4/8/8/5p2/8 w - - 0 50",
+        "2kr3r/pp3ppp/2n5/8/1b6/8/PPP2PPP/2KR1B1R b - - 1 14",
+        "4k3/8/8/8/8/8/8/RN2K1NR w - - 0 1",
+    ] {
+        assert_symmetric(&Board::from_fen(fen).unwrap());
+    }
+}
+
+#[test]
+fn random_positions() {
+    let mut rng = StdRng::seed_from_u64(336);
+    for pieces in 0..=30 {
+        for _ in 0..20 {
+            let constraints = PositionConstraints {
+                pieces,
+                ..PositionConstraints::default()
+            };
+            assert_symmetric(&Board::random_position(&mut rng, &constraints));
+        }
+    }
+}
@@ -0,0 +1,70 @@
+// The root moves searched side by side give the same move and score as searched in turn, and
+// a search repeated gives the same move and score again; only the node counts may differ
+use chess::engine::{
+    improved_best_move_for_color, move_to_uci, Board, SearchLimits, SearchResult, MATE_VALUE,
+};
+
+fn search(fen: &str, depth: u32, threads: usize) -> SearchResult {
+    let board = Board::from_fen(fen).unwrap();
+    let limits = SearchLimits {
+        threads,
+        ..SearchLimits::depth(depth)
+    };
+    improved_best_move_for_color(&board, board.side_to_move, &limits, &[board.zobrist_key()])
+        .unwrap()
+}
+
+fn assert_same(fen: &str, depth: u32, threads: [usize; 2]) -> SearchResult {
+    let first = search(fen, depth, threads[0]);
+    let second = search(fen, depth, threads[1]);
+    assert_eq!(
+        (move_to_uci(first.best_move), first.score),
+        (move_to_uci(second.best_move), second.score),
+        "{} threads against {} in {}",
+        threads[0],
+        threads[1],
+        fen
+    );
+    first
+}
+
+#[test]
+fn repeated_searches_agree() {
+    for fen in [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4",
+        "8/5pk1/6p1/8/3R4/6P1/5PK1/r7 b - - 0 40",
+    ] {
+        assert_same(fen, 4, [1, 1]);
+        assert_same(fen, 4, [2, 2]);
+    }
+}
+
+#[test]
+fn thread_counts_agree_on_tactics() {
+    for (fen, best) in [
+        // Back-rank mate
+        ("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", "a1a8"),
+        // Scholar's mate
+        (
+            "r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4",
+            "h5f7",
+        ),
+        // A knight fork of king and rook
+        ("r3k3/8/8/1N6/8/8/8/4K3 w - - 0 1", "b5c7"),
+    ] {
+        for threads in [2, 4] {
+            let result = assert_same(fen, 4, [1, threads]);
+            assert_eq!(move_to_uci(result.best_move), best, "{}", fen);
+        }
+    }
+}
+
+// Qd8+ Bxd8 Re8#: the queen sacrifice is the only mate in two
+#[test]
+fn thread_counts_agree_on_mate_in_two() {
+    let fen = "r1b2k1r/ppp1bppp/8/1B1Q4/5q2/2P5/PPP2PPP/R3R1K1 w - - 1 1";
+    let result = assert_same(fen, 4, [1, 2]);
+    assert_eq!(move_to_uci(result.best_move), "d5d8");
+    assert_eq!(result.score, MATE_VALUE - 3);
+}
@@ -0,0 +1,135 @@
+// Scripted exchanges with the UCI front end, as a GUI would send them
+use chess::engine::Board;
+use chess::uci;
+
+// Runs the engine over `script`, returning what it sent back a line at a time
+fn exchange(script: &str) -> Vec<String> {
+    let mut output = Vec::new();
+    uci::run(script.as_bytes(), &mut output).unwrap();
+    String::from_utf8(output)
+        .unwrap()
+        .lines()
+        .map(str::to_string)
+        .collect()
+}
+
+fn best_moves(lines: &[String]) -> Vec<&str> {
+    lines
+        .iter()
+        .filter_map(|line| line.strip_prefix("bestmove "))
+        .collect()
+}
+
+#[test]
+fn handshake() {
+    let lines = exchange("uci\nisready\nquit\n");
+    assert!(lines[0].starts_with("id name"));
+    assert!(lines
+        .iter()
+        .any(|line| line.starts_with("option name Hash")));
+    let uciok = lines.iter().position(|line| line == "uciok").unwrap();
+    let readyok = lines.iter().position(|line| line == "readyok").unwrap();
+    assert!(uciok < readyok);
+}
+
+// A fixed-depth search runs to the end before the next `go`, and an infinite one answers only
+// once stopped
+#[test]
+fn searches_then_stops() {
+    let lines = exchange(
+        "uci\nisready\nucinewgame\nposition startpos moves e2e4 e7e5\ngo depth 3\n\
+         go infinite\nstop\nquit\n",
+    );
+    assert!(lines.contains(&"uciok".to_string()));
+    assert!(lines.contains(&"readyok".to_string()));
+    assert!(lines.iter().any(|line| line.starts_with("info depth 3 ")));
+    let mut board = Board::new();
+    for m in ["e2e4", "e7e5"] {
+        board.apply_move(board.parse_uci_move(m).unwrap());
+    }
+    let best_moves = best_moves(&lines);
+    assert_eq!(best_moves.len(), 2);
+    for m in best_moves {
+        assert!(board.parse_uci_move(m).is_ok(), "illegal bestmove {}", m);
+    }
+    // The first search reports its iterations before it answers
+    let first_best = lines
+        .iter()
+        .position(|line| line.starts_with("bestmove"))
+        .unwrap();
+    assert!(lines[..first_best]
+        .iter()
+        .any(|line| line.starts_with("info depth 1 ")));
+}
+
+#[test]
+fn reports_mate_for_the_side_to_move() {
+    let lines = exchange("position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1\ngo depth 3\n");
+    assert!(lines.iter().any(|line| line.contains("score mate 1")));
+    assert_eq!(best_moves(&lines), ["a1a8"]);
+}
+
+#[test]
+fn reports_bad_positions() {
+    let lines = exchange("position fen not-a-fen\nposition startpos moves e2e5\nquit\n");
+    assert_eq!(lines.len(), 2);
+    assert!(lines.iter().all(|line| line.starts_with("info string")));
+}
+
+// Every option a GUI may set is listed before `uciok`
+#[test]
+fn lists_options() {
+    let lines = exchange("uci\nquit\n");
+    let uciok = lines.iter().position(|line| line == "uciok").unwrap();
+    for name in ["Hash", "Threads", "Contempt", "OwnBook"] {
+        let option = format!("option name {} type ", name);
+        assert!(
+            lines[..uciok].iter().any(|line| line.starts_with(&option)),
+            "no {} in {:?}",
+            name,
+            lines
+        );
+    }
+}
+
+#[test]
+fn replies_to_e4() {
+    let lines = exchange("position startpos moves e2e4\ngo depth 3\nquit\n");
+    let best_moves = best_moves(&lines);
+    assert_eq!(best_moves.len(), 1);
+    let mut board = Board::new();
+    board.apply_move(board.parse_uci_move("e2e4").unwrap());
+    let reply = board.parse_uci_move(best_moves[0]).unwrap();
+    assert!(board
+        .generate_all_legal_moves(board.side_to_move)
+        .contains(&reply));
+}
+
+// `stop` ends an infinite search with its answer, before anything sent after it is answered
+#[test]
+fn stop_answers_infinite_search() {
+    let lines = exchange("position startpos\ngo infinite\nstop\nisready\nquit\n");
+    assert_eq!(best_moves(&lines).len(), 1);
+    assert!(Board::new().parse_uci_move(best_moves(&lines)[0]).is_ok());
+    let best = lines
+        .iter()
+        .position(|line| line.starts_with("bestmove"))
+        .unwrap();
+    let readyok = lines.iter().position(|line| line == "readyok").unwrap();
+    assert!(best < readyok);
+}
+
+#[test]
+fn reports_bad_options() {
+    let lines = exchange(
+        "setoption name Hash value lots\nsetoption name Threads value -2\n\
+         setoption name OwnBook value maybe\nsetoption name Ponder value true\n\
+         setoption Hash 16\nsetoption name hash value 16\nsetoption name Contempt value -20\n\
+         quit\n",
+    );
+    // The last two are valid, names being matched ignoring case
+    assert_eq!(lines.len(), 5, "{:?}", lines);
+    assert!(lines.iter().all(|line| line.starts_with("info string")));
+    assert_eq!(lines[0], "info string invalid value 'lots' for Hash");
+    assert_eq!(lines[3], "info string unknown option 'Ponder'");
+}
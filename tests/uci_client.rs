@@ -0,0 +1,97 @@
+// `UciEngine` against fake engines: shell scripts that answer the handshake and reply to `go`
+// with a move of their own choosing, or not at all
+#![cfg(unix)]
+use chess::engine::uci_client::{UciClientError, UciEngine};
+use chess::engine::{move_to_uci, Board, SearchResult};
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+use std::time::{Duration, Instant};
+
+// Writes an engine that handshakes as "Fake" and answers `go` with `on_go`, shell commands
+// printing its reply
+fn fake_engine(name: &str, on_go: &str) -> PathBuf {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(format!("fake_{}.sh", name));
+    let script = format!(
+        "#!/bin/sh\n\
+         while read -r command rest; do\n\
+         \tcase \"$command\" in\n\
+         \t\tuci) echo 'id name Fake'; echo 'option name Hash type spin default 16'; \
+         echo 'uciok' ;;\n\
+         \t\tisready) echo 'readyok' ;;\n\
+         \t\tgo) {} ;;\n\
+         \t\tquit) exit 0 ;;\n\
+         \tesac\n\
+         done\n",
+        on_go
+    );
+    std::fs::write(&path, script).unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    path
+}
+
+fn ask(engine: &mut UciEngine) -> Result<SearchResult, UciClientError> {
+    engine.best_move(
+        &Board::new(),
+        &[],
+        Duration::from_millis(50),
+        &AtomicBool::new(false),
+    )
+}
+
+#[test]
+fn plays_the_engine_move() {
+    let path = fake_engine(
+        "e2e4",
+        "echo 'info depth 7 score cp 31 nodes 1234 pv e2e4 e7e5'; echo 'bestmove e2e4'",
+    );
+    let mut engine = UciEngine::spawn(&path).unwrap();
+    assert_eq!(engine.name(), Some("Fake"));
+    assert_eq!(engine.path(), path);
+    let result = ask(&mut engine).unwrap();
+    assert_eq!(move_to_uci(result.best_move), "e2e4");
+    assert_eq!((result.depth, result.score, result.nodes), (7, 31, 1234));
+    let pv: Vec<String> = result.pv.iter().map(|&m| move_to_uci(m)).collect();
+    assert_eq!(pv, ["e2e4", "e7e5"]);
+    // And again, the engine staying up between moves
+    assert_eq!(move_to_uci(ask(&mut engine).unwrap().best_move), "e2e4");
+}
+
+#[test]
+fn illegal_move() {
+    let path = fake_engine("e2e5", "echo 'bestmove e2e5'");
+    let mut engine = UciEngine::spawn(&path).unwrap();
+    assert!(matches!(
+        ask(&mut engine),
+        Err(UciClientError::IllegalMove(text)) if text == "e2e5"
+    ));
+}
+
+// An engine that never answers `go` is given its move time and a grace period, then reported
+#[test]
+fn timeout() {
+    let path = fake_engine("silent", ":");
+    let mut engine = UciEngine::spawn(&path).unwrap();
+    let start = Instant::now();
+    assert!(matches!(
+        ask(&mut engine),
+        Err(UciClientError::Timeout("bestmove"))
+    ));
+    assert!(start.elapsed() < Duration::from_secs(30));
+}
+
+#[test]
+fn exits() {
+    let path = fake_engine("exits", "exit 0");
+    let mut engine = UciEngine::spawn(&path).unwrap();
+    assert!(matches!(ask(&mut engine), Err(UciClientError::Exited)));
+}
+
+#[test]
+fn missing_executable() {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("no_such_engine");
+    assert!(matches!(
+        UciEngine::spawn(&path),
+        Err(UciClientError::Spawn(_))
+    ));
+}
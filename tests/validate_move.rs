@@ -0,0 +1,126 @@
+// Every reason `Board::validate_move` gives for refusing a move, each from a position where it
+// is the only thing wrong
+use chess::engine::{algebraic_to_square, Board, MoveError};
+
+const START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+fn validate(fen: &str, from: &str, to: &str) -> Result<(), MoveError> {
+    let board = Board::from_fen(fen).unwrap();
+    board.validate_move(
+        algebraic_to_square(from).unwrap(),
+        algebraic_to_square(to).unwrap(),
+    )
+}
+
+#[test]
+fn legal_moves() {
+    assert_eq!(validate(START, "e2", "e4"), Ok(()));
+    assert_eq!(validate(START, "g1", "f3"), Ok(()));
+    assert_eq!(
+        validate("4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1", "e1", "c1"),
+        Ok(())
+    );
+    // En passant, onto an empty square
+    assert_eq!(
+        validate("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 2", "e5", "d6"),
+        Ok(())
+    );
+}
+
+#[test]
+fn out_of_bounds() {
+    let board = Board::from_fen(START).unwrap();
+    assert_eq!(
+        board.validate_move((1, 4), (8, 4)),
+        Err(MoveError::OutOfBounds)
+    );
+    assert_eq!(
+        board.validate_move((1, 9), (3, 4)),
+        Err(MoveError::OutOfBounds)
+    );
+}
+
+#[test]
+fn no_piece_at_source() {
+    assert_eq!(validate(START, "e3", "e4"), Err(MoveError::NoPieceAtSource));
+}
+
+#[test]
+fn wrong_color() {
+    assert_eq!(validate(START, "e7", "e5"), Err(MoveError::WrongColor));
+}
+
+#[test]
+fn own_piece_on_target() {
+    assert_eq!(
+        validate(START, "d1", "d2"),
+        Err(MoveError::OwnPieceOnTarget)
+    );
+}
+
+#[test]
+fn not_a_piece_move() {
+    assert_eq!(validate(START, "b1", "b3"), Err(MoveError::NotAPieceMove));
+    // A pawn only moves diagonally to capture
+    assert_eq!(validate(START, "e2", "d3"), Err(MoveError::NotAPieceMove));
+}
+
+#[test]
+fn blocked_path() {
+    assert_eq!(validate(START, "a1", "a3"), Err(MoveError::BlockedPath));
+    // A pawn cannot push onto a piece
+    assert_eq!(
+        validate(
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2",
+            "e4",
+            "e5"
+        ),
+        Err(MoveError::BlockedPath)
+    );
+    // Nor castle with a piece between the King and Rook
+    assert_eq!(
+        validate("4k3/8/8/8/8/8/8/RN2K2R w KQ - 0 1", "e1", "c1"),
+        Err(MoveError::BlockedPath)
+    );
+}
+
+#[test]
+fn no_castling_rights() {
+    assert_eq!(
+        validate("4k3/8/8/8/8/8/8/R3K2R w Q - 0 1", "e1", "g1"),
+        Err(MoveError::NoCastlingRights)
+    );
+}
+
+#[test]
+fn castle_through_check() {
+    // Through f1, attacked by the Rook on f8
+    assert_eq!(
+        validate("4kr2/8/8/8/8/8/8/R3K2R w KQ - 0 1", "e1", "g1"),
+        Err(MoveError::CastleThroughCheck)
+    );
+    // Into check on g1
+    assert_eq!(
+        validate("4k1r1/8/8/8/8/8/8/R3K2R w KQ - 0 1", "e1", "g1"),
+        Err(MoveError::CastleThroughCheck)
+    );
+    // Out of check
+    assert_eq!(
+        validate("4r1k1/8/8/8/8/8/8/R3K2R w KQ - 0 1", "e1", "c1"),
+        Err(MoveError::CastleThroughCheck)
+    );
+}
+
+#[test]
+fn leaves_king_in_check() {
+    // The Knight is pinned to its King by the Rook on e8
+    assert_eq!(
+        validate("4r1k1/8/8/8/8/8/4N3/4K3 w - - 0 1", "e2", "c3"),
+        Err(MoveError::LeavesKingInCheck)
+    );
+    // The King steps onto a square the Rook attacks
+    assert_eq!(
+        validate("3r2k1/8/8/8/8/8/8/4K3 w - - 0 1", "e1", "d2"),
+        Err(MoveError::LeavesKingInCheck)
+    );
+}
@@ -0,0 +1,73 @@
+// Scripted exchanges with the XBoard front end, as WinBoard would send them
+use chess::engine::Board;
+use chess::xboard;
+
+fn exchange(script: &str) -> Vec<String> {
+    let mut output = Vec::new();
+    xboard::run(script.as_bytes(), &mut output).unwrap();
+    String::from_utf8(output)
+        .unwrap()
+        .lines()
+        .map(str::to_string)
+        .collect()
+}
+
+fn engine_moves(lines: &[String]) -> Vec<&str> {
+    lines
+        .iter()
+        .filter_map(|line| line.strip_prefix("move "))
+        .collect()
+}
+
+#[test]
+fn plays_both_sides() {
+    let lines = exchange("xboard\nprotover 2\nnew\nsd 3\nusermove e2e4\ngo\nquit\n");
+    // The features come as one block, the GUI waiting from done=0 to done=1
+    let features: Vec<&String> = lines
+        .iter()
+        .filter(|line| line.starts_with("feature "))
+        .collect();
+    assert_eq!(features.first().unwrap().as_str(), "feature done=0");
+    assert_eq!(features.last().unwrap().as_str(), "feature done=1");
+    assert!(features
+        .iter()
+        .any(|line| line.contains("usermove=1") && line.contains("myname=\"chess-rust\"")));
+
+    // The engine answers e4 as Black, then `go` has it take over White
+    let moves = engine_moves(&lines);
+    assert_eq!(moves.len(), 2, "{:?}", lines);
+    let mut board = Board::new();
+    for text in ["e2e4", moves[0], moves[1]] {
+        let m = board.parse_uci_move(text).unwrap();
+        board.apply_move(m);
+    }
+    assert!(!lines.iter().any(|line| line.starts_with("Error")));
+}
+
+#[test]
+fn refuses_bad_input() {
+    let lines = exchange("new\nforce\nusermove e2e5\nsd deep\nping 7\nfrobnicate\nquit\n");
+    assert_eq!(
+        lines,
+        [
+            "Illegal move: e2e5",
+            "Error (bad depth): deep",
+            "pong 7",
+            "Error (unknown command): frobnicate",
+        ]
+    );
+}
+
+// In force mode moves are only relayed, bare ones too, until `go`
+#[test]
+fn force_mode() {
+    let lines = exchange("new\nforce\ne2e4\ne7e5\nsd 2\ngo\nquit\n");
+    let moves = engine_moves(&lines);
+    assert_eq!(moves.len(), 1, "{:?}", lines);
+    let mut board = Board::new();
+    for text in ["e2e4", "e7e5"] {
+        let m = board.parse_uci_move(text).unwrap();
+        board.apply_move(m);
+    }
+    assert!(board.parse_uci_move(moves[0]).is_ok());
+}